@@ -1,12 +1,76 @@
 use clap::{App, AppSettings, Arg};
 use simulator::exp_setup::exp_parser::ExperimentArgs;
-use simulator::{exp_setup, world, Directories};
+use simulator::world::{SimError, SimSummary};
+use simulator::{exp_setup, hardio, with_nverts, world, Directories};
 use std::convert::TryFrom;
 use std::env::current_dir;
 use std::path::PathBuf;
 use std::time::Instant;
 
 pub const EXP_DIR: &str = "B:\\rust-ncc\\experiments\\";
+/// Number of states held in memory before they are written out, when
+/// resuming a simulation.
+pub const RESUME_MAX_ON_RAM: usize = 1000;
+
+fn report(result: Result<SimSummary, SimError>, start: Instant) {
+    match result {
+        Ok(summary) => println!(
+            "Simulation complete ({}). {} s.",
+            summary.reason,
+            start.elapsed().as_secs()
+        ),
+        Err(e) => eprintln!(
+            "Simulation failed after {} s: {}",
+            start.elapsed().as_secs(),
+            e
+        ),
+    }
+}
+
+/// Resume the simulation saved in `binc_path`, whose cells have `N`
+/// vertices.
+fn resume<const N: usize>(binc_path: PathBuf) {
+    let (info, snapshot) = hardio::load_last_snapshot::<N>(
+        &binc_path,
+    )
+    .unwrap_or_else(|e| panic!("{}: {}", binc_path.display(), e));
+    let out_dir =
+        binc_path.parent().map_or_else(PathBuf::new, PathBuf::from);
+    let name = binc_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap()
+        .to_string();
+    let mut w = world::World::from_snapshot(
+        info,
+        snapshot,
+        out_dir,
+        name,
+        RESUME_MAX_ON_RAM,
+    );
+
+    let now = Instant::now();
+    report(w.simulate(true), now);
+}
+
+/// Run the experiments described by `exp_args`, whose cells have `N`
+/// vertices.
+fn run<const N: usize>(
+    directories: Directories,
+    exp_args: ExperimentArgs,
+) {
+    let exps = exp_setup::generate::<N>(directories, exp_args)
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    for exp in exps {
+        let mut w = world::World::new(exp);
+
+        let now = Instant::now();
+        // A failed simulation is reported, but does not stop the
+        // remaining simulations from running.
+        report(w.simulate(true), now);
+    }
+}
 
 fn main() {
     let parsed_args = App::new("simulator executor")
@@ -22,14 +86,32 @@ fn main() {
             Arg::with_name("experiments")
                 .short("e")
                 .long("exps")
-                .required(true)
+                .required_unless("resume")
                 .takes_value(true)
                 .multiple(true)
                 .min_values(1),
         )
+        .arg(
+            Arg::with_name("resume")
+                .short("r")
+                .long("resume")
+                .takes_value(true),
+        )
         .setting(AppSettings::TrailingVarArg)
         .get_matches();
 
+    if let Some(resume_path) = parsed_args.value_of("resume") {
+        let binc_path = PathBuf::from(resume_path);
+        let nverts =
+            hardio::read_nverts(&binc_path).unwrap_or_else(|e| {
+                panic!("{}: {}", binc_path.display(), e)
+            });
+        with_nverts!(nverts, resume(binc_path)).unwrap_or_else(|n| {
+            panic!("unsupported number of vertices per cell: {}", n)
+        });
+        return;
+    }
+
     let default_cfg_path: PathBuf =
         [current_dir().unwrap(), PathBuf::from("cfg")]
             .iter()
@@ -60,18 +142,12 @@ fn main() {
     }
 
     for exp_args in exp_json_args {
-        let exps = exp_setup::generate(directories.clone(), exp_args);
-
-        for exp in exps {
-            let mut w = world::World::new(exp);
-
-            let now = Instant::now();
-            w.simulate(true);
-
-            println!(
-                "Simulation complete. {} s.",
-                now.elapsed().as_secs()
-            );
-        }
+        // Experiment arguments are checked to have a supported number
+        // of vertices when they are parsed.
+        with_nverts!(
+            exp_args.nverts,
+            run(directories.clone(), exp_args)
+        )
+        .unwrap();
     }
 }
@@ -8,8 +8,13 @@ pub fn derive(
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
     let DeriveInput {
-        ident: id, data, ..
+        ident: id,
+        generics,
+        data,
+        ..
     } = parse_macro_input!(input as DeriveInput);
+    let (impl_generics, ty_generics, where_clause) =
+        generics.split_for_impl();
 
     let mut fids: Vec<Ident> = vec![];
     let mut ftys: Vec<Type> = vec![];
@@ -44,7 +49,7 @@ pub fn derive(
     );
 
     let expanded = quote! {
-        impl #id {
+        impl #impl_generics #id #ty_generics #where_clause {
             #(#methods)*
         }
     };
@@ -51,7 +51,7 @@ fn main() {
             },
         },
     };
-    let exp = generate(directories, exp_args)[0].clone();
+    let exp = generate(directories, exp_args).unwrap()[0].clone();
     let mut w = world::World::new(exp);
 
     let now = Instant::now();
@@ -12,36 +12,35 @@ use crate::parameters::Parameters;
 use crate::utils::normal::NormalDistrib;
 use crate::utils::pcg32::Pcg32;
 use crate::utils::{circ_ix_minus, circ_ix_plus};
-use crate::NVERTS;
 use rand::seq::SliceRandom;
 use rand_distr::{Distribution, Uniform};
 use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
 use std::fmt;
 use std::fmt::{Debug, Display};
 
 pub mod distrib_gens {
     use crate::utils::pcg32::Pcg32;
-    use crate::NVERTS;
     use rand::distributions::Uniform;
     use rand::Rng;
 
     /// [1.0, 10.0, 4.0, 5.0] (consider the relative fraction of Rho GTPase), sum = 20.0,,
     /// go thru each and divide by 20 = [0.05, 0.5, 0.2, 0.25]
     /// if we now sum up everything in the new array, sum = 1.0
-    fn scaled_unitize(
+    fn scaled_unitize<const N: usize>(
         frac: f64,
-        mut distrib: [f64; NVERTS as usize],
-    ) -> [f64; NVERTS as usize] {
+        mut distrib: [f64; N],
+    ) -> [f64; N] {
         let sum: f64 = distrib.iter().sum();
         distrib.iter_mut().for_each(|e| *e = *e * frac / sum);
         distrib
     }
 
-    pub fn random(
+    pub fn random<const N: usize>(
         rng: &mut Pcg32,
         frac: f64,
-    ) -> [f64; NVERTS as usize] {
-        let mut r = [0.0; NVERTS as usize];
+    ) -> [f64; N] {
+        let mut r = [0.0; N];
         let prob_distrib: Uniform<f64> =
             Uniform::new_inclusive(0.0, 1.0);
         r.iter_mut().for_each(|e| {
@@ -50,12 +49,12 @@ pub mod distrib_gens {
         scaled_unitize(frac, r)
     }
 
-    pub fn specific_uniform(
+    pub fn specific_uniform<const N: usize>(
         frac: f64,
-        marked_verts: [bool; NVERTS as usize],
-    ) -> [f64; NVERTS as usize] {
+        marked_verts: [bool; N],
+    ) -> [f64; N] {
         //println!("marking in gen_specific: {:?}", &marked_verts);
-        let mut r = [0.0; NVERTS as usize];
+        let mut r = [0.0; N];
         marked_verts.iter().zip(r.iter_mut()).for_each(
             |(&marked, e)| {
                 if marked {
@@ -66,13 +65,13 @@ pub mod distrib_gens {
         scaled_unitize(frac, r)
     }
 
-    pub fn specific_random(
+    pub fn specific_random<const N: usize>(
         rng: &mut Pcg32,
         frac: f64,
-        marked_verts: [bool; NVERTS as usize],
-    ) -> [f64; NVERTS as usize] {
+        marked_verts: [bool; N],
+    ) -> [f64; N] {
         //println!("marking in gen_specific: {:?}", &marked_verts);
-        let mut r = [0.0; NVERTS as usize];
+        let mut r = [0.0; N];
         let prob_distrib: Uniform<f64> =
             Uniform::new_inclusive(0.0, 1.0);
         marked_verts.iter().zip(r.iter_mut()).for_each(
@@ -86,19 +85,19 @@ pub mod distrib_gens {
     }
 }
 
-#[derive(
-    Clone, Copy, Deserialize, Serialize, Default, Debug, PartialEq,
-)]
-pub struct RgtpDistribution {
-    pub active: [f64; NVERTS],
-    pub inactive: [f64; NVERTS],
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq)]
+pub struct RgtpDistribution<const N: usize> {
+    #[serde(with = "BigArray")]
+    pub active: [f64; N],
+    #[serde(with = "BigArray")]
+    pub inactive: [f64; N],
 }
 
-impl RgtpDistribution {
+impl<const N: usize> RgtpDistribution<N> {
     pub fn new(
-        active: [f64; NVERTS],
-        inactive: [f64; NVERTS],
-    ) -> RgtpDistribution {
+        active: [f64; N],
+        inactive: [f64; N],
+    ) -> RgtpDistribution<N> {
         if active.iter().sum::<f64>() + inactive.iter().sum::<f64>()
             > 1.0
         {
@@ -109,67 +108,84 @@ impl RgtpDistribution {
     }
 }
 
-fn calc_directed_fluxes(
-    edge_lens: &[f64; NVERTS],
+impl<const N: usize> Default for RgtpDistribution<N> {
+    fn default() -> Self {
+        RgtpDistribution {
+            active: [0.0; N],
+            inactive: [0.0; N],
+        }
+    }
+}
+
+fn calc_directed_fluxes<const N: usize>(
+    edge_lens: &[f64; N],
     rgtp_d: f64,
-    conc_rgtps: &[f64; NVERTS],
-) -> [f64; NVERTS] {
-    let mut r = [0.0_f64; NVERTS];
-    for i in 0..NVERTS {
-        let plus_i = circ_ix_plus(i, NVERTS);
+    conc_rgtps: &[f64; N],
+) -> [f64; N] {
+    let mut r = [0.0_f64; N];
+    for i in 0..N {
+        let plus_i = circ_ix_plus(i, N);
         r[i] = -1.0 * rgtp_d * (conc_rgtps[plus_i] - conc_rgtps[i])
             / edge_lens[i];
     }
     r
 }
 
-pub fn calc_net_fluxes(
-    edge_lens: &[f64; NVERTS],
+pub fn calc_net_fluxes<const N: usize>(
+    edge_lens: &[f64; N],
     rgtp_d: f64,
-    conc_rgtps: &[f64; NVERTS],
-) -> [f64; NVERTS] {
+    conc_rgtps: &[f64; N],
+) -> [f64; N] {
     let directed_fluxes =
         calc_directed_fluxes(edge_lens, rgtp_d, conc_rgtps);
-    let mut r = [0.0_f64; NVERTS];
-    (0..NVERTS).for_each(|i| {
-        let min_i = circ_ix_minus(i, NVERTS);
+    let mut r = [0.0_f64; N];
+    (0..N).for_each(|i| {
+        let min_i = circ_ix_minus(i, N);
         r[i] = directed_fluxes[min_i] - directed_fluxes[i];
     });
     r
 }
 
 /// Calculate approximate concentration of a Rho GTPase at a vertex.
-pub fn calc_conc_rgtps(
-    avg_edge_lens: &[f64; NVERTS],
-    rgtps: &[f64; NVERTS],
-) -> [f64; NVERTS] {
-    let mut r = [0.0_f64; NVERTS];
-    (0..NVERTS).for_each(|i| r[i] = rgtps[i] / avg_edge_lens[i]);
+pub fn calc_conc_rgtps<const N: usize>(
+    avg_edge_lens: &[f64; N],
+    rgtps: &[f64; N],
+) -> [f64; N] {
+    let mut r = [0.0_f64; N];
+    (0..N).for_each(|i| r[i] = rgtps[i] / avg_edge_lens[i]);
     r
 }
 
 /// Calculates Rac1 activation rates, as discussed in SI.
-pub fn calc_kgtps_rac(
-    rac_acts: &[f64; NVERTS],
-    conc_rac_acts: &[f64; NVERTS],
-    x_rands: &[f64; NVERTS],
-    x_coas: &[f64; NVERTS],
-    x_cils: &[f64; NVERTS],
-    x_chemos: &[f64; NVERTS],
-    x_cals: &[f64; NVERTS],
+/// `x_src_acts` and `x_src_autos` are the factors of interaction
+/// sources which increase base and auto activation respectively.
+pub fn calc_kgtps_rac<const N: usize>(
+    rac_acts: &[f64; N],
+    conc_rac_acts: &[f64; N],
+    x_rands: &[f64; N],
+    x_coas: &[f64; N],
+    x_cils: &[f64; N],
+    x_chemos: &[f64; N],
+    x_cals: &[f64; N],
+    x_src_acts: &[f64; N],
+    x_src_autos: &[f64; N],
     kgtp_rac_base: f64,
     kgtp_rac_auto: f64,
     halfmax_rac_conc: f64,
-) -> [f64; NVERTS] {
+) -> [f64; N] {
     let nvs = rac_acts.len();
-    let mut kgtps_rac = [0.0_f64; NVERTS];
+    let mut kgtps_rac = [0.0_f64; N];
 
     for i in 0..nvs {
         // Base activation rate of Rac1 is increased (not multiplied!)
         // by: CAL, randomization, and co-attraction.
         let this_x_coa =
             if x_cils[i] > 0.0 { 0.0 } else { x_coas[i] };
-        let base = (x_cals[i] + x_rands[i] + this_x_coa + 1.0)
+        let base = (x_cals[i]
+            + x_rands[i]
+            + this_x_coa
+            + x_src_acts[i]
+            + 1.0)
             * kgtp_rac_base;
         // Auto activation rate of Rac1 is increased by
         // chemoattraction only. This is because we assume that Sdf1
@@ -178,7 +194,7 @@ pub fn calc_kgtps_rac(
         let auto_factor = {
             let af =
                 hill_function3(halfmax_rac_conc, conc_rac_acts[i])
-                    * (1.0 + x_chemos[i]);
+                    * (1.0 + x_chemos[i] + x_src_autos[i]);
             // This comes from the Python code. It's necessary for
             // auto-activation to not blow up, but it also kind of
             // changes the shape of the sigmoid. Is this recorded
@@ -196,25 +212,28 @@ pub fn calc_kgtps_rac(
 }
 
 /// Calculates Rac1 inactivation rates, as discussed in SI.
-pub fn calc_kdgtps_rac(
-    rac_acts: &[f64; NVERTS],
-    conc_rho_acts: &[f64; NVERTS],
-    x_cils: &[f64; NVERTS],
+/// `x_srcs` are the factors of interaction sources which increase
+/// inactivation.
+pub fn calc_kdgtps_rac<const N: usize>(
+    rac_acts: &[f64; N],
+    conc_rho_acts: &[f64; N],
+    x_cils: &[f64; N],
+    x_srcs: &[f64; N],
     x_tens: f64,
     kdgtp_rac_base: f64,
     kdgtp_rho_on_rac: f64,
     halfmax_conc_rho: f64,
-) -> [f64; NVERTS] {
+) -> [f64; N] {
     let nvs = rac_acts.len();
-    let mut kdgtps_rac = [0.0_f64; NVERTS];
+    let mut kdgtps_rac = [0.0_f64; N];
 
     for i in 0..nvs {
         // Baseline is affected by tension inhibition, and CIL.
         let cil = (x_cils[i]
-            + x_cils[circ_ix_plus(i, NVERTS)]
-            + x_cils[circ_ix_minus(i, NVERTS)])
+            + x_cils[circ_ix_plus(i, N)]
+            + x_cils[circ_ix_minus(i, N)])
             / 3.0;
-        let base = (1.0 + x_tens + cil) * kdgtp_rac_base;
+        let base = (1.0 + x_tens + cil + x_srcs[i]) * kdgtp_rac_base;
         // Effect of RhoA on Rac1, related to activity of RhoA at a
         // vertex.
         let mutual =
@@ -226,24 +245,27 @@ pub fn calc_kdgtps_rac(
     kdgtps_rac
 }
 
-/// Calculates RhoA activation rates, as discussed in SI.
-pub fn calc_kgtps_rho(
-    rho_acts: &[f64; NVERTS],
-    conc_rho_acts: &[f64; NVERTS],
-    x_cils: &[f64; NVERTS],
+/// Calculates RhoA activation rates, as discussed in SI. `x_srcs`
+/// are the factors of interaction sources which increase activation.
+pub fn calc_kgtps_rho<const N: usize>(
+    rho_acts: &[f64; N],
+    conc_rho_acts: &[f64; N],
+    x_cils: &[f64; N],
+    x_srcs: &[f64; N],
     kgtp_rho_base: f64,
     halfmax_rho_thresh: f64,
     kgtp_rho_auto: f64,
-) -> [f64; NVERTS] {
+) -> [f64; N] {
     let nvs = rho_acts.len();
-    let mut kgtps_rho = [0.0_f64; NVERTS];
+    let mut kgtps_rho = [0.0_f64; N];
 
     for i in 0..nvs {
         let base = (1.0
             + (x_cils[i]
-                + x_cils[circ_ix_minus(i, NVERTS)]
-                + x_cils[circ_ix_plus(i, NVERTS)])
-                / 3.0)
+                + x_cils[circ_ix_minus(i, N)]
+                + x_cils[circ_ix_plus(i, N)])
+                / 3.0
+            + x_srcs[i])
             * kgtp_rho_base;
         let auto =
             hill_function3(halfmax_rho_thresh, conc_rho_acts[i])
@@ -254,51 +276,55 @@ pub fn calc_kgtps_rho(
     kgtps_rho
 }
 
-/// Calculates RhoA inactivation rates, as discussed in SI.
-pub fn calc_kdgtps_rho(
-    rho_acts: &[f64; NVERTS],
-    conc_rac_acts: &[f64; NVERTS],
+/// Calculates RhoA inactivation rates, as discussed in SI. `x_srcs`
+/// are the factors of interaction sources which increase
+/// inactivation.
+pub fn calc_kdgtps_rho<const N: usize>(
+    rho_acts: &[f64; N],
+    conc_rac_acts: &[f64; N],
+    x_srcs: &[f64; N],
     kdgtp_rho_base: f64,
     kdgtp_rac_on_rho: f64,
     halfmax_conc_rac: f64,
-) -> [f64; NVERTS] {
+) -> [f64; N] {
     let nvs = rho_acts.len();
-    let mut kdgtps_rho = [0.0_f64; NVERTS];
+    let mut kdgtps_rho = [0.0_f64; N];
 
     for i in 0..nvs {
         let mutual =
             hill_function3(halfmax_conc_rac, conc_rac_acts[i])
                 * kdgtp_rac_on_rho;
-        kdgtps_rho[i] = kdgtp_rho_base + mutual;
+        kdgtps_rho[i] = (1.0 + x_srcs[i]) * kdgtp_rho_base + mutual;
     }
 
     kdgtps_rho
 }
 
 #[derive(Copy, Clone, Deserialize, Serialize, PartialEq)]
-pub struct RacRandState {
+pub struct RacRandState<const N: usize> {
     pub enabled: bool,
     /// When does the next update occur?
     pub next_update: f64,
     /// Rac1 randomization factors per vertex.
-    pub x_rands: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub x_rands: [f64; N],
     distrib: NormalDistrib,
 }
 
-impl Debug for RacRandState {
+impl<const N: usize> Debug for RacRandState<N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "RacRandState {{ enabled: {}, next_update: {}, x_rands: [{}], distrib: <skipped> }}", self.enabled, self.next_update, self.x_rands.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", "))
     }
 }
 
-impl RacRandState {
+impl<const N: usize> RacRandState<N> {
     pub fn gen_rand_factors(
         rng: &mut Pcg32,
         num_rand_verts: usize,
         rand_mag: f64,
-    ) -> [f64; NVERTS] {
-        let vs = (0..NVERTS).collect::<Vec<usize>>();
-        let mut r = [0.0; NVERTS];
+    ) -> [f64; N] {
+        let vs = (0..N).collect::<Vec<usize>>();
+        let mut r = [0.0; N];
         vs.choose_multiple(rng, num_rand_verts)
             .for_each(|&v| r[v] = rand_mag);
         r
@@ -306,8 +332,8 @@ impl RacRandState {
 
     pub fn new(
         rng: &mut Pcg32,
-        parameters: &Parameters,
-    ) -> RacRandState {
+        parameters: &Parameters<N>,
+    ) -> RacRandState<N> {
         let ut = Uniform::from(0.0..parameters.rand_avg_t);
         RacRandState {
             enabled: true,
@@ -328,8 +354,8 @@ impl RacRandState {
         &self,
         tpoint: f64,
         rng: &mut Pcg32,
-        parameters: &Parameters,
-    ) -> RacRandState {
+        parameters: &Parameters<N>,
+    ) -> RacRandState<N> {
         if tpoint > self.next_update && self.enabled {
             let next_update = tpoint + self.distrib.sample(rng);
             // println!("random update from {} to {}", tstep, next_update);
@@ -351,19 +377,19 @@ impl RacRandState {
     }
 }
 
-impl Display for RacRandState {
+impl<const N: usize> Display for RacRandState<N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_var_arr(f, "rfs", &self.x_rands)?;
         writeln!(f, "next_update: {}", self.next_update)
     }
 }
 
-impl Default for RacRandState {
+impl<const N: usize> Default for RacRandState<N> {
     fn default() -> Self {
         RacRandState {
             enabled: false,
             next_update: 0.0,
-            x_rands: [0.0; NVERTS],
+            x_rands: [0.0; N],
             distrib: NormalDistrib::new(0.0, 1.0),
         }
     }
@@ -0,0 +1,160 @@
+// Copyright © 2020 Brian Merchant.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::cell::states::{Core, DCoreDt};
+
+/// Approximation of a cell's core state over one integration step,
+/// from `t0` to `t0 + h`.
+#[derive(Clone, Debug)]
+pub enum Segment<const N: usize> {
+    /// Continuous extension of the Dormand-Prince method, see
+    /// section II.6, Hairer, Wanner & Norsett, Solving ODEs Vol. 1.
+    Dp5 {
+        t0: f64,
+        h: f64,
+        rcont: [Core<N>; 5],
+    },
+    /// Cubic Hermite interpolation between states `y0` and `y1`,
+    /// with derivatives `f0` and `f1`.
+    Hermite {
+        t0: f64,
+        h: f64,
+        y0: Core<N>,
+        y1: Core<N>,
+        f0: DCoreDt<N>,
+        f1: DCoreDt<N>,
+    },
+    /// Linear interpolation between states `y0` and `y1`.
+    Linear {
+        t0: f64,
+        h: f64,
+        y0: Core<N>,
+        y1: Core<N>,
+    },
+}
+
+impl<const N: usize> Segment<N> {
+    fn t0(&self) -> f64 {
+        match self {
+            Segment::Dp5 { t0, .. }
+            | Segment::Hermite { t0, .. }
+            | Segment::Linear { t0, .. } => *t0,
+        }
+    }
+
+    fn t1(&self) -> f64 {
+        match self {
+            Segment::Dp5 { t0, h, .. }
+            | Segment::Hermite { t0, h, .. }
+            | Segment::Linear { t0, h, .. } => t0 + h,
+        }
+    }
+
+    /// The same segment, starting `offset` later.
+    fn shifted(self, offset: f64) -> Segment<N> {
+        match self {
+            Segment::Dp5 { t0, h, rcont } => Segment::Dp5 {
+                t0: t0 + offset,
+                h,
+                rcont,
+            },
+            Segment::Hermite {
+                t0,
+                h,
+                y0,
+                y1,
+                f0,
+                f1,
+            } => Segment::Hermite {
+                t0: t0 + offset,
+                h,
+                y0,
+                y1,
+                f0,
+                f1,
+            },
+            Segment::Linear { t0, h, y0, y1 } => Segment::Linear {
+                t0: t0 + offset,
+                h,
+                y0,
+                y1,
+            },
+        }
+    }
+
+    fn eval(&self, t: f64) -> Core<N> {
+        match self {
+            Segment::Dp5 { t0, h, rcont } => {
+                let s = (t - t0) / h;
+                let s1 = 1.0 - s;
+                rcont[0]
+                    + s * (rcont[1]
+                        + s1 * (rcont[2]
+                            + s * (rcont[3] + s1 * rcont[4])))
+            }
+            Segment::Hermite {
+                t0,
+                h,
+                y0,
+                y1,
+                f0,
+                f1,
+            } => {
+                let s = (t - t0) / h;
+                let h00 = (1.0 + 2.0 * s) * (1.0 - s).powi(2);
+                let h10 = s * (1.0 - s).powi(2);
+                let h01 = s.powi(2) * (3.0 - 2.0 * s);
+                let h11 = s.powi(2) * (s - 1.0);
+                h00 * *y0
+                    + f0.time_step(h10 * h)
+                    + h01 * *y1
+                    + f1.time_step(h11 * h)
+            }
+            Segment::Linear { t0, h, y0, y1 } => {
+                let s = (t - t0) / h;
+                (1.0 - s) * *y0 + s * *y1
+            }
+        }
+    }
+}
+
+/// Continuous approximation of a cell's core state over a time step,
+/// made up of the integrator's steps (see
+/// `World::save_interpolated`). Time is measured from the start of
+/// the time step.
+#[derive(Clone, Debug, Default)]
+pub struct DenseOutput<const N: usize> {
+    segments: Vec<Segment<N>>,
+}
+
+impl<const N: usize> DenseOutput<N> {
+    /// Add a segment, which must start where the last one ended.
+    pub fn push(&mut self, segment: Segment<N>) {
+        self.segments.push(segment);
+    }
+
+    /// Add the segments of `other`, which covers a time step starting
+    /// `offset` after the start of this one.
+    pub fn append(&mut self, other: DenseOutput<N>, offset: f64) {
+        self.segments.extend(
+            other.segments.into_iter().map(|s| s.shifted(offset)),
+        );
+    }
+
+    /// Approximate state at time `t`. Times outside of the segments
+    /// are clamped to them.
+    pub fn eval(&self, t: f64) -> Core<N> {
+        let segment = self
+            .segments
+            .iter()
+            .find(|s| t <= s.t1())
+            .or_else(|| self.segments.last())
+            .expect("no dense output was recorded");
+        segment.eval(t.max(segment.t0()).min(segment.t1()))
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright © 2020 Brian Merchant.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::cell::states::Core;
+use crate::math::geometry::calc_poly_centroid;
+use crate::math::v2d::V2d;
+use crate::utils::circ_ix_plus;
+
+/// Daughter polygons are shrunk towards their centroids by this
+/// factor, so that they do not start out touching along the division
+/// line.
+const DAUGHTER_SCALE: f64 = 0.9;
+
+impl<const N: usize> Core<N> {
+    /// Divide the cell along the line through its centroid with
+    /// direction `dir`. Each daughter's polygon is the part of the
+    /// parent's polygon on one side of the line, resampled to
+    /// `N` vertices evenly spaced along its boundary.
+    ///
+    /// Membrane bound Rho GTPase is partitioned between the
+    /// daughters: the Rho GTPase on each parent vertex is moved to
+    /// the nearest vertex of the daughter on whose side it lies.
+    /// Whatever a daughter does not inherit is cytosolic.
+    ///
+    /// Returns `None` if the line does not cut the polygon into
+    /// exactly two pieces, which is possible if it is very concave.
+    pub fn divide(&self, dir: V2d) -> Option<[Core<N>; 2]> {
+        let centroid = calc_poly_centroid(&self.poly);
+        let normal = dir.normal();
+        let sides = self.poly.map(|v| (v - centroid).dot(&normal));
+        // Edges crossed by the division line, with the point at
+        // which they are crossed.
+        let crossings = (0..N)
+            .filter_map(|vi| {
+                let wi = circ_ix_plus(vi, N);
+                let (a, b) = (sides[vi], sides[wi]);
+                if (a >= 0.0) != (b >= 0.0) {
+                    let t = a / (a - b);
+                    let p = self.poly[vi]
+                        + t * (self.poly[wi] - self.poly[vi]);
+                    Some((vi, p))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<(usize, V2d)>>();
+        if crossings.len() != 2 {
+            return None;
+        }
+        let mut daughters = [Core::default(); 2];
+        for (k, daughter) in daughters.iter_mut().enumerate() {
+            // The daughter's boundary runs from one crossing, along
+            // the parent's boundary to the other crossing, and then
+            // back along the division line.
+            let (start_ei, start) = crossings[k];
+            let (end_ei, end) = crossings[1 - k];
+            let mut boundary = vec![start];
+            let mut parent_vis = vec![];
+            let mut vi = circ_ix_plus(start_ei, N);
+            loop {
+                parent_vis.push(vi);
+                boundary.push(self.poly[vi]);
+                if vi == end_ei {
+                    break;
+                }
+                vi = circ_ix_plus(vi, N);
+            }
+            boundary.push(end);
+            *daughter = self.daughter(&boundary, &parent_vis);
+        }
+        Some(daughters)
+    }
+
+    /// Create a daughter whose (closed) boundary is `boundary`.
+    /// `parent_vis` are the parent vertices which make up
+    /// `boundary[1..]`, in order.
+    fn daughter(
+        &self,
+        boundary: &[V2d],
+        parent_vis: &[usize],
+    ) -> Core<N> {
+        let num_points = boundary.len();
+        // Arc length along the boundary at which each point lies.
+        let mut arc_lens = vec![0.0; num_points + 1];
+        for i in 0..num_points {
+            let next = boundary[(i + 1) % num_points];
+            arc_lens[i + 1] =
+                arc_lens[i] + (next - boundary[i]).mag();
+        }
+        let perimeter = arc_lens[num_points];
+        let spacing = perimeter / N as f64;
+        let mut poly = [V2d::default(); N];
+        let mut seg = 0;
+        for (k, v) in poly.iter_mut().enumerate() {
+            let s = k as f64 * spacing;
+            while arc_lens[seg + 1] < s {
+                seg += 1;
+            }
+            let seg_len = arc_lens[seg + 1] - arc_lens[seg];
+            let t = if seg_len > 0.0 {
+                (s - arc_lens[seg]) / seg_len
+            } else {
+                0.0
+            };
+            let (a, b) =
+                (boundary[seg], boundary[(seg + 1) % num_points]);
+            *v = a + t * (b - a);
+        }
+        let centroid = calc_poly_centroid(&poly);
+        poly.iter_mut().for_each(|v| {
+            *v = centroid + DAUGHTER_SCALE * (*v - centroid)
+        });
+
+        let mut rac_acts = [0.0; N];
+        let mut rac_inacts = [0.0; N];
+        let mut rho_acts = [0.0; N];
+        let mut rho_inacts = [0.0; N];
+        for (i, &pvi) in parent_vis.iter().enumerate() {
+            let k = (arc_lens[i + 1] / spacing).round() as usize % N;
+            rac_acts[k] += self.rac_acts[pvi];
+            rac_inacts[k] += self.rac_inacts[pvi];
+            rho_acts[k] += self.rho_acts[pvi];
+            rho_inacts[k] += self.rho_inacts[pvi];
+        }
+        Core::new(poly, rac_acts, rac_inacts, rho_acts, rho_inacts)
+    }
+}
@@ -10,28 +10,27 @@ use crate::math::capped_linear_fn;
 use crate::math::geometry::calc_poly_area;
 use crate::math::v2d::V2d;
 use crate::utils::circ_ix_plus;
-use crate::NVERTS;
 
 /// Calculate edge vectors of a polygon.
-pub fn calc_edge_vecs(
-    vertex_coords: &[V2d; NVERTS],
-) -> [V2d; NVERTS] {
-    let mut r = [V2d::default(); NVERTS];
-    (0..NVERTS).for_each(|i| {
-        let plus_i = circ_ix_plus(i, NVERTS);
+pub fn calc_edge_vecs<const N: usize>(
+    vertex_coords: &[V2d; N],
+) -> [V2d; N] {
+    let mut r = [V2d::default(); N];
+    (0..N).for_each(|i| {
+        let plus_i = circ_ix_plus(i, N);
         r[i] = vertex_coords[plus_i] - vertex_coords[i];
     });
     r
 }
 
 /// Calculate elastic forces due to stretching of edges.
-pub fn calc_edge_forces(
-    edge_strains: &[f64; NVERTS],
-    unit_edge_vecs: &[V2d; NVERTS],
+pub fn calc_edge_forces<const N: usize>(
+    edge_strains: &[f64; N],
+    unit_edge_vecs: &[V2d; N],
     stiffness_edge: f64,
-) -> [V2d; NVERTS] {
-    let mut r = [V2d::default(); NVERTS];
-    (0..NVERTS).for_each(|i| {
+) -> [V2d; N] {
+    let mut r = [V2d::default(); N];
+    (0..N).for_each(|i| {
         // Elastic relationship: stiffness * strain = magnitude of
         // force. Direction of force is along the unite edge vector.
         r[i] = edge_strains[i] * stiffness_edge * unit_edge_vecs[i]
@@ -43,17 +42,17 @@ pub fn calc_edge_forces(
 /// Assume cytoplasm is a "elastic" liquid, where the force generated
 /// is linearly proportional to the areal strain:
 /// `(final_area - initial_area)/initial_area`
-pub fn calc_cyto_forces(
-    vertex_coords: &[V2d; NVERTS],
-    unit_inward_vecs: &[V2d; NVERTS],
+pub fn calc_cyto_forces<const N: usize>(
+    vertex_coords: &[V2d; N],
+    unit_inward_vecs: &[V2d; N],
     rest_area: f64,
     stiffness_cyto: f64,
-) -> [V2d; NVERTS] {
-    let mut r = [V2d::default(); NVERTS];
+) -> [V2d; N] {
+    let mut r = [V2d::default(); N];
     let area = calc_poly_area(vertex_coords);
     let areal_strain = (area / rest_area) - 1.0;
     let mag = stiffness_cyto * areal_strain;
-    (0..NVERTS).for_each(|i| r[i] = mag * unit_inward_vecs[i]);
+    (0..N).for_each(|i| r[i] = mag * unit_inward_vecs[i]);
     r
 }
 
@@ -65,16 +64,16 @@ pub fn calc_cyto_forces(
 /// linear (approximating a sigmoid) relationship between Rho GTPase
 /// activity, and the force generated. The shape of the sigmoid is
 /// governed by `halfmax_vertex_rgtp`.
-pub fn calc_rgtp_forces(
-    rac_acts: &[f64; NVERTS],
-    rho_acts: &[f64; NVERTS],
-    unit_inward_vecs: &[V2d; NVERTS],
+pub fn calc_rgtp_forces<const N: usize>(
+    rac_acts: &[f64; N],
+    rho_acts: &[f64; N],
+    unit_inward_vecs: &[V2d; N],
     halfmax_vertex_rgtp: f64,
     const_protrusive: f64,
     const_retractive: f64,
-) -> [V2d; NVERTS] {
-    let mut r = [V2d::default(); NVERTS];
-    for i in 0..NVERTS {
+) -> [V2d; N] {
+    let mut r = [V2d::default(); N];
+    for i in 0..N {
         // Direction force will point in.
         let uiv = unit_inward_vecs[i];
         let ra = rac_acts[i];
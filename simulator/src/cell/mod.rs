@@ -6,83 +6,122 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 pub mod chemistry;
+pub mod dense;
+pub mod division;
 pub mod mechanics;
+pub mod remesh;
 pub mod rkdp5;
+pub mod ros2;
+pub mod sde;
 pub mod states;
 
 use crate::cell::chemistry::RacRandState;
-use crate::cell::rkdp5::RkErr;
+use crate::cell::dense::{DenseOutput, Segment};
+use crate::cell::rkdp5::{IntStats, RkErr, Solution};
 use crate::cell::states::{confirm_volume_exclusion, Core, VolExErr};
-use crate::interactions::{ContactData, Interactions};
+use crate::interactions::{Contacts, Interactions};
 use crate::parameters::{Parameters, WorldParameters};
 use crate::utils::pcg32::Pcg32;
-use crate::world::{EulerOpts, RkOpts};
+use crate::world::{
+    EulerOpts, IntegratorOpts, NoiseOpts, RkOpts, SimError,
+};
 use serde::{Deserialize, Serialize};
 
 /// Cell state structure.
 #[derive(
     Copy, Clone, Deserialize, Serialize, PartialEq, Default, Debug,
 )]
-pub struct Cell {
-    /// Index of cell within world.
+pub struct Cell<const N: usize> {
+    /// Index of cell within world. Indices are reassigned when cells
+    /// are removed, so that they always run from `0` to the number
+    /// of cells.
     pub ix: usize,
+    /// Identifier of the cell, which unlike `ix`, does not change
+    /// over the cell's lifetime, and is never reused.
+    pub id: usize,
     /// Index of group that cell belongs to.
     pub group_ix: usize,
     /// State of Random Rac1 activity that affected `core`.
-    pub rac_rand: RacRandState,
+    pub rac_rand: RacRandState<N>,
     /// Core state of the cell (position, Rho GTPase).
-    pub core: Core,
+    pub core: Core<N>,
+    /// Random number generator belonging to the cell, from which
+    /// the noise of stochastic integrators is drawn.
+    pub rng: Pcg32,
 }
 
-impl Cell {
+impl<const N: usize> Cell<N> {
     pub fn new(
         ix: usize,
+        id: usize,
         group_ix: usize,
-        core: Core,
-        parameters: &Parameters,
-        rng: &mut Pcg32,
-    ) -> Cell {
+        core: Core<N>,
+        parameters: &Parameters<N>,
+        mut rng: Pcg32,
+    ) -> Cell<N> {
         let rac_rand = if parameters.randomization {
-            RacRandState::new(rng, parameters)
+            RacRandState::new(&mut rng, parameters)
         } else {
             RacRandState::default()
         };
         Cell {
             ix,
+            id,
             group_ix,
             core,
             rac_rand,
+            rng,
         }
     }
 
     /// Suppose our current state is `state`. We want to determine
-    /// the next state after a time period `dt` has elapsed. We
-    /// assume `(next_state - state)/delta(t) = delta(state)`.
+    /// the next state after a time period `dt` has elapsed, which
+    /// is split into `int_opts.num_int_steps` steps. We assume
+    /// `(next_state - state)/delta(t) = delta(state)`. If
+    /// `noise` is given, noise drawn from the cell's own random
+    /// number generator is added to each step (Euler-Maruyama, see
+    /// `Core::euler_maruyama_step`). If `dense` is given, linear
+    /// interpolants between the integration steps are added to it.
     pub fn simulate_euler(
         &self,
         tpoint: f64,
-        interactions: &Interactions,
-        contact_data: Vec<ContactData>,
+        dt: f64,
+        interactions: &Interactions<N>,
+        contact_data: Contacts<N>,
         world_parameters: &WorldParameters,
-        parameters: &Parameters,
+        parameters: &Parameters<N>,
         rng: &mut Pcg32,
         int_opts: EulerOpts,
-    ) -> Result<Cell, String> {
+        noise: Option<NoiseOpts>,
+        mut dense: Option<&mut DenseOutput<N>>,
+    ) -> Result<Cell<N>, SimError> {
         let mut state = self.core;
-        // Assumed normalized time by time provided in CharQuant.
-        // Therefore, we can take the time period to integrate over
-        // as 1.0.
-        let dt = 1.0 / (int_opts.num_int_steps as f64);
-        for _ in 0..int_opts.num_int_steps {
-            // d(state)/dt = dynamics_f(state) <- calculate RHS of ODE
-            let delta = Core::derivative(
-                &state,
-                &self.rac_rand,
-                &interactions,
-                world_parameters,
-                parameters,
-            );
-            state = state + delta.time_step(dt);
+        let mut cell_rng = self.rng;
+        let h = dt / (int_opts.num_int_steps as f64);
+        for int_step in 0..int_opts.num_int_steps {
+            let old_state = state;
+            state = match noise {
+                Some(noise) => state.euler_maruyama_step(
+                    &self.rac_rand,
+                    interactions,
+                    world_parameters,
+                    parameters,
+                    h,
+                    &noise,
+                    &mut cell_rng,
+                ),
+                None => {
+                    // d(state)/dt = dynamics_f(state) <- calculate RHS of ODE
+                    let delta = Core::derivative(
+                        &state,
+                        &self.rac_rand,
+                        interactions,
+                        world_parameters,
+                        parameters,
+                    );
+                    state + delta.time_step(h)
+                }
+            };
             // Enforcing volume exclusion! Tricky!
             state
                 .strict_enforce_volume_exclusion(
@@ -90,22 +129,40 @@ impl Cell {
                     &contact_data,
                 )
                 .map_err(|e| match e {
-                    VolExErr::OldVs(s) | VolExErr::NewVs(s) => s,
+                    VolExErr::OldVs(v) | VolExErr::NewVs(v) => {
+                        SimError::vol_ex(tpoint, self.ix, v)
+                    }
                 })?;
+            if let Some(dense) = dense.as_mut() {
+                dense.push(Segment::Linear {
+                    t0: int_step as f64 * h,
+                    h,
+                    y0: old_state,
+                    y1: state,
+                });
+            }
         }
 
         #[cfg(feature = "validate")]
-        state.validate("euler")?;
+        state.validate("euler").map_err(|msg| {
+            SimError::InvalidState {
+                tpoint,
+                cell_ix: self.ix,
+                msg,
+            }
+        })?;
 
         Ok(Cell {
             ix: self.ix,
+            id: self.id,
             group_ix: self.group_ix,
             core: state,
             rac_rand: self.rac_rand.update(
-                tpoint + 1.0,
+                tpoint + dt,
                 rng,
                 parameters,
             ),
+            rng: cell_rng,
         })
     }
 
@@ -114,14 +171,15 @@ impl Cell {
     /// assume `(next_state - state)/delta(t) = delta(state)`.
     pub fn simulate_euler_debug(
         &self,
-        interactions: &Interactions,
-        contact_data: Vec<ContactData>,
+        tpoint: f64,
+        interactions: &Interactions<N>,
+        contact_data: Contacts<N>,
         world_parameters: &WorldParameters,
-        parameters: &Parameters,
+        parameters: &Parameters<N>,
         int_opts: EulerOpts,
-    ) -> Result<Vec<Cell>, String> {
+    ) -> Result<Vec<Cell<N>>, SimError> {
         // println!("cell_ix: {}", cell_ix);
-        let mut r: Vec<Cell> =
+        let mut r: Vec<Cell<N>> =
             Vec::with_capacity(int_opts.num_int_steps as usize);
         let mut state = self.core;
         let dt = 1.0 / (int_opts.num_int_steps as f64);
@@ -129,7 +187,8 @@ impl Cell {
             &self.core.poly,
             &contact_data,
             "old_vs",
-        )?;
+        )
+        .map_err(|v| SimError::vol_ex(tpoint, self.ix, v))?;
         // let (focus_vi, other_focus_v) = if cell_ix == 0 {
         //     (0, contact_data[0].poly.verts[8])
         // } else {
@@ -156,7 +215,9 @@ impl Cell {
                     &contact_data,
                 )
                 .map_err(|e| match e {
-                    VolExErr::OldVs(s) | VolExErr::NewVs(s) => s,
+                    VolExErr::OldVs(v) | VolExErr::NewVs(v) => {
+                        SimError::vol_ex(tpoint, self.ix, v)
+                    }
                 })?;
             // println!(
             //     "after vol_ex | state.poly[{}] = {} | other: {}",
@@ -164,13 +225,21 @@ impl Cell {
             // );
             r.push(Cell {
                 ix: self.ix,
+                id: self.id,
                 group_ix: self.group_ix,
                 core: state,
                 rac_rand: self.rac_rand,
+                rng: self.rng,
             })
         }
         #[cfg(feature = "validate")]
-        state.validate("euler")?;
+        state.validate("euler").map_err(|msg| {
+            SimError::InvalidState {
+                tpoint,
+                cell_ix: self.ix,
+                msg,
+            }
+        })?;
         // println!(
         //     "final | state.poly[{}] = {} | other: {}",
         //     focus_vi,
@@ -188,18 +257,23 @@ impl Cell {
         Ok(r)
     }
 
+    /// Integrate the cell over a time step of length `dt` using the
+    /// Runge-Kutta Dormand-Prince 5(4) method. Returns the cell's new
+    /// state, along with statistics from the integrator. If `dense`
+    /// is given, the method's continuous extension is added to it.
     pub fn simulate_rkdp5(
         &self,
         tpoint: f64,
         dt: f64,
-        interactions: &Interactions,
-        contact_data: Vec<ContactData>,
+        interactions: &Interactions<N>,
+        contact_data: Contacts<N>,
         world_parameters: &WorldParameters,
-        parameters: &Parameters,
+        parameters: &Parameters<N>,
         rng: &mut Pcg32,
         int_opts: RkOpts,
-    ) -> Result<Cell, String> {
-        let mut result = rkdp5::integrator(
+        dense: Option<&mut DenseOutput<N>>,
+    ) -> Result<(Cell<N>, IntStats), SimError> {
+        let result = rkdp5::integrator(
             dt,
             Core::derivative,
             self.core,
@@ -209,25 +283,149 @@ impl Cell {
             parameters,
             &contact_data,
             int_opts,
+            dense,
+        );
+        self.finish_adaptive(
+            tpoint,
+            result,
+            parameters,
+            rng,
+            IntegratorOpts::Rkdp5(int_opts),
+        )
+    }
+
+    /// Integrate the cell over a time step of length `dt` using the
+    /// stiff ROS2 method (see `ros2::integrator`). Returns the cell's
+    /// new state, along with statistics from the integrator. If
+    /// `dense` is given, interpolants of the steps are added to it.
+    pub fn simulate_ros2(
+        &self,
+        tpoint: f64,
+        dt: f64,
+        interactions: &Interactions<N>,
+        contact_data: Contacts<N>,
+        world_parameters: &WorldParameters,
+        parameters: &Parameters<N>,
+        rng: &mut Pcg32,
+        int_opts: RkOpts,
+        dense: Option<&mut DenseOutput<N>>,
+    ) -> Result<(Cell<N>, IntStats), SimError> {
+        let result = ros2::integrator(
+            dt,
+            self.core,
+            &self.rac_rand,
+            interactions,
+            world_parameters,
+            parameters,
+            &contact_data,
+            int_opts,
+            dense,
         );
+        self.finish_adaptive(
+            tpoint,
+            result,
+            parameters,
+            rng,
+            IntegratorOpts::Ros2(int_opts),
+        )
+    }
 
-        match &mut result.state {
+    /// Turn the solution found by an adaptive integrator into the
+    /// cell's new state.
+    fn finish_adaptive(
+        &self,
+        tpoint: f64,
+        result: Solution<N>,
+        parameters: &Parameters<N>,
+        rng: &mut Pcg32,
+        int_opts: IntegratorOpts,
+    ) -> Result<(Cell<N>, IntStats), SimError> {
+        match result.state {
             Ok(cs) => {
                 #[cfg(feature = "validate")]
-                cs.validate("rkdp5")?;
+                cs.validate("adaptive").map_err(|msg| {
+                    SimError::InvalidState {
+                        tpoint,
+                        cell_ix: self.ix,
+                        msg,
+                    }
+                })?;
 
-                Ok(Cell {
-                    ix: self.ix,
-                    group_ix: self.group_ix,
-                    core: *cs,
-                    rac_rand: self
-                        .rac_rand
-                        .update(tpoint, rng, parameters),
+                Ok((
+                    Cell {
+                        ix: self.ix,
+                        id: self.id,
+                        group_ix: self.group_ix,
+                        core: cs,
+                        rac_rand: self
+                            .rac_rand
+                            .update(tpoint, rng, parameters),
+                        rng: self.rng,
+                    },
+                    result.stats,
+                ))
+            }
+            Err(RkErr::VolEx(v)) => {
+                Err(SimError::vol_ex(tpoint, self.ix, v))
+            }
+            Err(RkErr::TooManyIters) => {
+                Err(SimError::NonConvergence {
+                    tpoint,
+                    cell_ix: self.ix,
+                    num_iters: result.stats.num_iters,
+                    num_rejections: result.stats.num_rejections,
+                    int_opts,
                 })
             }
-            Err(RkErr::VolEx(s)) | Err(RkErr::TooManyIters(s)) => {
-                Err(format!("{} ({:?})", s, int_opts))
+        }
+    }
+
+    /// Integrate the cell over a time step of length `dt`, split into
+    /// `substeps` steps of equal length, each taken by `integrate`
+    /// (given the cell, time point, and length of the step). Returns
+    /// the cell's new state, along with any integrator statistics
+    /// from each step. If `dense` is given, the dense output of each
+    /// step is added to it.
+    pub fn simulate_substeps<F>(
+        &self,
+        tpoint: f64,
+        dt: f64,
+        substeps: usize,
+        mut dense: Option<&mut DenseOutput<N>>,
+        mut integrate: F,
+    ) -> Result<(Cell<N>, Vec<IntStats>), SimError>
+    where
+        F: FnMut(
+            &Cell<N>,
+            f64,
+            f64,
+            Option<&mut DenseOutput<N>>,
+        )
+            -> Result<(Cell<N>, Option<IntStats>), SimError>,
+    {
+        if substeps == 1 {
+            return integrate(self, tpoint, dt, dense).map(
+                |(cell, stats)| (cell, stats.into_iter().collect()),
+            );
+        }
+        let h = dt / (substeps as f64);
+        let mut cell = *self;
+        let mut all_stats = vec![];
+        for k in 0..substeps {
+            let offset = (k as f64) * h;
+            let mut output = DenseOutput::default();
+            let (next, stats) = integrate(
+                &cell,
+                tpoint + offset,
+                h,
+                dense.is_some().then_some(&mut output),
+            )?;
+            if let Some(dense) = dense.as_mut() {
+                dense.append(output, offset);
             }
+            all_stats.extend(stats);
+            cell = next;
         }
+        Ok((cell, all_stats))
     }
 }
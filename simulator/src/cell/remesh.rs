@@ -0,0 +1,127 @@
+// Copyright © 2020 Brian Merchant.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::cell::states::Core;
+use crate::math::v2d::V2d;
+use crate::utils::circ_ix_minus;
+use serde::{Deserialize, Serialize};
+
+/// When cells are remeshed, as given in an experiment's JSON.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RemeshOpts {
+    /// A cell is remeshed after a time step if the ratio of its
+    /// longest edge to its shortest edge exceeds this.
+    pub max_edge_ratio: f64,
+}
+
+impl<const N: usize> Core<N> {
+    /// Ratio of the length of the cell's longest edge to that of its
+    /// shortest edge.
+    pub fn edge_len_ratio(&self) -> f64 {
+        let lens = &self.geom.edge_lens;
+        let max =
+            lens.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let min = lens.iter().cloned().fold(f64::INFINITY, f64::min);
+        max / min
+    }
+
+    /// Redistribute the cell's vertices so that they are evenly
+    /// spaced along its perimeter. Vertex `0` keeps its position.
+    ///
+    /// The Rho GTPase on each vertex is taken to be spread evenly
+    /// over the part of the membrane closest to it (half of each
+    /// adjacent edge). Each new vertex receives the Rho GTPase on
+    /// the part of the membrane closest to it, so the total membrane
+    /// bound Rho GTPase is unchanged.
+    pub fn remesh(&self) -> Core<N> {
+        let edge_lens = self.geom.edge_lens;
+        // Arc length along the perimeter, starting from vertex 0, at
+        // which each vertex lies.
+        let mut arc_lens = [0.0; N];
+        for vi in 1..N {
+            arc_lens[vi] = arc_lens[vi - 1] + edge_lens[vi - 1];
+        }
+        let perimeter = arc_lens[N - 1] + edge_lens[N - 1];
+        let spacing = perimeter / N as f64;
+
+        let mut poly = [V2d::default(); N];
+        let mut ei = 0;
+        for (k, v) in poly.iter_mut().enumerate() {
+            let s = k as f64 * spacing;
+            while ei + 1 < N && arc_lens[ei + 1] <= s {
+                ei += 1;
+            }
+            let t = (s - arc_lens[ei]) / edge_lens[ei];
+            let (a, b) = (self.poly[ei], self.poly[(ei + 1) % N]);
+            *v = a + t * (b - a);
+        }
+
+        // The part of the membrane closest to vertex `vi` starts at
+        // `starts[vi]`.
+        let mut starts = [0.0; N];
+        for vi in 0..N {
+            starts[vi] =
+                arc_lens[vi] - 0.5 * edge_lens[circ_ix_minus(vi, N)];
+        }
+        let remap = |amounts: &[f64; N]| -> [f64; N] {
+            let mut r = [0.0; N];
+            for (k, x) in r.iter_mut().enumerate() {
+                let s = k as f64 * spacing;
+                *x = cumulative(
+                    &starts,
+                    perimeter,
+                    amounts,
+                    s + 0.5 * spacing,
+                ) - cumulative(
+                    &starts,
+                    perimeter,
+                    amounts,
+                    s - 0.5 * spacing,
+                );
+            }
+            r
+        };
+        Core::new(
+            poly,
+            remap(&self.rac_acts),
+            remap(&self.rac_inacts),
+            remap(&self.rho_acts),
+            remap(&self.rho_inacts),
+        )
+    }
+}
+
+/// Amount of Rho GTPase on the membrane between arc length
+/// `starts[0]` and `s`, where vertex `vi` has `amounts[vi]` spread
+/// evenly between `starts[vi]` and `starts[vi + 1]`. `s` may wrap
+/// around the perimeter any number of times.
+fn cumulative<const N: usize>(
+    starts: &[f64; N],
+    perimeter: f64,
+    amounts: &[f64; N],
+    s: f64,
+) -> f64 {
+    let total: f64 = amounts.iter().sum();
+    let laps = ((s - starts[0]) / perimeter).floor();
+    let s = s - laps * perimeter;
+    let mut r = laps * total;
+    for vi in 0..N {
+        let end = if vi + 1 < N {
+            starts[vi + 1]
+        } else {
+            starts[0] + perimeter
+        };
+        if s >= end {
+            r += amounts[vi];
+        } else {
+            r += amounts[vi] * (s - starts[vi]) / (end - starts[vi]);
+            break;
+        }
+    }
+    r
+}
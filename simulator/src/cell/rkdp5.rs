@@ -1,17 +1,19 @@
-use crate::cell::states::{DCoreDt, VolExErr};
+use crate::cell::dense::{DenseOutput, Segment};
+use crate::cell::states::{DCoreDt, VolExErr, VolExViolation};
 use crate::cell::{chemistry::RacRandState, states::Core};
-use crate::interactions::{ContactData, Interactions};
+use crate::interactions::{Contacts, Interactions};
 use crate::math::min_f64;
 use crate::parameters::{Parameters, WorldParameters};
 use crate::world::RkOpts;
+use serde::{Deserialize, Serialize};
 
-type CellDynamicsFn = fn(
-    state: &Core,
-    rac_random_state: &RacRandState,
-    interactions: &Interactions,
+type CellDynamicsFn<const N: usize> = fn(
+    state: &Core<N>,
+    rac_random_state: &RacRandState<N>,
+    interactions: &Interactions<N>,
     world_parameters: &WorldParameters,
-    parameters: &Parameters,
-) -> DCoreDt;
+    parameters: &Parameters<N>,
+) -> DCoreDt<N>;
 
 // A0s are all zeros
 const A1: f64 = 1.0 / 5.0;
@@ -56,41 +58,64 @@ const B_HAT: [f64; 7] = [
     187.0 / 2100.0,
     1.0 / 40.0,
 ];
+// Coefficients of the continuous extension, see section II.6 in HNW vol1
+const D: [f64; 7] = [
+    -12715105075.0 / 11282082432.0,
+    0.0,
+    87487479700.0 / 32700410799.0,
+    -10690763975.0 / 1880347072.0,
+    701980252875.0 / 199316789632.0,
+    -1453857185.0 / 822651844.0,
+    69997945.0 / 29380423.0,
+];
 const INV_QP1: f64 = 1.0 / 5.0; // inverse (max of p and p_hat) + 1, see explanation for equation 4.12 in HNW vol1
 const FAC: f64 = 0.8; // safety factor, approximately 0.38^QP1, see explanation for equation 4.12 in HWN vol1
 const FAC_MAX: f64 = (5.0 - 1.5) / 2.0; // see explanation for equation 4.12 in HWN vol1
 
 pub enum RkErr {
-    VolEx(String),
-    TooManyIters(String),
+    VolEx(VolExViolation),
+    TooManyIters,
 }
 
-pub struct Solution {
-    pub state: Result<Core, RkErr>,
-    pub num_rejections: usize,
+/// Statistics describing how the integrator arrived at a cell's
+/// state after a time step.
+#[derive(
+    Clone, Debug, Default, Deserialize, Serialize, PartialEq,
+)]
+pub struct IntStats {
+    /// Number of iterations (accepted and rejected steps).
     pub num_iters: usize,
+    /// Number of rejected steps.
+    pub num_rejections: usize,
+    /// Sizes of the accepted steps, in order.
+    pub step_sizes: Vec<f64>,
+}
+
+pub struct Solution<const N: usize> {
+    pub state: Result<Core<N>, RkErr>,
+    pub stats: IntStats,
 }
 
-pub struct Ks {
-    k0: DCoreDt,
-    k1: DCoreDt,
-    k2: DCoreDt,
-    k3: DCoreDt,
-    k4: DCoreDt,
-    k5: DCoreDt,
-    k6: DCoreDt,
+pub struct Ks<const N: usize> {
+    k0: DCoreDt<N>,
+    k1: DCoreDt<N>,
+    k2: DCoreDt<N>,
+    k3: DCoreDt<N>,
+    k4: DCoreDt<N>,
+    k5: DCoreDt<N>,
+    k6: DCoreDt<N>,
 }
 
-impl Ks {
+impl<const N: usize> Ks<N> {
     fn calc(
-        f: CellDynamicsFn,
+        f: CellDynamicsFn<N>,
         h: f64,
-        init_state: Core,
-        rand_state: &RacRandState,
-        inter_state: &Interactions,
+        init_state: Core<N>,
+        rand_state: &RacRandState<N>,
+        inter_state: &Interactions<N>,
         world_parameters: &WorldParameters,
-        parameters: &Parameters,
-    ) -> Ks {
+        parameters: &Parameters<N>,
+    ) -> Ks<N> {
         let k0 = f(
             &init_state,
             rand_state,
@@ -194,19 +219,57 @@ impl Ks {
             k6,
         }
     }
+
+    /// Coefficients of the continuous extension over a step of size
+    /// `h` from `y0` to `y1`.
+    fn dense_segment(
+        &self,
+        t0: f64,
+        h: f64,
+        y0: Core<N>,
+        y1: Core<N>,
+    ) -> Segment<N> {
+        let Ks {
+            k0,
+            k1: _,
+            k2,
+            k3,
+            k4,
+            k5,
+            k6,
+        } = self;
+        let r1 = y1 - y0;
+        let r2 = k0.time_step(h) - r1;
+        let r3 = r1 - k6.time_step(h) - r2;
+        let r4 = k0.time_step(h * D[0])
+            + k2.time_step(h * D[2])
+            + k3.time_step(h * D[3])
+            + k4.time_step(h * D[4])
+            + k5.time_step(h * D[5])
+            + k6.time_step(h * D[6]);
+        Segment::Dp5 {
+            t0,
+            h,
+            rcont: [y0, r1, r2, r3, r4],
+        }
+    }
 }
 
-pub fn integrator(
+/// Integrate a cell over a time step of length `dt`. If `dense` is
+/// given, the continuous extension of each accepted step is added to
+/// it.
+pub fn integrator<const N: usize>(
     mut dt: f64,
-    f: CellDynamicsFn,
-    mut init_state: Core,
-    rand_state: &RacRandState,
-    interactions: &Interactions,
+    f: CellDynamicsFn<N>,
+    mut init_state: Core<N>,
+    rand_state: &RacRandState<N>,
+    interactions: &Interactions<N>,
     world_parameters: &WorldParameters,
-    parameters: &Parameters,
-    contact_data: &[ContactData],
+    parameters: &Parameters<N>,
+    contact_data: &Contacts<N>,
     int_opts: RkOpts,
-) -> Solution {
+    mut dense: Option<&mut DenseOutput<N>>,
+) -> Solution<N> {
     let RkOpts {
         max_iters,
         atol,
@@ -221,17 +284,11 @@ pub fn integrator(
     let mut fac_max = FAC_MAX;
     let mut try_as_last_iter = false;
     let mut num_rejections: usize = 0;
+    let mut step_sizes = vec![];
+    let mut t = 0.0;
 
     while num_iters < max_iters && h > 1e-14 {
-        let Ks {
-            k0,
-            k1,
-            k2,
-            k3,
-            k4,
-            k5,
-            k6,
-        } = Ks::calc(
+        let ks = Ks::calc(
             f,
             h,
             init_state,
@@ -240,6 +297,15 @@ pub fn integrator(
             world_parameters,
             parameters,
         );
+        let Ks {
+            k0,
+            k1,
+            k2,
+            k3,
+            k4,
+            k5,
+            k6,
+        } = ks;
 
         let mut next_state = init_state
             + B[0] * k0.time_step(h)
@@ -277,12 +343,24 @@ pub fn integrator(
             ),
         ) {
             (true, Ok(_)) => {
+                step_sizes.push(h);
+                if let Some(dense) = dense.as_mut() {
+                    dense.push(
+                        ks.dense_segment(
+                            t, h, init_state, next_state,
+                        ),
+                    );
+                }
+                t += h;
                 if try_as_last_iter {
                     assert!((h - dt).abs() < f64::EPSILON);
                     return Solution {
                         state: Ok(next_state),
-                        num_rejections,
-                        num_iters,
+                        stats: IntStats {
+                            num_iters: num_iters + 1,
+                            num_rejections,
+                            step_sizes,
+                        },
                     };
                 } else {
                     fac_max = FAC_MAX;
@@ -298,8 +376,11 @@ pub fn integrator(
             (true, Err(VolExErr::OldVs(s))) => {
                 return Solution {
                     state: Err(RkErr::VolEx(s)),
-                    num_rejections,
-                    num_iters,
+                    stats: IntStats {
+                        num_iters: num_iters + 1,
+                        num_rejections,
+                        step_sizes,
+                    },
                 };
             }
             (false, _) | (true, Err(VolExErr::NewVs(_))) => {
@@ -314,10 +395,11 @@ pub fn integrator(
     }
 
     Solution {
-        state: Err(RkErr::TooManyIters(
-            "Too many iterations!".to_string(),
-        )),
-        num_rejections,
-        num_iters,
+        state: Err(RkErr::TooManyIters),
+        stats: IntStats {
+            num_iters,
+            num_rejections,
+            step_sizes,
+        },
     }
 }
@@ -0,0 +1,246 @@
+// Copyright © 2020 Brian Merchant.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Second order Rosenbrock method (ROS2) for stiff cell dynamics.
+//! See Verwer, Spee, Blom & Hundsdorfer (1999), "A second-order
+//! Rosenbrock method applied to photochemical dispersion problems".
+//! Each step solves linear systems in `I - GAMMA * h * J`, where `J`
+//! is the Jacobian of the cell dynamics, so the method remains
+//! stable for step sizes at which explicit methods such as RKDP5
+//! must keep rejecting steps.
+
+use crate::cell::chemistry::RacRandState;
+use crate::cell::dense::{DenseOutput, Segment};
+use crate::cell::rkdp5::{IntStats, RkErr, Solution};
+use crate::cell::states::{Core, VolExErr};
+use crate::interactions::{Contacts, Interactions};
+use crate::math::linalg::{Lu, Matrix};
+use crate::math::min_f64;
+use crate::parameters::{Parameters, WorldParameters};
+use crate::world::RkOpts;
+
+/// `1 + 1/sqrt(2)`, which makes the method L-stable.
+const GAMMA: f64 = 1.0 + std::f64::consts::FRAC_1_SQRT_2;
+const INV_QP1: f64 = 1.0 / 2.0; // inverse of (order of error estimate + 1)
+const FAC: f64 = 0.8; // safety factor
+const FAC_MAX: f64 = 2.0;
+
+/// Finite difference approximation of the Jacobian of `Core::derivative`
+/// at `state`, given the derivative `f0` at `state`.
+fn jacobian<const N: usize>(
+    state: &Core<N>,
+    f0: &[f64],
+    rand_state: &RacRandState<N>,
+    interactions: &Interactions<N>,
+    world_parameters: &WorldParameters,
+    parameters: &Parameters<N>,
+) -> Matrix {
+    let y = state.to_flat();
+    let n = y.len();
+    let mut jac = Matrix::zeros(n);
+    let mut y_plus = y.clone();
+    for j in 0..n {
+        let delta = (f64::EPSILON * y[j].abs().max(1e-5)).sqrt();
+        y_plus[j] = y[j] + delta;
+        let f = Core::from_flat(&y_plus)
+            .derivative(
+                rand_state,
+                interactions,
+                world_parameters,
+                parameters,
+            )
+            .to_flat();
+        y_plus[j] = y[j];
+        for i in 0..n {
+            jac.set(i, j, (f[i] - f0[i]) / delta);
+        }
+    }
+    jac
+}
+
+/// Decomposition of `I - GAMMA * h * jac`.
+fn decompose_w(jac: &Matrix, h: f64) -> Option<Lu> {
+    let mut w = jac.clone();
+    w.data.iter_mut().for_each(|x| *x *= -GAMMA * h);
+    for i in 0..w.n {
+        w.set(i, i, w.get(i, i) + 1.0);
+    }
+    Lu::new(w)
+}
+
+/// Integrate the cell over a time step of length `dt`. Step size
+/// control, and the handling of volume exclusion violations, are the
+/// same as in `rkdp5::integrator`. The Jacobian is recomputed after
+/// each accepted step. If `dense` is given, a cubic Hermite
+/// interpolant of each accepted step is added to it.
+pub fn integrator<const N: usize>(
+    mut dt: f64,
+    mut init_state: Core<N>,
+    rand_state: &RacRandState<N>,
+    interactions: &Interactions<N>,
+    world_parameters: &WorldParameters,
+    parameters: &Parameters<N>,
+    contact_data: &Contacts<N>,
+    int_opts: RkOpts,
+    mut dense: Option<&mut DenseOutput<N>>,
+) -> Solution<N> {
+    let RkOpts {
+        max_iters,
+        atol,
+        rtol,
+        init_h_scale,
+    } = int_opts;
+    let f = |state: &Core<N>| {
+        state.derivative(
+            rand_state,
+            interactions,
+            world_parameters,
+            parameters,
+        )
+    };
+
+    let mut h = init_h_scale * dt;
+
+    let mut num_iters: usize = 0;
+    let mut fac_max = FAC_MAX;
+    let mut try_as_last_iter = false;
+    let mut num_rejections: usize = 0;
+    let mut step_sizes = vec![];
+    let mut t = 0.0;
+    let mut f0 = f(&init_state);
+    let mut jac = jacobian(
+        &init_state,
+        &f0.to_flat(),
+        rand_state,
+        interactions,
+        world_parameters,
+        parameters,
+    );
+
+    while num_iters < max_iters && h > 1e-14 {
+        let y0 = init_state.to_flat();
+        let (mut next_state, error) = match decompose_w(&jac, h) {
+            Some(w) => {
+                let k1 = w.solve(&f0.to_flat());
+                let y1 = y0
+                    .iter()
+                    .zip(k1.iter())
+                    .map(|(y, k)| y + h * k)
+                    .collect::<Vec<f64>>();
+                let f1 = f(&Core::from_flat(&y1)).to_flat();
+                let rhs = f1
+                    .iter()
+                    .zip(k1.iter())
+                    .map(|(f, k)| f - 2.0 * k)
+                    .collect::<Vec<f64>>();
+                let k2 = w.solve(&rhs);
+                let next_flat = (0..y0.len())
+                    .map(|i| y0[i] + h * (1.5 * k1[i] + 0.5 * k2[i]))
+                    .collect::<Vec<f64>>();
+                // The embedded first order solution is `y1`.
+                let next_state = Core::from_flat(&next_flat);
+                let next_state_hat = Core::from_flat(&y1);
+                // Equations 4.10, 4.11, Hairer,Wanner&Norsett Solving ODEs Vol. 1
+                let sc = rtol
+                    * init_state.abs().max(&next_state.abs())
+                    + atol;
+                let error = ((next_state - next_state_hat).square()
+                    / sc)
+                    .flat_avg()
+                    .sqrt();
+                (next_state, error)
+            }
+            // `w` is singular, so treat the step as failed.
+            None => (init_state, f64::INFINITY),
+        };
+        let mut h_new = if error.is_finite() {
+            h * min_f64(fac_max, FAC * (1.0 / error).powf(INV_QP1))
+        } else {
+            0.5 * h
+        };
+
+        match (
+            error <= 1.0,
+            next_state.strict_enforce_volume_exclusion(
+                &init_state.poly,
+                contact_data,
+            ),
+        ) {
+            (true, Ok(_)) => {
+                step_sizes.push(h);
+                if let Some(dense) = dense.as_mut() {
+                    dense.push(Segment::Hermite {
+                        t0: t,
+                        h,
+                        y0: init_state,
+                        y1: next_state,
+                        f0,
+                        f1: f(&next_state),
+                    });
+                }
+                t += h;
+                if try_as_last_iter {
+                    assert!((h - dt).abs() < f64::EPSILON);
+                    return Solution {
+                        state: Ok(next_state),
+                        stats: IntStats {
+                            num_iters: num_iters + 1,
+                            num_rejections,
+                            step_sizes,
+                        },
+                    };
+                } else {
+                    fac_max = FAC_MAX;
+                    init_state = next_state;
+                    f0 = f(&init_state);
+                    jac = jacobian(
+                        &init_state,
+                        &f0.to_flat(),
+                        rand_state,
+                        interactions,
+                        world_parameters,
+                        parameters,
+                    );
+                    if h + h_new > dt {
+                        h_new = dt - h;
+                        try_as_last_iter = true;
+                    };
+                    dt -= h;
+                    h = h_new;
+                }
+            }
+            (true, Err(VolExErr::OldVs(s))) => {
+                return Solution {
+                    state: Err(RkErr::VolEx(s)),
+                    stats: IntStats {
+                        num_iters: num_iters + 1,
+                        num_rejections,
+                        step_sizes,
+                    },
+                };
+            }
+            (false, _) | (true, Err(VolExErr::NewVs(_))) => {
+                fac_max = 1.0;
+                num_rejections += 1;
+                h = h_new;
+                try_as_last_iter = false;
+            }
+        }
+
+        num_iters += 1;
+    }
+
+    Solution {
+        state: Err(RkErr::TooManyIters),
+        stats: IntStats {
+            num_iters,
+            num_rejections,
+            step_sizes,
+        },
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright © 2020 Brian Merchant.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::cell::chemistry::RacRandState;
+use crate::cell::states::Core;
+use crate::interactions::Interactions;
+use crate::math::v2d::V2d;
+use crate::parameters::{Parameters, WorldParameters};
+use crate::utils::pcg32::Pcg32;
+use crate::world::NoiseOpts;
+use rand::Rng;
+use rand_distr::StandardNormal;
+
+impl<const N: usize> Core<N> {
+    /// Take an Euler-Maruyama step of length `dt` from this state.
+    /// The deterministic part of the step is given by
+    /// `Core::derivative`. To it is added:
+    ///     * independent additive noise on each vertex coordinate,
+    ///     with standard deviation `noise.vertex_noise * sqrt(dt)`;
+    ///     * multiplicative noise on the activation and deactivation
+    ///     of Rac1 and RhoA at each vertex: a reaction with rate `r`
+    ///     is perturbed by `noise.reaction_noise * r * dW`, where
+    ///     `dW` has variance `dt`.
+    /// Reaction noise moves Rho GTPase between its active and
+    /// inactive forms, so total Rho GTPase is unchanged. It is
+    /// limited so that neither form becomes negative.
+    pub fn euler_maruyama_step(
+        &self,
+        rac_rand: &RacRandState<N>,
+        interactions: &Interactions<N>,
+        world_parameters: &WorldParameters,
+        parameters: &Parameters<N>,
+        dt: f64,
+        noise: &NoiseOpts,
+        rng: &mut Pcg32,
+    ) -> Core<N> {
+        let delta = self.derivative(
+            rac_rand,
+            interactions,
+            world_parameters,
+            parameters,
+        );
+        let mut next = *self + delta.time_step(dt);
+        let mech_state = self.calc_mech_state(parameters);
+        let chem_state = self.calc_chem_state(
+            &mech_state,
+            rac_rand,
+            interactions,
+            parameters,
+        );
+        let sqrt_dt = dt.sqrt();
+        let mut dw =
+            || sqrt_dt * rng.sample::<f64, _>(StandardNormal);
+        for i in 0..N {
+            next.poly[i] = next.poly[i]
+                + noise.vertex_noise * V2d { x: dw(), y: dw() };
+            let rac_noise = noise.reaction_noise
+                * (chem_state.kgtps_rac[i]
+                    * self.rac_inacts[i]
+                    * dw()
+                    - chem_state.kdgtps_rac[i]
+                        * self.rac_acts[i]
+                        * dw());
+            let rac_noise = rac_noise
+                .max(-next.rac_acts[i])
+                .min(next.rac_inacts[i]);
+            next.rac_acts[i] += rac_noise;
+            next.rac_inacts[i] -= rac_noise;
+            let rho_noise = noise.reaction_noise
+                * (chem_state.kgtps_rho[i]
+                    * self.rho_inacts[i]
+                    * dw()
+                    - chem_state.kdgtps_rho[i]
+                        * self.rho_acts[i]
+                        * dw());
+            let rho_noise = rho_noise
+                .max(-next.rho_acts[i])
+                .min(next.rho_inacts[i]);
+            next.rho_acts[i] += rho_noise;
+            next.rho_inacts[i] -= rho_noise;
+        }
+        // The geometry of the perturbed polygon must be recalculated.
+        Core::new(
+            next.poly,
+            next.rac_acts,
+            next.rac_inacts,
+            next.rho_acts,
+            next.rho_inacts,
+        )
+    }
+}
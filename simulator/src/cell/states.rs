@@ -8,72 +8,110 @@ use crate::cell::mechanics::{
     calc_rgtp_forces,
 };
 use crate::interactions::{
-    ContactData, Interactions, RelativeRgtpActivity,
+    Contacts, Interactions, RelativeRgtpActivity,
+};
+use crate::math::geometry::{
+    calc_poly_centroid, lsegs_intersect, LineSeg2D,
 };
-use crate::math::geometry::{lsegs_intersect, LineSeg2D, Poly};
 use crate::math::v2d::{SqP2d, V2d};
 use crate::math::{hill_function3, max_f64};
 use crate::parameters::{Parameters, WorldParameters};
 use crate::utils::{circ_ix_minus, circ_ix_plus};
-use crate::NVERTS;
 use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
 use std::fmt;
 use std::fmt::Display;
 use std::ops::{Add, Div, Mul, Sub};
 
+/// Fraction of the distance to the centroid by which vertices are
+/// pulled in each attempt of `Core::separate_from`.
+const SEPARATION_STEP: f64 = 0.1;
+const MAX_SEPARATION_ITERS: usize = 30;
+
 /// `CoreState` contains all the variables that are simulated between geometric
 /// updates. They are simulated using ODEs which are then integrated using
 /// either the Euler method or Runge-Kutta Dormand-Prince 5 (Matlab's `ode45`).
-#[derive(
-    Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq,
-)]
-pub struct Core {
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Core<const N: usize> {
     /// Polygon representing cell shape.
-    pub poly: [V2d; NVERTS],
+    #[serde(with = "BigArray")]
+    pub poly: [V2d; N],
     /// Fraction of Rac1 active at each vertex.
-    pub rac_acts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rac_acts: [f64; N],
     /// Fraction of Rac1 inactive at each vertex.
-    pub rac_inacts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rac_inacts: [f64; N],
     /// Fraction of RhoA active at each vertex.
-    pub rho_acts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rho_acts: [f64; N],
     /// Fraction of RhoA inactive at each vertex.
-    pub rho_inacts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rho_inacts: [f64; N],
     /// Geometric state resulting from this core state.
-    pub geom: GeomState,
+    pub geom: GeomState<N>,
+}
+
+impl<const N: usize> Default for Core<N> {
+    fn default() -> Self {
+        Core {
+            poly: [V2d::default(); N],
+            rac_acts: [0.0; N],
+            rac_inacts: [0.0; N],
+            rho_acts: [0.0; N],
+            rho_inacts: [0.0; N],
+            geom: Default::default(),
+        }
+    }
 }
 
 /// `DCoreDt` is the derivative of `CoreState`.
-#[derive(
-    Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq,
-)]
-pub struct DCoreDt {
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DCoreDt<const N: usize> {
     /// Polygon representing cell shape.
-    pub poly: [V2d; NVERTS],
+    #[serde(with = "BigArray")]
+    pub poly: [V2d; N],
     /// Fraction of Rac1 active at each vertex.
-    pub rac_acts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rac_acts: [f64; N],
     /// Fraction of Rac1 inactive at each vertex.
-    pub rac_inacts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rac_inacts: [f64; N],
     /// Fraction of RhoA active at each vertex.
-    pub rho_acts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rho_acts: [f64; N],
     /// Fraction of RhoA inactive at each vertex.
-    pub rho_inacts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rho_inacts: [f64; N],
 }
 
-impl From<&Core> for DCoreDt {
-    fn from(_: &Core) -> Self {
+impl<const N: usize> Default for DCoreDt<N> {
+    fn default() -> Self {
+        DCoreDt {
+            poly: [V2d::default(); N],
+            rac_acts: [0.0; N],
+            rac_inacts: [0.0; N],
+            rho_acts: [0.0; N],
+            rho_inacts: [0.0; N],
+        }
+    }
+}
+
+impl<const N: usize> From<&Core<N>> for DCoreDt<N> {
+    fn from(_: &Core<N>) -> Self {
         unimplemented!()
     }
 }
 
-impl DCoreDt {
-    pub fn time_step(&self, dt: f64) -> Core {
-        let mut poly = [V2d::default(); NVERTS];
-        let mut rac_acts = [0.0_f64; NVERTS];
-        let mut rac_inacts = [0.0_f64; NVERTS];
-        let mut rho_acts = [0.0_f64; NVERTS];
-        let mut rho_inacts = [0.0_f64; NVERTS];
+impl<const N: usize> DCoreDt<N> {
+    pub fn time_step(&self, dt: f64) -> Core<N> {
+        let mut poly = [V2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             poly[i] = dt * self.poly[i];
             rac_acts[i] = dt * self.rac_acts[i];
             rac_inacts[i] = dt * self.rac_inacts[i];
@@ -83,37 +121,58 @@ impl DCoreDt {
 
         Core::new(poly, rac_acts, rac_inacts, rho_acts, rho_inacts)
     }
+
+    /// The derivative as a flat vector, ordered as in
+    /// `Core::to_flat`.
+    pub fn to_flat(&self) -> Vec<f64> {
+        self.time_step(1.0).to_flat()
+    }
 }
 
 /// `PowCoreState` results from multiplication/division of `CoreState`s, or
 /// taking their power using `CoreState::powi`.
-#[derive(
-    Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq,
-)]
-pub struct SqCore {
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SqCore<const N: usize> {
     /// Polygon representing cell shape.
-    pub poly: [SqP2d; NVERTS],
+    #[serde(with = "BigArray")]
+    pub poly: [SqP2d; N],
     /// Fraction of Rac1 active at each vertex.
-    pub rac_acts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rac_acts: [f64; N],
     /// Fraction of Rac1 inactive at each vertex.
-    pub rac_inacts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rac_inacts: [f64; N],
     /// Fraction of RhoA active at each vertex.
-    pub rho_acts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rho_acts: [f64; N],
     /// Fraction of RhoA inactive at each vertex.
-    pub rho_inacts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rho_inacts: [f64; N],
 }
 
-impl Add for Core {
-    type Output = Core;
+impl<const N: usize> Default for SqCore<N> {
+    fn default() -> Self {
+        SqCore {
+            poly: [SqP2d::default(); N],
+            rac_acts: [0.0; N],
+            rac_inacts: [0.0; N],
+            rho_acts: [0.0; N],
+            rho_inacts: [0.0; N],
+        }
+    }
+}
+
+impl<const N: usize> Add for Core<N> {
+    type Output = Core<N>;
 
-    fn add(self, rhs: Core) -> Core {
-        let mut poly = [V2d::default(); NVERTS];
-        let mut rac_acts = [0.0_f64; NVERTS];
-        let mut rac_inacts = [0.0_f64; NVERTS];
-        let mut rho_acts = [0.0_f64; NVERTS];
-        let mut rho_inacts = [0.0_f64; NVERTS];
+    fn add(self, rhs: Core<N>) -> Core<N> {
+        let mut poly = [V2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             poly[i] = self.poly[i] + rhs.poly[i];
             rac_acts[i] = self.rac_acts[i] + rhs.rac_acts[i];
             rac_inacts[i] = self.rac_inacts[i] + rhs.rac_inacts[i];
@@ -125,17 +184,17 @@ impl Add for Core {
     }
 }
 
-impl Mul for Core {
-    type Output = SqCore;
+impl<const N: usize> Mul for Core<N> {
+    type Output = SqCore<N>;
 
-    fn mul(self, rhs: Core) -> Self::Output {
-        let mut poly = [SqP2d::default(); NVERTS];
-        let mut rac_acts = [0.0_f64; NVERTS];
-        let mut rac_inacts = [0.0_f64; NVERTS];
-        let mut rho_acts = [0.0_f64; NVERTS];
-        let mut rho_inacts = [0.0_f64; NVERTS];
+    fn mul(self, rhs: Core<N>) -> Self::Output {
+        let mut poly = [SqP2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             poly[i] = self.poly[i] * rhs.poly[i];
             rac_acts[i] = self.rac_acts[i] * rhs.rac_acts[i];
             rac_inacts[i] = self.rac_inacts[i] * rhs.rac_inacts[i];
@@ -153,17 +212,17 @@ impl Mul for Core {
     }
 }
 
-impl Mul<&Core> for &Core {
-    type Output = SqCore;
+impl<const N: usize> Mul<&Core<N>> for &Core<N> {
+    type Output = SqCore<N>;
 
-    fn mul(self, rhs: &Core) -> Self::Output {
-        let mut poly = [SqP2d::default(); NVERTS];
-        let mut rac_acts = [0.0_f64; NVERTS];
-        let mut rac_inacts = [0.0_f64; NVERTS];
-        let mut rho_acts = [0.0_f64; NVERTS];
-        let mut rho_inacts = [0.0_f64; NVERTS];
+    fn mul(self, rhs: &Core<N>) -> Self::Output {
+        let mut poly = [SqP2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             poly[i] = self.poly[i] * rhs.poly[i];
             rac_acts[i] = self.rac_acts[i] * rhs.rac_acts[i];
             rac_inacts[i] = self.rac_inacts[i] * rhs.rac_inacts[i];
@@ -181,17 +240,17 @@ impl Mul<&Core> for &Core {
     }
 }
 
-impl Sub for Core {
-    type Output = Core;
+impl<const N: usize> Sub for Core<N> {
+    type Output = Core<N>;
 
-    fn sub(self, rhs: Core) -> Core {
-        let mut poly = [V2d::default(); NVERTS];
-        let mut rac_acts = [0.0_f64; NVERTS];
-        let mut rac_inacts = [0.0_f64; NVERTS];
-        let mut rho_acts = [0.0_f64; NVERTS];
-        let mut rho_inacts = [0.0_f64; NVERTS];
+    fn sub(self, rhs: Core<N>) -> Core<N> {
+        let mut poly = [V2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             poly[i] = self.poly[i] - rhs.poly[i];
             rac_acts[i] = self.rac_acts[i] - rhs.rac_acts[i];
             rac_inacts[i] = self.rac_inacts[i] - rhs.rac_inacts[i];
@@ -203,17 +262,17 @@ impl Sub for Core {
     }
 }
 
-impl Mul<f64> for Core {
-    type Output = Core;
+impl<const N: usize> Mul<f64> for Core<N> {
+    type Output = Core<N>;
 
     fn mul(self, rhs: f64) -> Self::Output {
-        let mut poly = [V2d::default(); NVERTS];
-        let mut rac_acts = [0.0_f64; NVERTS];
-        let mut rac_inacts = [0.0_f64; NVERTS];
-        let mut rho_acts = [0.0_f64; NVERTS];
-        let mut rho_inacts = [0.0_f64; NVERTS];
+        let mut poly = [V2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             poly[i] = self.poly[i].scale(rhs);
             rac_acts[i] = self.rac_acts[i] * rhs;
             rac_inacts[i] = self.rac_inacts[i] * rhs;
@@ -225,25 +284,25 @@ impl Mul<f64> for Core {
     }
 }
 
-impl Mul<Core> for f64 {
-    type Output = Core;
+impl<const N: usize> Mul<Core<N>> for f64 {
+    type Output = Core<N>;
 
-    fn mul(self, rhs: Core) -> Self::Output {
+    fn mul(self, rhs: Core<N>) -> Self::Output {
         rhs * self
     }
 }
 
-impl Add<f64> for Core {
-    type Output = Core;
+impl<const N: usize> Add<f64> for Core<N> {
+    type Output = Core<N>;
 
     fn add(self, rhs: f64) -> Self::Output {
-        let mut poly = [V2d::default(); NVERTS];
-        let mut rac_acts = [0.0_f64; NVERTS];
-        let mut rac_inacts = [0.0_f64; NVERTS];
-        let mut rho_acts = [0.0_f64; NVERTS];
-        let mut rho_inacts = [0.0_f64; NVERTS];
+        let mut poly = [V2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             poly[i] = self.poly[i] + rhs;
             rac_acts[i] = self.rac_acts[i] + rhs;
             rac_inacts[i] = self.rac_inacts[i] + rhs;
@@ -255,25 +314,25 @@ impl Add<f64> for Core {
     }
 }
 
-impl Add<Core> for f64 {
-    type Output = Core;
+impl<const N: usize> Add<Core<N>> for f64 {
+    type Output = Core<N>;
 
-    fn add(self, rhs: Core) -> Self::Output {
+    fn add(self, rhs: Core<N>) -> Self::Output {
         rhs + self
     }
 }
 
-impl Div<Core> for SqCore {
-    type Output = Core;
+impl<const N: usize> Div<Core<N>> for SqCore<N> {
+    type Output = Core<N>;
 
-    fn div(self, rhs: Core) -> Self::Output {
-        let mut poly = [V2d::default(); NVERTS];
-        let mut rac_acts = [0.0_f64; NVERTS];
-        let mut rac_inacts = [0.0_f64; NVERTS];
-        let mut rho_acts = [0.0_f64; NVERTS];
-        let mut rho_inacts = [0.0_f64; NVERTS];
+    fn div(self, rhs: Core<N>) -> Self::Output {
+        let mut poly = [V2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             poly[i] = self.poly[i] / rhs.poly[i];
             rac_acts[i] = self.rac_acts[i] / rhs.rac_acts[i];
             rac_inacts[i] = self.rac_inacts[i] / rhs.rac_inacts[i];
@@ -286,25 +345,41 @@ impl Div<Core> for SqCore {
 }
 
 /// Records the mechanical state of a cell.
-#[derive(
-    Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq,
-)]
-pub struct MechState {
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct MechState<const N: usize> {
     /// Strain each edge is under, where resting edge length is
     /// defined in the cell's parameters.
-    pub edge_strains: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub edge_strains: [f64; N],
     /// Forces on each vertex due to Rho GTPase activity.
-    pub rgtp_forces: [V2d; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rgtp_forces: [V2d; N],
     /// Forces on each vertex due to cytoplasmic pressure.
-    pub cyto_forces: [V2d; NVERTS],
+    #[serde(with = "BigArray")]
+    pub cyto_forces: [V2d; N],
     /// Forces on each vertex due to edge-edge (elastic) forces.
-    pub edge_forces: [V2d; NVERTS],
+    #[serde(with = "BigArray")]
+    pub edge_forces: [V2d; N],
     /// Average of the strain in edges which are under tension (i.e.
     /// they are longer than their initial resting edge length.
     pub avg_tens_strain: f64,
     /// Sum of all forces that are acting on a vertex, except for
     /// adhesion, which comes from interaction information.
-    pub sum_forces: [V2d; NVERTS],
+    #[serde(with = "BigArray")]
+    pub sum_forces: [V2d; N],
+}
+
+impl<const N: usize> Default for MechState<N> {
+    fn default() -> Self {
+        MechState {
+            edge_strains: [0.0; N],
+            rgtp_forces: [V2d::default(); N],
+            cyto_forces: [V2d::default(); N],
+            edge_forces: [V2d::default(); N],
+            avg_tens_strain: Default::default(),
+            sum_forces: [V2d::default(); N],
+        }
+    }
 }
 
 /// Calculates the various rates necessary to define the ODEs
@@ -319,60 +394,94 @@ pub struct MechState {
 ///     * `x_tens`: "tension" factor that affects Rac1 activation
 /// rate, calculated based on average tensile strain in cell (i.e.
 /// how stretched the cell is).
-#[derive(
-    Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq,
-)]
-pub struct ChemState {
-    pub kdgtps_rac: [f64; NVERTS],
-    pub kgtps_rac: [f64; NVERTS],
-    pub rac_act_net_fluxes: [f64; NVERTS],
-    pub rac_inact_net_fluxes: [f64; NVERTS],
-    pub kdgtps_rho: [f64; NVERTS],
-    pub kgtps_rho: [f64; NVERTS],
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ChemState<const N: usize> {
+    #[serde(with = "BigArray")]
+    pub kdgtps_rac: [f64; N],
+    #[serde(with = "BigArray")]
+    pub kgtps_rac: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rac_act_net_fluxes: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rac_inact_net_fluxes: [f64; N],
+    #[serde(with = "BigArray")]
+    pub kdgtps_rho: [f64; N],
+    #[serde(with = "BigArray")]
+    pub kgtps_rho: [f64; N],
     pub rac_cyto: f64,
     pub rho_cyto: f64,
-    pub rho_act_net_fluxes: [f64; NVERTS],
-    pub rho_inact_net_fluxes: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rho_act_net_fluxes: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rho_inact_net_fluxes: [f64; N],
     pub x_tens: f64,
 }
 
-#[derive(
-    Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq,
-)]
-pub struct GeomState {
+impl<const N: usize> Default for ChemState<N> {
+    fn default() -> Self {
+        ChemState {
+            kdgtps_rac: [0.0; N],
+            kgtps_rac: [0.0; N],
+            rac_act_net_fluxes: [0.0; N],
+            rac_inact_net_fluxes: [0.0; N],
+            kdgtps_rho: [0.0; N],
+            kgtps_rho: [0.0; N],
+            rac_cyto: Default::default(),
+            rho_cyto: Default::default(),
+            rho_act_net_fluxes: [0.0; N],
+            rho_inact_net_fluxes: [0.0; N],
+            x_tens: Default::default(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct GeomState<const N: usize> {
     /// Unit edge vectors which point from position of vertex `vi`
     /// to position of vertex `vi + 1`, where `vi + 1` is calculated
-    /// modulo `NVERTS`. Note that an edge is defined by its "lower"
-    /// index, modulo `NVERTS`. That is, the edge `(0, 1)`, is
+    /// modulo `N`. Note that an edge is defined by its "lower"
+    /// index, modulo `N`. That is, the edge `(0, 1)`, is
     /// different from the edge `(1, 2)`, and `(0, 1)` is also
-    /// different from `(15, 0)` (assuming that `NVERTS == 16` in
+    /// different from `(15, 0)` (assuming that `N == 16` in
     /// this example).
-    pub unit_edge_vecs: [V2d; NVERTS],
+    #[serde(with = "BigArray")]
+    pub unit_edge_vecs: [V2d; N],
     /// Length of edges. Each edge is defined by its smallest vertex.
-    pub edge_lens: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub edge_lens: [f64; N],
     /// Inward pointing unit vectors at each vertex. These are
     /// calculated so that they bisect the angle between the two
     /// edges which meet at a vertex.
-    pub unit_in_vecs: [V2d; NVERTS],
+    #[serde(with = "BigArray")]
+    pub unit_in_vecs: [V2d; N],
 }
 
-impl From<&[V2d; NVERTS]> for GeomState {
-    fn from(poly: &[V2d; 16]) -> Self {
+impl<const N: usize> Default for GeomState<N> {
+    fn default() -> Self {
+        GeomState {
+            unit_edge_vecs: [V2d::default(); N],
+            edge_lens: [0.0; N],
+            unit_in_vecs: [V2d::default(); N],
+        }
+    }
+}
+
+impl<const N: usize> From<&[V2d; N]> for GeomState<N> {
+    fn from(poly: &[V2d; N]) -> Self {
         // Calculate edge vectors of a polygon.
         let evs = calc_edge_vecs(poly);
         // Calculate magnitude of each edge vec, to get its length.
-        let mut edge_lens = [0.0_f64; NVERTS];
-        (0..NVERTS).for_each(|i| edge_lens[i] = (&evs[i]).mag());
+        let mut edge_lens = [0.0_f64; N];
+        (0..N).for_each(|i| edge_lens[i] = evs[i].mag());
         // Divide each edge vector by its magnitude to get the
         // corresponding unit vector.
-        let mut unit_edge_vecs = [V2d::default(); NVERTS];
-        (0..NVERTS)
-            .for_each(|i| unit_edge_vecs[i] = (&evs[i]).unitize());
+        let mut unit_edge_vecs = [V2d::default(); N];
+        (0..N).for_each(|i| unit_edge_vecs[i] = evs[i].unitize());
         // Given two unit edge vectors, find the vector which points
         // into the polygon and bisects the angle
-        let mut unit_in_vecs = [V2d::default(); NVERTS];
-        (0..NVERTS).for_each(|i| {
-            let im1 = circ_ix_minus(i, NVERTS);
+        let mut unit_in_vecs = [V2d::default(); N];
+        (0..N).for_each(|i| {
+            let im1 = circ_ix_minus(i, N);
             let tangent =
                 (unit_edge_vecs[i] + unit_edge_vecs[im1]).unitize();
             unit_in_vecs[i] = tangent.normal();
@@ -386,10 +495,10 @@ impl From<&[V2d; NVERTS]> for GeomState {
     }
 }
 
-pub fn fmt_var_arr<T: fmt::Display>(
+pub fn fmt_var_arr<T: fmt::Display, const N: usize>(
     f: &mut fmt::Formatter<'_>,
     description: &str,
-    vars: &[T; NVERTS],
+    vars: &[T; N],
 ) -> fmt::Result {
     let contents = vars
         .iter()
@@ -399,7 +508,7 @@ pub fn fmt_var_arr<T: fmt::Display>(
     writeln!(f, "{}: [{}]", description, contents)
 }
 
-impl Display for Core {
+impl<const N: usize> Display for Core<N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_var_arr(f, "vertex_coords", &self.poly)?;
         fmt_var_arr(f, "rac_acts", &self.rac_acts)?;
@@ -409,7 +518,7 @@ impl Display for Core {
     }
 }
 
-impl Display for GeomState {
+impl<const N: usize> Display for GeomState<N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_var_arr(f, "edge_lens", &self.edge_lens)?;
         fmt_var_arr(f, "uivs", &self.unit_in_vecs)?;
@@ -417,7 +526,7 @@ impl Display for GeomState {
     }
 }
 
-impl Display for MechState {
+impl<const N: usize> Display for MechState<N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt_var_arr(f, "rgtp_forces", &self.rgtp_forces)?;
         fmt_var_arr(f, "edge_strains", &self.edge_strains)?;
@@ -428,7 +537,7 @@ impl Display for MechState {
     }
 }
 
-impl Display for ChemState {
+impl<const N: usize> Display for ChemState<N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "x_tens: {}", self.x_tens)?;
         fmt_var_arr(f, "kgtps_rac", &self.kgtps_rac)?;
@@ -438,11 +547,11 @@ impl Display for ChemState {
     }
 }
 
-impl Core {
+impl<const N: usize> Core<N> {
     pub fn calc_mech_state(
         &self,
-        parameters: &Parameters,
-    ) -> MechState {
+        parameters: &Parameters<N>,
+    ) -> MechState<N> {
         let GeomState {
             unit_edge_vecs,
             edge_lens,
@@ -463,8 +572,8 @@ impl Core {
             parameters.stiffness_cyto,
         );
         // Calculate strain in each edge.
-        let mut edge_strains = [0.0_f64; NVERTS];
-        (0..NVERTS).for_each(|i| {
+        let mut edge_strains = [0.0_f64; N];
+        (0..N).for_each(|i| {
             edge_strains[i] =
                 (edge_lens[i] / parameters.rest_edge_len) - 1.0
         });
@@ -484,13 +593,13 @@ impl Core {
             .iter()
             .map(|&es| if es < 0.0 { 0.0 } else { es })
             .sum::<f64>()
-            / NVERTS as f64;
+            / N as f64;
         // Sum of all the non-adhesive forces acting on the cell.
-        let mut sum_forces = [V2d::default(); NVERTS];
-        (0..NVERTS).for_each(|i| {
+        let mut sum_forces = [V2d::default(); N];
+        (0..N).for_each(|i| {
             sum_forces[i] =
                 rgtp_forces[i] + cyto_forces[i] + edge_forces[i]
-                    - edge_forces[circ_ix_minus(i, NVERTS)];
+                    - edge_forces[circ_ix_minus(i, N)];
         });
         MechState {
             edge_strains,
@@ -504,20 +613,20 @@ impl Core {
 
     pub fn calc_chem_state(
         &self,
-        mech_state: &MechState,
-        rac_rand_state: &RacRandState,
-        interactions: &Interactions,
-        parameters: &Parameters,
-    ) -> ChemState {
+        mech_state: &MechState<N>,
+        rac_rand_state: &RacRandState<N>,
+        interactions: &Interactions<N>,
+        parameters: &Parameters<N>,
+    ) -> ChemState<N> {
         let GeomState { edge_lens, .. } = self.geom;
         // Need to calculate average length of edges meeting at
         // a vertex in order to roughly approximate diffusion related
         // flux of Rho GTPase from neighbouring vertices. Provides
         // an approximation for the length of the membrane abstracted
         // by the edges that meet at that vertex.
-        let mut avg_edge_lens: [f64; NVERTS] = [0.0_f64; NVERTS];
-        (0..NVERTS).for_each(|i| {
-            let im1 = circ_ix_minus(i, NVERTS);
+        let mut avg_edge_lens: [f64; N] = [0.0_f64; N];
+        (0..N).for_each(|i| {
+            let im1 = circ_ix_minus(i, N);
             avg_edge_lens[i] = (edge_lens[i] + edge_lens[im1]) / 2.0;
         });
 
@@ -532,14 +641,22 @@ impl Core {
         let conc_rho_inacts =
             calc_conc_rgtps(&avg_edge_lens, &self.rho_inacts);
 
+        // Contact with a wall of the boundary acts like CIL.
+        let mut x_cils = interactions.x_cils;
+        x_cils
+            .iter_mut()
+            .zip(interactions.x_bdrys.iter())
+            .for_each(|(x_cil, x_bdry)| *x_cil += x_bdry);
         let kgtps_rac = calc_kgtps_rac(
             &self.rac_acts,
             &conc_rac_acts,
             &rac_rand_state.x_rands,
             &interactions.x_coas,
-            &interactions.x_cils,
+            &x_cils,
             &interactions.x_chem_attrs,
             &interactions.x_cals,
+            &interactions.x_srcs.rac_acts,
+            &interactions.x_srcs.rac_autos,
             parameters.kgtp_rac,
             parameters.kgtp_rac_auto,
             parameters.halfmax_vertex_rgtp_conc,
@@ -555,7 +672,8 @@ impl Core {
         let kdgtps_rac = calc_kdgtps_rac(
             &self.rac_acts,
             &conc_rho_acts,
-            &interactions.x_cils,
+            &x_cils,
+            &interactions.x_srcs.rac_inacts,
             x_tens,
             parameters.kdgtp_rac,
             parameters.kdgtp_rho_on_rac,
@@ -564,7 +682,8 @@ impl Core {
         let kgtps_rho = calc_kgtps_rho(
             &self.rho_acts,
             &conc_rho_acts,
-            &interactions.x_cils,
+            &x_cils,
+            &interactions.x_srcs.rho_acts,
             parameters.kgtp_rho,
             parameters.halfmax_vertex_rgtp_conc,
             parameters.kgtp_rho_auto,
@@ -572,6 +691,7 @@ impl Core {
         let kdgtps_rho = calc_kdgtps_rho(
             &self.rho_acts,
             &conc_rac_acts,
+            &interactions.x_srcs.rho_inacts,
             parameters.kdgtp_rho,
             parameters.kdgtp_rac_on_rho,
             parameters.halfmax_vertex_rgtp_conc,
@@ -622,11 +742,11 @@ impl Core {
     /// vertex motion and biochemistry.
     pub fn derivative(
         &self,
-        rac_rand_state: &RacRandState,
-        interactions: &Interactions,
+        rac_rand_state: &RacRandState<N>,
+        interactions: &Interactions<N>,
         world_parameters: &WorldParameters,
-        parameters: &Parameters,
-    ) -> DCoreDt {
+        parameters: &Parameters<N>,
+    ) -> DCoreDt<N> {
         //TODO: is it necessary to recalculate chem/mech/geom states in
         // `derivative`, if we have saved this info in the `Cell` struct?
         // What is the importance of `interactions`---might it have changed
@@ -639,7 +759,7 @@ impl Core {
             parameters,
         );
         let mut delta = DCoreDt::default();
-        for i in 0..NVERTS {
+        for i in 0..N {
             // rate of rac deactivation * current fraction of rac active
             let inactivated_rac =
                 chem_state.kdgtps_rac[i] * self.rac_acts[i];
@@ -688,16 +808,19 @@ impl Core {
                 + vertex_rho_inact_flux
                 - delta_rho_activated;
             delta.poly[i] = (1.0 / world_parameters.vertex_eta)
-                * (mech_state.sum_forces[i] + interactions.x_adhs[i]);
+                * (mech_state.sum_forces[i]
+                    + interactions.x_adhs[i]
+                    + interactions.x_srcs.inward_forces[i]
+                        * self.geom.unit_in_vecs[i]);
         }
         delta
     }
 
     pub fn init(
-        poly: [V2d; NVERTS],
-        init_rac: RgtpDistribution,
-        init_rho: RgtpDistribution,
-    ) -> Core {
+        poly: [V2d; N],
+        init_rac: RgtpDistribution<N>,
+        init_rho: RgtpDistribution<N>,
+    ) -> Core<N> {
         Core::new(
             poly,
             init_rac.active,
@@ -708,12 +831,12 @@ impl Core {
     }
 
     pub fn new(
-        poly: [V2d; NVERTS],
-        rac_acts: [f64; NVERTS],
-        rac_inacts: [f64; NVERTS],
-        rho_acts: [f64; NVERTS],
-        rho_inacts: [f64; NVERTS],
-    ) -> Core {
+        poly: [V2d; N],
+        rac_acts: [f64; N],
+        rac_inacts: [f64; N],
+        rho_acts: [f64; N],
+        rho_inacts: [f64; N],
+    ) -> Core<N> {
         let geom = GeomState::from(&poly);
         Core {
             poly,
@@ -725,14 +848,14 @@ impl Core {
         }
     }
 
-    pub fn abs(&self) -> Core {
-        let mut vertex_coords = [V2d::default(); NVERTS];
-        let mut rac_acts = [0.0_f64; NVERTS];
-        let mut rac_inacts = [0.0_f64; NVERTS];
-        let mut rho_acts = [0.0_f64; NVERTS];
-        let mut rho_inacts = [0.0_f64; NVERTS];
+    pub fn abs(&self) -> Core<N> {
+        let mut vertex_coords = [V2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             vertex_coords[i] = vertex_coords[i].abs();
             rac_acts[i] = self.rac_acts[i].abs();
             rac_inacts[i] = self.rac_inacts[i].abs();
@@ -749,18 +872,18 @@ impl Core {
         )
     }
 
-    pub fn square(&self) -> SqCore {
+    pub fn square(&self) -> SqCore<N> {
         self * self
     }
 
-    pub fn max(&self, other: &Core) -> Core {
-        let mut poly = [V2d::default(); NVERTS];
-        let mut rac_acts = [0.0_f64; NVERTS];
-        let mut rac_inacts = [0.0_f64; NVERTS];
-        let mut rho_acts = [0.0_f64; NVERTS];
-        let mut rho_inacts = [0.0_f64; NVERTS];
+    pub fn max(&self, other: &Core<N>) -> Core<N> {
+        let mut poly = [V2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             poly[i] = poly[i].max(&other.poly[i]);
             rac_acts[i] =
                 max_f64(self.rac_acts[i], other.rac_acts[i]);
@@ -779,9 +902,9 @@ impl Core {
     /// at this vertex.
     pub fn calc_relative_rgtp_activity(
         &self,
-        parameters: &Parameters,
-    ) -> [RelativeRgtpActivity; NVERTS] {
-        let mut r = [RelativeRgtpActivity::RhoDominant(0.0); NVERTS];
+        parameters: &Parameters<N>,
+    ) -> [RelativeRgtpActivity; N] {
+        let mut r = [RelativeRgtpActivity::RhoDominant(0.0); N];
         self.rac_acts
             .iter()
             .zip(self.rho_acts.iter())
@@ -848,8 +971,8 @@ impl Core {
 
     pub fn strict_enforce_volume_exclusion(
         &mut self,
-        old_vs: &[V2d; NVERTS],
-        contacts: &[ContactData],
+        old_vs: &[V2d; N],
+        contacts: &Contacts<N>,
     ) -> Result<(), VolExErr> {
         confirm_volume_exclusion(&old_vs, &contacts, "old_vs")
             .map_err(VolExErr::OldVs)?;
@@ -863,36 +986,89 @@ impl Core {
 
     pub fn enforce_volume_exclusion(
         &mut self,
-        old_vs: &[V2d; NVERTS],
-        contacts: &[ContactData],
+        old_vs: &[V2d; N],
+        contacts: &Contacts<N>,
     ) {
-        for vi in 0..NVERTS {
-            let ui = circ_ix_minus(vi, NVERTS);
-            let wi = circ_ix_plus(vi, NVERTS);
+        for vi in 0..N {
+            let ui = circ_ix_minus(vi, N);
+            let wi = circ_ix_plus(vi, N);
             let u = self.poly[ui];
             let v = self.poly[vi];
             let w = self.poly[wi];
             let old_u = old_vs[ui];
             let old_v = old_vs[vi];
             let old_w = old_vs[wi];
-            for contact in contacts {
-                for other in contact.poly.edges.iter() {
-                    if lsegs_intersect(&v, &w, other)
-                        || lsegs_intersect(&u, &v, other)
-                    {
-                        let (new_u, new_v, new_w) =
-                            fix_edge_intersection(
-                                (old_u, old_v, old_w),
-                                (u, v, w),
-                                other,
-                            );
-                        self.poly[ui] = new_u;
-                        self.poly[vi] = new_v;
-                        self.poly[wi] = new_w;
-                    }
+            let others = contacts
+                .cells
+                .iter()
+                .flat_map(|contact| contact.poly.edges.iter())
+                .chain(contacts.walls.iter())
+                .chain(contacts.obstacles.iter());
+            for other in others {
+                if lsegs_intersect(&v, &w, other)
+                    || lsegs_intersect(&u, &v, other)
+                {
+                    let (new_u, new_v, new_w) = fix_edge_intersection(
+                        (old_u, old_v, old_w),
+                        (u, v, w),
+                        other,
+                    );
+                    self.poly[ui] = new_u;
+                    self.poly[vi] = new_v;
+                    self.poly[wi] = new_w;
+                }
+            }
+        }
+    }
+
+    /// Pull the vertices of edges which intersect `contacts` towards
+    /// the cell's centroid, a little at a time, until volume
+    /// exclusion is satisfied or `MAX_SEPARATION_ITERS` attempts
+    /// have been made. Returns whether volume exclusion is
+    /// satisfied.
+    pub fn separate_from(&mut self, contacts: &Contacts<N>) -> bool {
+        let centroid = calc_poly_centroid(&self.poly);
+        let mut separated = false;
+        let mut moved = false;
+        for _ in 0..MAX_SEPARATION_ITERS {
+            let mut pull = [false; N];
+            for vi in 0..N {
+                let wi = circ_ix_plus(vi, N);
+                if violates_volume_exclusion(
+                    &self.poly[vi],
+                    &self.poly[wi],
+                    contacts,
+                )
+                .is_some()
+                {
+                    pull[vi] = true;
+                    pull[wi] = true;
                 }
             }
+            if !pull.contains(&true) {
+                separated = true;
+                break;
+            }
+            for (v, _) in self
+                .poly
+                .iter_mut()
+                .zip(pull.iter())
+                .filter(|(_, &p)| p)
+            {
+                *v = *v + (centroid - *v).scale(SEPARATION_STEP);
+            }
+            moved = true;
         }
+        if moved {
+            *self = Core::new(
+                self.poly,
+                self.rac_acts,
+                self.rac_inacts,
+                self.rho_acts,
+                self.rho_inacts,
+            );
+        }
+        separated
     }
 
     //TODO(BM): automate generation of `num_vars` using proc macro.
@@ -900,13 +1076,13 @@ impl Core {
     /// holds. That is: the number of variables per vertex, times the
     /// number of all the vertices in a cell.
     pub fn num_vars() -> u32 {
-        (NVERTS * 6) as u32
+        (N * 6) as u32
     }
 
     pub fn flat_sum(&self) -> f64 {
         let mut r: f64 = 0.0;
 
-        for i in 0..(NVERTS) {
+        for i in 0..(N) {
             r += self.poly[i].x + self.poly[i].y;
             r += self.rac_acts[i];
             r += self.rac_inacts[i];
@@ -920,21 +1096,68 @@ impl Core {
     pub fn flat_avg(&self) -> f64 {
         self.flat_sum() / (Self::num_vars() as f64)
     }
+
+    /// The variables of the core state, as a flat vector. The
+    /// variables of vertex `i` are at `6 * i..6 * (i + 1)`.
+    pub fn to_flat(&self) -> Vec<f64> {
+        let mut r = Vec::with_capacity(N * 6);
+        for i in 0..N {
+            r.extend([
+                self.poly[i].x,
+                self.poly[i].y,
+                self.rac_acts[i],
+                self.rac_inacts[i],
+                self.rho_acts[i],
+                self.rho_inacts[i],
+            ]);
+        }
+        r
+    }
+
+    /// Inverse of `Core::to_flat`.
+    pub fn from_flat(xs: &[f64]) -> Core<N> {
+        let mut poly = [V2d::default(); N];
+        let mut rac_acts = [0.0_f64; N];
+        let mut rac_inacts = [0.0_f64; N];
+        let mut rho_acts = [0.0_f64; N];
+        let mut rho_inacts = [0.0_f64; N];
+        for i in 0..N {
+            let vs = &xs[6 * i..6 * (i + 1)];
+            poly[i] = V2d { x: vs[0], y: vs[1] };
+            rac_acts[i] = vs[2];
+            rac_inacts[i] = vs[3];
+            rho_acts[i] = vs[4];
+            rho_inacts[i] = vs[5];
+        }
+        Core::new(poly, rac_acts, rac_inacts, rho_acts, rho_inacts)
+    }
 }
 
-fn violates_volume_exclusion(
+fn violates_volume_exclusion<const N: usize>(
     test_v: &V2d,
     test_w: &V2d,
-    contacts: &[ContactData],
-) -> Option<(Poly, V2d, V2d)> {
-    for contact in contacts {
+    contacts: &Contacts<N>,
+) -> Option<(Option<usize>, Vec<V2d>, V2d, V2d)> {
+    for contact in contacts.cells.iter() {
         for other in contact.poly.edges.iter() {
             if lsegs_intersect(test_v, test_w, other) {
-                return Some((contact.poly, other.p0, other.p1));
+                return Some((
+                    Some(contact.oci),
+                    contact.poly.verts.to_vec(),
+                    other.p0,
+                    other.p1,
+                ));
             }
         }
     }
-    None
+    contacts
+        .walls
+        .iter()
+        .chain(contacts.obstacles.iter())
+        .find(|other| lsegs_intersect(test_v, test_w, other))
+        .map(|other| {
+            (None, vec![other.p0, other.p1], other.p0, other.p1)
+        })
 }
 
 fn fix_edge_intersection(
@@ -968,24 +1191,36 @@ fn fix_edge_intersection(
     }
 }
 
+/// Information about an edge of a cell's polygon which intersects
+/// another cell, or a wall of the boundary.
+#[derive(Clone, Debug)]
+pub struct VolExViolation {
+    /// Index of the vertex starting the offending edge.
+    pub vert_ix: usize,
+    /// Index of the cell intersected by the offending edge, or
+    /// `None` if it intersects a wall.
+    pub other_cell_ix: Option<usize>,
+    pub msg: String,
+}
+
 pub enum VolExErr {
-    OldVs(String),
-    NewVs(String),
+    OldVs(VolExViolation),
+    NewVs(VolExViolation),
 }
 
-pub fn confirm_volume_exclusion(
-    vs: &[V2d; NVERTS],
-    contacts: &[ContactData],
+pub fn confirm_volume_exclusion<const N: usize>(
+    vs: &[V2d; N],
+    contacts: &Contacts<N>,
     msg: &str,
-) -> Result<(), String> {
+) -> Result<(), VolExViolation> {
     use crate::math::v2d::poly_to_string;
     for (vi, v) in vs.iter().enumerate() {
-        let wi = circ_ix_plus(vi, NVERTS);
+        let wi = circ_ix_plus(vi, N);
         let w = vs[wi];
-        if let Some((p, a, b)) =
+        if let Some((oci, p, a, b)) =
             violates_volume_exclusion(v, &w, contacts)
         {
-            return Err(format!(
+            let msg = format!(
                 "{} violates volume exclusion.\n\
                     vs = {}, \n\
                     other poly = {}  \n\
@@ -993,11 +1228,16 @@ pub fn confirm_volume_exclusion(
                     a = {}, b = {}",
                 msg,
                 v,
-                &poly_to_string(&p.verts),
+                &poly_to_string(&p),
                 &poly_to_string(vs),
                 a,
                 b,
-            ));
+            );
+            return Err(VolExViolation {
+                vert_ix: vi,
+                other_cell_ix: oci,
+                msg,
+            });
         }
     }
     Ok(())
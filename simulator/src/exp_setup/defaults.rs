@@ -1,5 +1,4 @@
 use crate::cell::chemistry::{distrib_gens, RgtpDistribution};
-use crate::exp_setup::markers::ALL;
 use crate::parameters::quantity::{
     Force, General, Length, Quantity, Stress, Time, Tinv, Viscosity,
 };
@@ -7,7 +6,6 @@ use crate::parameters::{
     CharQuantities, RawCoaParams, RawInteractionParams,
     RawParameters, RawPhysicalContactParams, RawWorldParameters,
 };
-use crate::NVERTS;
 use once_cell::sync::Lazy;
 
 //TODO: Document all justifications for characteristic quantities, rather than
@@ -42,27 +40,28 @@ pub static CHAR_QUANTS: Lazy<CharQuantities> =
 pub static MAX_CELL_V: Lazy<General> =
     Lazy::new(|| Length(3.0).micro().g() * Tinv(1.0 / 60.0).g());
 pub const ADH_INDEX: f64 = 0.99;
-pub static ADH_MAG: Lazy<Force> = Lazy::new(|| {
+/// Default adhesion magnitude, for cells with `nverts` vertices.
+pub fn adh_mag(nverts: usize) -> Force {
     (CHAR_VISCOSITY.g() * (*MAX_CELL_V))
-        .scale(1.0 / NVERTS as f64)
+        .scale(1.0 / nverts as f64)
         .to_force()
         .expect(
             "Procedure for generating default force does \
              not produce a force. Check units!",
         )
-});
+}
 pub static CELL_DIAMETER: Lazy<Length> =
     Lazy::new(|| Length(40.0).micro());
 
-/// Default raw parameters for cells.
-pub static RAW_PARAMS: Lazy<RawParameters> = Lazy::new(|| {
+/// Default raw parameters for cells with `N` vertices.
+pub fn raw_params<const N: usize>() -> RawParameters<N> {
     let rgtp_d = (Length(0.1_f64.sqrt()).micro().pow(2.0).g()
         / Time(1.0).g())
     .to_diffusion()
     .unwrap();
     let init_rac = RgtpDistribution::new(
-        distrib_gens::specific_uniform(0.1, ALL),
-        distrib_gens::specific_uniform(0.1, ALL),
+        distrib_gens::specific_uniform(0.1, [true; N]),
+        distrib_gens::specific_uniform(0.1, [true; N]),
     );
     RawParameters {
         cell_diam: *CELL_DIAMETER,
@@ -94,13 +93,16 @@ pub static RAW_PARAMS: Lazy<RawParameters> = Lazy::new(|| {
         init_rac,
         init_rho: init_rac,
     }
-});
+}
 
 /// We take the viscosity of the world to be 0.29 N m^-2. We
-/// divide viscosity by the number of vertices, on a cell in
+/// divide viscosity by the number of vertices, `nverts`, on a cell in
 /// order to scale it properly.
-pub fn vertex_viscosity(char_quants: &CharQuantities) -> Viscosity {
-    char_quants.eta.scale(2.9 / (NVERTS as f64))
+pub fn vertex_viscosity(
+    char_quants: &CharQuantities,
+    nverts: usize,
+) -> Viscosity {
+    char_quants.eta.scale(2.9 / (nverts as f64))
 }
 
 pub static PHYS_CLOSE_DIST: Lazy<Length> =
@@ -124,23 +126,28 @@ pub static RAW_COA_PARAMS_WITH_ZERO_MAG: Lazy<RawCoaParams> =
         too_close_dist: Length(1.0).micro(), //PHYS_CLOSE_DIST.scale(2.0),
     });
 
-pub static RAW_WORLD_PARAMS: Lazy<RawWorldParameters> =
-    Lazy::new(|| {
-        let one_at = *PHYS_CLOSE_DIST;
-        RawWorldParameters {
-            vertex_eta: vertex_viscosity(&CHAR_QUANTS),
-            interactions: RawInteractionParams {
-                coa: None,
-                chem_attr: None,
-                bdry: None,
-                phys_contact: RawPhysicalContactParams {
-                    crl_one_at: one_at,
-                    zero_at: one_at.scale(2.0),
-                    adh_mag: None,
-                    cal_mag: None,
-                    cil_mag: CIL_MAG,
-                    adh_break: None,
-                },
+/// Default raw world parameters, for cells with `nverts` vertices.
+pub fn raw_world_params(nverts: usize) -> RawWorldParameters {
+    let one_at = *PHYS_CLOSE_DIST;
+    RawWorldParameters {
+        vertex_eta: vertex_viscosity(&CHAR_QUANTS, nverts),
+        interactions: RawInteractionParams {
+            coa: None,
+            chem_attr: None,
+            chem_field: None,
+            bdry: None,
+            group_pair_mags: None,
+            adh_bonds: None,
+            phys_contact: RawPhysicalContactParams {
+                crl_one_at: one_at,
+                zero_at: one_at.scale(2.0),
+                adh_mag: None,
+                cal_mag: None,
+                cil_mag: CIL_MAG,
+                adh_break: None,
             },
-        }
-    });
+        },
+        obstacles: None,
+        domain: None,
+    }
+}
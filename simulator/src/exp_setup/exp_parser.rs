@@ -1,9 +1,21 @@
+use crate::cell::remesh::RemeshOpts;
+use crate::exp_setup::defaults;
 use crate::exp_setup::defaults::{
-    PHYS_CLOSE_DIST_ONE_AT, PHYS_CLOSE_DIST_ZERO_AT, RAW_COA_PARAMS_WITH_ZERO_MAG,
+    CELL_DIAMETER, COA_HALFMAX_DIST, PHYS_CLOSE_DIST_ONE_AT, PHYS_CLOSE_DIST_ZERO_AT, RAW_COA_PARAMS_WITH_ZERO_MAG,
 };
-use crate::exp_setup::{ExperimentType, RgtpDistribDefs};
-use crate::parameters::quantity::{Length, Quantity, Time};
-use crate::world::{EulerOpts, IntegratorOpts, RkOpts};
+use crate::exp_setup::{CellParamVariation, ExperimentType, RgtpDistribDefs};
+use crate::interactions::coa_reach;
+use crate::parameters::quantity::{Diffusion, Length, Quantity, Time, Tinv};
+use crate::parameters::{
+    RawBdryParams, RawChemAttrParams, RawChemAttrSource, RawChemFieldParams, RawChemProfile,
+    RawAdhBondParams, RawChemSchedule, RawChemSource, RawGroupPairMags, RawObstacleParams,
+    RawPeriodicDomain,
+};
+use crate::world::population::RawPopulationRules;
+use crate::world::recovery::RecoveryOpts;
+use crate::world::stop_conditions::RawStopCondition;
+use crate::world::{EulerOpts, IntegratorOpts, NoiseOpts, RkOpts, SdeOpts, UpdateMode};
+use crate::{DEFAULT_NVERTS, SUPPORTED_NVERTS};
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 use std::error;
@@ -24,11 +36,20 @@ struct ParsedEulerOpts {
     num_int_steps: Option<usize>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct ParsedSdeOpts {
+    num_int_steps: Option<usize>,
+    vertex_noise: Option<f64>,
+    reaction_noise: Option<f64>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 enum ParsedIntOpts {
     RkDp5(ParsedRkOpts),
     Euler(ParsedEulerOpts),
     EulerDebug(ParsedEulerOpts),
+    Ros2(ParsedRkOpts),
+    EulerMaruyama(ParsedSdeOpts),
 }
 
 impl Default for ParsedIntOpts {
@@ -37,28 +58,213 @@ impl Default for ParsedIntOpts {
     }
 }
 
+impl From<ParsedRkOpts> for RkOpts {
+    fn from(opts: ParsedRkOpts) -> Self {
+        let default = RkOpts::default();
+        RkOpts {
+            max_iters: opts.max_iters.unwrap_or(default.max_iters),
+            atol: opts.atol.unwrap_or(default.atol),
+            rtol: opts.rtol.unwrap_or(default.rtol),
+            init_h_scale: opts.init_h_scale.unwrap_or(default.init_h_scale),
+        }
+    }
+}
+
 impl From<ParsedIntOpts> for IntegratorOpts {
     fn from(parsed_opts: ParsedIntOpts) -> Self {
         match parsed_opts {
-            ParsedIntOpts::RkDp5(opts) => {
-                let default = RkOpts::default();
-                IntegratorOpts::Rkdp5(RkOpts {
-                    max_iters: opts.max_iters.unwrap_or(default.max_iters),
-                    atol: opts.atol.unwrap_or(default.atol),
-                    rtol: opts.rtol.unwrap_or(default.rtol),
-                    init_h_scale: opts.init_h_scale.unwrap_or(default.init_h_scale),
-                })
-            }
+            ParsedIntOpts::RkDp5(opts) => IntegratorOpts::Rkdp5(opts.into()),
+            ParsedIntOpts::Ros2(opts) => IntegratorOpts::Ros2(opts.into()),
             ParsedIntOpts::Euler(opts) => IntegratorOpts::Euler(EulerOpts {
                 num_int_steps: opts.num_int_steps.unwrap_or(10),
             }),
             ParsedIntOpts::EulerDebug(opts) => IntegratorOpts::EulerDebug(EulerOpts {
                 num_int_steps: opts.num_int_steps.unwrap_or(10),
             }),
+            ParsedIntOpts::EulerMaruyama(opts) => IntegratorOpts::EulerMaruyama(SdeOpts {
+                num_int_steps: opts.num_int_steps.unwrap_or(10),
+                noise: NoiseOpts {
+                    vertex_noise: opts.vertex_noise.unwrap_or(0.0),
+                    reaction_noise: opts.reaction_noise.unwrap_or(0.0),
+                },
+            }),
         }
     }
 }
 
+/// Boundary walls, with lengths in micrometers.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct ParsedBdryParams {
+    shapes: Vec<Vec<[f64; 2]>>,
+    touch_dist: Option<f64>,
+    mag: f64,
+}
+
+/// Static obstacles, with lengths in micrometers. Vertices touching
+/// an obstacle only experience CIL if `cil_mag` is given.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct ParsedObstacleParams {
+    shapes: Vec<Vec<[f64; 2]>>,
+    touch_dist: Option<f64>,
+    cil_mag: Option<f64>,
+}
+
+/// Periodic domain, with lengths in micrometers.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct ParsedPeriodicDomain {
+    origin: [f64; 2],
+    size: [f64; 2],
+}
+
+/// Schedule of a chemoattractant source, with times in seconds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum ParsedChemSchedule {
+    Constant,
+    OnOff {
+        periods: Vec<[f64; 2]>,
+    },
+    Ramp {
+        start: f64,
+        end: f64,
+        from: f64,
+        to: f64,
+    },
+}
+
+impl From<ParsedChemSchedule> for RawChemSchedule {
+    fn from(schedule: ParsedChemSchedule) -> Self {
+        match schedule {
+            ParsedChemSchedule::Constant => RawChemSchedule::Constant,
+            ParsedChemSchedule::OnOff { periods } => RawChemSchedule::OnOff {
+                periods: periods.iter().map(|p| [Time(p[0]), Time(p[1])]).collect(),
+            },
+            ParsedChemSchedule::Ramp {
+                start,
+                end,
+                from,
+                to,
+            } => RawChemSchedule::Ramp {
+                start: Time(start),
+                end: Time(end),
+                from,
+                to,
+            },
+        }
+    }
+}
+
+/// Profile of a chemoattractant source, with lengths in
+/// micrometers. If `char_l` is not given, it is the cell diameter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum ParsedChemProfile {
+    Linear {
+        drop_per_char_l: f64,
+        char_l: Option<f64>,
+    },
+    Exponential {
+        decay_length: f64,
+    },
+    Gaussian {
+        width: f64,
+    },
+    Saturating {
+        decay_length: f64,
+        halfmax: f64,
+    },
+}
+
+impl From<ParsedChemProfile> for RawChemProfile {
+    fn from(profile: ParsedChemProfile) -> Self {
+        match profile {
+            ParsedChemProfile::Linear {
+                drop_per_char_l,
+                char_l,
+            } => RawChemProfile::Linear {
+                drop_per_char_l,
+                char_l: char_l.map_or(*CELL_DIAMETER, |v| Length(v).micro()),
+            },
+            ParsedChemProfile::Exponential { decay_length } => RawChemProfile::Exponential {
+                decay_length: Length(decay_length).micro(),
+            },
+            ParsedChemProfile::Gaussian { width } => RawChemProfile::Gaussian {
+                width: Length(width).micro(),
+            },
+            ParsedChemProfile::Saturating {
+                decay_length,
+                halfmax,
+            } => RawChemProfile::Saturating {
+                decay_length: Length(decay_length).micro(),
+                halfmax,
+            },
+        }
+    }
+}
+
+/// Chemoattractant source with an analytic profile, centered at
+/// `center` (in micrometers). Sources without a schedule are always
+/// at full strength.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ParsedChemAttrSource {
+    center: [f64; 2],
+    mag: f64,
+    profile: ParsedChemProfile,
+    schedule: Option<ParsedChemSchedule>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct ParsedChemAttrParams {
+    sources: Vec<ParsedChemAttrSource>,
+}
+
+/// Point source of chemoattractant, at `pos` (in micrometers),
+/// producing `rate` units of concentration per second at full
+/// strength.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ParsedChemSource {
+    pos: [f64; 2],
+    rate: f64,
+    schedule: Option<ParsedChemSchedule>,
+}
+
+/// Chemoattractant field, with lengths in micrometers, diffusion in
+/// square micrometers per second, and rates per second.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct ParsedChemFieldParams {
+    origin: [f64; 2],
+    spacing: f64,
+    shape: [usize; 2],
+    diffusion: f64,
+    decay: Option<f64>,
+    consumption: Option<f64>,
+    sources: Vec<ParsedChemSource>,
+    mag: f64,
+}
+
+/// Interaction magnitudes per ordered pair of cell groups, indexed
+/// like `cil[g][og]`. Adhesion is given as a scale of the default
+/// adhesion magnitude, like `adh_scale`.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+struct ParsedGroupPairMags {
+    cil: Option<Vec<Vec<f64>>>,
+    cal: Option<Vec<Vec<f64>>>,
+    adh_scale: Option<Vec<Vec<f64>>>,
+    coa: Option<Vec<Vec<f64>>>,
+}
+
+/// Stateful adhesion bonds, with lengths in micrometers and rates
+/// per second. The Bell force is given as a scale of the default
+/// adhesion magnitude, like `adh_scale`. Bonds only form between
+/// cells which adhere, so `adh_scale` or `group_pair_mags.adh_scale`
+/// must be given too.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default)]
+struct ParsedAdhBondParams {
+    form_dist: Option<f64>,
+    form_rate: f64,
+    rupture_rate: f64,
+    bell_force_scale: f64,
+    max_len: Option<f64>,
+}
+
 #[derive(Clone, Copy, Deserialize, Serialize, Debug)]
 pub struct AnimationOptions {
     label_verts: bool,
@@ -87,6 +293,21 @@ struct ParsedExpArgs {
     rgtp_distrib_defs: Option<RgtpDistribDefs>,
     seeds: Vec<u64>,
     int_opts: ParsedIntOpts,
+    update_mode: Option<UpdateMode>,
+    stop_conditions: Option<Vec<RawStopCondition>>,
+    population: Option<RawPopulationRules>,
+    remesh: Option<RemeshOpts>,
+    dense_output: Option<bool>,
+    recovery: Option<RecoveryOpts>,
+    bdry: Option<ParsedBdryParams>,
+    chem_attr: Option<ParsedChemAttrParams>,
+    chem_field: Option<ParsedChemFieldParams>,
+    group_pair_mags: Option<ParsedGroupPairMags>,
+    adh_bonds: Option<ParsedAdhBondParams>,
+    obstacles: Option<ParsedObstacleParams>,
+    domain: Option<ParsedPeriodicDomain>,
+    cell_param_variation: Option<CellParamVariation>,
+    nverts: Option<usize>,
     ani_opts: Vec<AnimationOptions>,
 }
 
@@ -109,6 +330,45 @@ pub struct ExperimentArgs {
     pub randomization: bool,
     pub seeds: Vec<u64>,
     pub int_opts: IntegratorOpts,
+    pub update_mode: UpdateMode,
+    pub stop_conditions: Vec<RawStopCondition>,
+    pub population: RawPopulationRules,
+    /// When cells are remeshed. If `None`, cells are never remeshed.
+    pub remesh: Option<RemeshOpts>,
+    /// Whether states are saved at exact multiples of `snap_period`,
+    /// using the integrator's dense output. Interpolated states
+    /// cannot be resumed from, so a simulation is resumed from the
+    /// last state saved at the end of a time step.
+    pub dense_output: bool,
+    /// How time steps which fail due to volume exclusion violations
+    /// are retried.
+    pub recovery: RecoveryOpts,
+    /// Walls confining the cells. If `None`, there are none.
+    pub bdry: Option<RawBdryParams>,
+    /// Chemoattractant sources with analytic profiles. If `None`,
+    /// there are none, though experiments may add their own.
+    pub chem_attr: Option<RawChemAttrParams>,
+    /// Chemoattractant field solved on a grid. If `None`, there is
+    /// none.
+    pub chem_field: Option<RawChemFieldParams>,
+    /// Interaction magnitudes per ordered pair of cell groups. If
+    /// `None`, cells of all groups interact the same way.
+    pub group_pair_mags: Option<RawGroupPairMags>,
+    /// Stateful adhesion bonds. If `None`, adhesion has no memory.
+    pub adh_bonds: Option<RawAdhBondParams>,
+    /// Static obstacles. If `None`, there are none.
+    pub obstacles: Option<RawObstacleParams>,
+    /// Periodic domain. If `None`, space is unbounded.
+    pub domain: Option<RawPeriodicDomain>,
+    /// Distributions of the factors by which parameters of each cell
+    /// are scaled relative to those of its group. Cells are laid out
+    /// using their group's diameter, so varying `cell_diam` may make
+    /// neighbouring cells overlap initially. Not supported by
+    /// `PyCompare` experiments, whose cells must match the Python
+    /// model.
+    pub cell_param_variation: CellParamVariation,
+    /// Number of vertices per cell.
+    pub nverts: usize,
     pub rgtp_distrib_defs: RgtpDistribDefs,
 }
 
@@ -137,8 +397,83 @@ impl TryFrom<&PathBuf> for ExperimentArgs {
             rgtp_distrib_defs,
             seeds,
             int_opts,
+            update_mode,
+            stop_conditions,
+            population,
+            remesh,
+            dense_output,
+            recovery,
+            bdry,
+            chem_attr,
+            chem_field,
+            group_pair_mags,
+            adh_bonds,
+            obstacles,
+            domain,
+            cell_param_variation,
+            nverts,
             ..
         } = serde_json::from_str(&json_out).unwrap();
+        let nverts = nverts.unwrap_or(DEFAULT_NVERTS);
+        if !SUPPORTED_NVERTS.contains(&nverts) {
+            return Err(format!(
+                "nverts must be one of {:?}, got: {}",
+                SUPPORTED_NVERTS, nverts
+            )
+            .into());
+        }
+        if let Some(defs) = &rgtp_distrib_defs {
+            defs.validate(nverts)?;
+        }
+        if let ExperimentType::Pair { rgtp_distrib_defs_per_cell, .. } = &ty {
+            rgtp_distrib_defs_per_cell.cell0.validate(nverts)?;
+            rgtp_distrib_defs_per_cell.cell1.validate(nverts)?;
+        }
+        if let Some(cf) = &chem_field {
+            if cf.shape.contains(&0) || cf.spacing <= 0.0 {
+                return Err(format!(
+                    "chem_field must have a non-empty grid with positive spacing, got: shape = {:?}, spacing = {}",
+                    cf.shape, cf.spacing
+                )
+                .into());
+            }
+        }
+        if let Some(ab) = &adh_bonds {
+            if ab.bell_force_scale <= 0.0 || ab.form_rate < 0.0 || ab.rupture_rate < 0.0 {
+                return Err(format!(
+                    "adh_bonds must have a positive bell_force_scale and non-negative rates, got: {:?}",
+                    ab
+                )
+                .into());
+            }
+        }
+        if let Some(d) = &domain {
+            if d.size.iter().any(|&x| x <= 0.0) {
+                return Err(format!("domain must have positive size, got: {:?}", d.size).into());
+            }
+        }
+        let cell_param_variation = cell_param_variation.unwrap_or_default();
+        for distrib in cell_param_variation.values() {
+            distrib.validate()?;
+        }
+        if matches!(ty, ExperimentType::PyCompare { .. }) && !cell_param_variation.is_empty() {
+            return Err("cell_param_variation is not supported by PyCompare experiments".into());
+        }
+        if let Some(gpm) = &group_pair_mags {
+            let matrices = [&gpm.cil, &gpm.cal, &gpm.adh_scale, &gpm.coa];
+            let sizes = matrices
+                .iter()
+                .filter_map(|m| m.as_ref())
+                .map(|m| (m.len(), m.iter().all(|row| row.len() == m.len())))
+                .collect::<Vec<(usize, bool)>>();
+            if sizes.iter().any(|&(n, square)| !square || n != sizes[0].0) {
+                return Err(format!(
+                    "group_pair_mags must be square matrices of the same size, got: {:?}",
+                    gpm
+                )
+                .into());
+            }
+        }
         let file_name: String = json_path
             .file_stem()
             .unwrap_or_else(|| {
@@ -155,6 +490,128 @@ impl TryFrom<&PathBuf> for ExperimentArgs {
                 )
             })
             .into();
+        let zero_at = zero_at.map_or_else(|| *PHYS_CLOSE_DIST_ZERO_AT, |v| Length(v).micro());
+        let bdry = bdry.map(|b| RawBdryParams {
+            shapes: b
+                .shapes
+                .iter()
+                .map(|shape| {
+                    shape
+                        .iter()
+                        .map(|p| [Length(p[0]).micro(), Length(p[1]).micro()])
+                        .collect()
+                })
+                .collect(),
+            touch_dist: b.touch_dist.map_or(zero_at, |v| Length(v).micro()),
+            mag: b.mag,
+        });
+        let obstacles = obstacles.map(|o| RawObstacleParams {
+            shapes: o
+                .shapes
+                .iter()
+                .map(|shape| {
+                    shape
+                        .iter()
+                        .map(|p| [Length(p[0]).micro(), Length(p[1]).micro()])
+                        .collect()
+                })
+                .collect(),
+            touch_dist: o.touch_dist.map_or(zero_at, |v| Length(v).micro()),
+            cil_mag: o.cil_mag,
+        });
+        let domain = domain.map(|d| RawPeriodicDomain {
+            origin: [Length(d.origin[0]).micro(), Length(d.origin[1]).micro()],
+            size: [Length(d.size[0]).micro(), Length(d.size[1]).micro()],
+        });
+        let chem_attr = chem_attr.map(|ca| RawChemAttrParams {
+            sources: ca
+                .sources
+                .into_iter()
+                .map(|s| RawChemAttrSource {
+                    center: [Length(s.center[0]).micro(), Length(s.center[1]).micro()],
+                    mag: s.mag,
+                    profile: s.profile.into(),
+                    schedule: s
+                        .schedule
+                        .map_or_else(RawChemSchedule::default, |s| s.into()),
+                })
+                .collect(),
+        });
+        let chem_field = chem_field.map(|cf| RawChemFieldParams {
+            origin: [Length(cf.origin[0]).micro(), Length(cf.origin[1]).micro()],
+            spacing: Length(cf.spacing).micro(),
+            shape: cf.shape,
+            diffusion: Diffusion(cf.diffusion).micro().micro(),
+            decay: Tinv(cf.decay.unwrap_or(0.0)),
+            consumption: Tinv(cf.consumption.unwrap_or(0.0)),
+            sources: cf
+                .sources
+                .into_iter()
+                .map(|s| RawChemSource {
+                    pos: [Length(s.pos[0]).micro(), Length(s.pos[1]).micro()],
+                    rate: Tinv(s.rate),
+                    schedule: s
+                        .schedule
+                        .map_or_else(RawChemSchedule::default, |s| s.into()),
+                })
+                .collect(),
+            mag: cf.mag,
+        });
+        // COA magnitudes given per pair of groups require COA to be
+        // enabled, even if no global magnitude is given.
+        let coa_mag = match &group_pair_mags {
+            Some(ParsedGroupPairMags { coa: Some(_), .. }) => Some(coa_mag.unwrap_or(0.0)),
+            _ => coa_mag,
+        };
+        if let Some(d) = &domain {
+            // Cells only interact with the nearest image of each other
+            // cell, so the domain must exceed twice the distance over
+            // which cells, taken to be a cell diameter across, can
+            // interact.
+            let reach = if coa_mag.is_some() {
+                coa_reach(COA_HALFMAX_DIST.0, CELL_DIAMETER.0)
+            } else {
+                0.0
+            }
+            .max(zero_at.0 + CELL_DIAMETER.0);
+            if d.size.iter().any(|x| x.0 <= 2.0 * reach) {
+                return Err(format!(
+                    "domain size must exceed twice the interaction reach of {:.1} um, got: [{:.1}, {:.1}] um",
+                    reach * 1e6,
+                    d.size[0].0 * 1e6,
+                    d.size[1].0 * 1e6,
+                )
+                .into());
+            }
+        }
+        let group_pair_mags = group_pair_mags.map(|gpm| RawGroupPairMags {
+            cil: gpm.cil,
+            cal: gpm.cal,
+            adh: gpm.adh_scale.map(|m| {
+                m.iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|&x| defaults::adh_mag(nverts).scale(x))
+                            .collect()
+                    })
+                    .collect()
+            }),
+            coa: gpm.coa,
+        });
+        let crl_one_at = crl_one_at.map_or_else(|| *PHYS_CLOSE_DIST_ONE_AT, |v| Length(v).micro());
+        let adh_break = adh_break.map(|v| Length(v).micro());
+        // Like adhesion without bonds, bonds form up to the distance
+        // at which adhesion starts breaking, and break beyond the
+        // contact distance.
+        let adh_bonds = adh_bonds.map(|ab| RawAdhBondParams {
+            form_dist: ab
+                .form_dist
+                .map_or(adh_break.unwrap_or(crl_one_at), |v| Length(v).micro()),
+            form_rate: Tinv(ab.form_rate),
+            rupture_rate: Tinv(ab.rupture_rate),
+            bell_force: defaults::adh_mag(nverts).scale(ab.bell_force_scale),
+            max_len: ab.max_len.map_or(zero_at, |v| Length(v).micro()),
+        });
         let exp_args = ExperimentArgs {
             file_name,
             ty,
@@ -164,9 +621,9 @@ impl TryFrom<&PathBuf> for ExperimentArgs {
             coa_mag,
             cal_mag,
             adh_scale,
-            adh_break: adh_break.map(|v| Length(v).micro()),
-            crl_one_at: crl_one_at.map_or_else(|| *PHYS_CLOSE_DIST_ONE_AT, |v| Length(v).micro()),
-            zero_at: zero_at.map_or_else(|| *PHYS_CLOSE_DIST_ZERO_AT, |v| Length(v).micro()),
+            adh_break,
+            crl_one_at,
+            zero_at,
             too_close_dist: too_close_dist.map_or_else(
                 || RAW_COA_PARAMS_WITH_ZERO_MAG.too_close_dist,
                 |v| Length(v).micro(),
@@ -176,6 +633,21 @@ impl TryFrom<&PathBuf> for ExperimentArgs {
             randomization,
             seeds,
             int_opts: int_opts.into(),
+            update_mode: update_mode.unwrap_or_default(),
+            stop_conditions: stop_conditions.unwrap_or_default(),
+            population: population.unwrap_or_default(),
+            remesh,
+            dense_output: dense_output.unwrap_or(false),
+            recovery: recovery.unwrap_or_default(),
+            bdry,
+            chem_attr,
+            chem_field,
+            group_pair_mags,
+            adh_bonds,
+            obstacles,
+            domain,
+            cell_param_variation,
+            nverts,
             rgtp_distrib_defs: rgtp_distrib_defs.unwrap_or_default(),
         };
         Ok(exp_args)
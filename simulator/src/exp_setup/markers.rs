@@ -1,13 +1,12 @@
 use crate::math::radians::{Radians, RAD_PI};
-use crate::NVERTS;
 
-pub fn mark_between_angles(
+pub fn mark_between_angles<const N: usize>(
     bounds: (Radians, Radians),
-) -> [bool; NVERTS] {
-    let mut r = [false; NVERTS];
+) -> [bool; N] {
+    let mut r = [false; N];
     let (b0, b1) = bounds;
-    (0..NVERTS).for_each(|vi| {
-        let va = (2.0 * vi as f64 / NVERTS as f64) * (*RAD_PI);
+    (0..N).for_each(|vi| {
+        let va = (2.0 * vi as f64 / N as f64) * (*RAD_PI);
         if va.between(b0, b1) {
             r[vi] = true;
         }
@@ -15,15 +14,29 @@ pub fn mark_between_angles(
     r
 }
 
-pub const ALL: [bool; NVERTS] = [true; NVERTS];
-
-pub fn mark_verts(verts: &[usize]) -> [bool; NVERTS] {
-    if verts.len() > NVERTS {
-        panic!("verts.len() = {} > NVERTS = {}", verts.len(), NVERTS);
+/// Check that the vertices `verts` exist in cells with `nverts`
+/// vertices.
+pub fn check_marked_verts(
+    verts: &[usize],
+    nverts: usize,
+) -> Result<(), String> {
+    match verts.iter().find(|&&v| v >= nverts) {
+        Some(v) => Err(format!(
+            "marked vertex {} is out of range for {} vertices",
+            v, nverts
+        )),
+        None => Ok(()),
     }
-    let mut r = [false; NVERTS];
+}
+
+/// Mark the vertices `verts`, which must be less than `N`.
+pub fn mark_verts<const N: usize>(
+    verts: &[usize],
+) -> Result<[bool; N], String> {
+    check_marked_verts(verts, N)?;
+    let mut r = [false; N];
     for &v in verts {
         r[v] = true;
     }
-    r
+    Ok(r)
 }
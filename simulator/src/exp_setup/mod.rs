@@ -6,12 +6,18 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use crate::cell::remesh::RemeshOpts;
 use crate::exp_setup::exp_parser::ExperimentArgs;
 use crate::math::v2d::V2d;
-use crate::parameters::{CharQuantities, Parameters, WorldParameters};
+use crate::parameters::{
+    CellParam, CharQuantities, Parameters, RawParameters, WorldParameters,
+};
 use crate::utils::pcg32::Pcg32;
-use crate::world::IntegratorOpts;
-use crate::{Directories, NVERTS};
+use crate::world::population::RawPopulationRules;
+use crate::world::recovery::RecoveryOpts;
+use crate::world::stop_conditions::RawStopCondition;
+use crate::world::{IntegratorOpts, UpdateMode};
+use crate::Directories;
 
 pub mod defaults;
 pub mod exp_parser;
@@ -21,9 +27,11 @@ pub mod pair;
 pub mod py_compare;
 
 use crate::cell::chemistry::distrib_gens::{random, specific_random, specific_uniform};
-use crate::exp_setup::markers::mark_verts;
+use crate::exp_setup::markers::{check_marked_verts, mark_verts};
 use crate::parameters::quantity::Time;
+use rand_distr::{Distribution, LogNormal, Normal, Uniform};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -52,14 +60,31 @@ pub enum DistribDef {
 }
 
 impl DistribDef {
-    pub fn to_distrib(&self, rng: &mut Pcg32) -> [f64; NVERTS] {
+    /// Check that the marked vertices exist in cells with `nverts`
+    /// vertices.
+    pub fn validate(&self, nverts: usize) -> Result<(), String> {
+        match self {
+            DistribDef::Random { .. } => Ok(()),
+            DistribDef::SpecificRandom { marked_verts, .. }
+            | DistribDef::SpecificUniform { marked_verts, .. } => {
+                check_marked_verts(marked_verts, nverts)
+            }
+        }
+    }
+
+    /// The distribution for cells with `N` vertices, for which it must
+    /// have been validated (see `DistribDef::validate`).
+    pub fn to_distrib<const N: usize>(&self, rng: &mut Pcg32) -> [f64; N] {
+        let marked = |verts: &[usize]| {
+            mark_verts(verts).unwrap_or_else(|e| panic!("{}", e))
+        };
         match self {
             DistribDef::Random { frac } => random(rng, *frac),
             DistribDef::SpecificRandom { frac, marked_verts } => {
-                specific_random(rng, *frac, mark_verts(&marked_verts))
+                specific_random(rng, *frac, marked(marked_verts))
             }
             DistribDef::SpecificUniform { frac, marked_verts } => {
-                specific_uniform(*frac, mark_verts(&marked_verts))
+                specific_uniform(*frac, marked(marked_verts))
             }
         }
     }
@@ -71,6 +96,92 @@ impl Default for DistribDef {
     }
 }
 
+/// Distribution of the factor by which a parameter of each cell is
+/// scaled.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub enum ScaleDistrib {
+    /// Normal distribution, truncated at zero.
+    Normal { mean: f64, std: f64 },
+    /// Log-normal distribution with the given mean and standard
+    /// deviation.
+    LogNormal { mean: f64, std: f64 },
+    Uniform { min: f64, max: f64 },
+}
+
+impl ScaleDistrib {
+    /// Check that the distribution only gives positive factors.
+    pub fn validate(&self) -> Result<(), String> {
+        let valid = match *self {
+            ScaleDistrib::Normal { mean, std }
+            | ScaleDistrib::LogNormal { mean, std } => {
+                mean > 0.0 && std >= 0.0
+            }
+            ScaleDistrib::Uniform { min, max } => {
+                min > 0.0 && min <= max
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "scale distribution must give positive factors, got: {:?}",
+                self
+            ))
+        }
+    }
+
+    pub fn sample(&self, rng: &mut Pcg32) -> f64 {
+        match *self {
+            ScaleDistrib::Normal { mean, std } => {
+                let distrib = Normal::new(mean, std).unwrap();
+                loop {
+                    let x = distrib.sample(rng);
+                    if x > 0.0 {
+                        break x;
+                    }
+                }
+            }
+            ScaleDistrib::LogNormal { mean, std } => {
+                let var = (1.0 + (std / mean).powi(2)).ln();
+                LogNormal::new(mean.ln() - 0.5 * var, var.sqrt())
+                    .unwrap()
+                    .sample(rng)
+            }
+            ScaleDistrib::Uniform { min, max } => {
+                Uniform::new_inclusive(min, max).sample(rng)
+            }
+        }
+    }
+}
+
+/// Distributions of the factors by which parameters of each cell in
+/// a group are scaled. Parameters not given are the same for all
+/// cells.
+pub type CellParamVariation = BTreeMap<CellParam, ScaleDistrib>;
+
+/// Refine `raw_params` for each of `num_cells` cells, scaling the
+/// parameters in `variation` by factors sampled independently for
+/// each cell, in order of cell and then parameter. If `variation` is
+/// empty, `rng` is not used.
+pub fn sample_cell_params<const N: usize>(
+    rng: &mut Pcg32,
+    raw_params: &RawParameters<N>,
+    variation: &CellParamVariation,
+    num_cells: usize,
+    char_quants: &CharQuantities,
+) -> Vec<Parameters<N>> {
+    (0..num_cells)
+        .map(|_| {
+            variation
+                .iter()
+                .fold(*raw_params, |p, (&param, distrib)| {
+                    p.scale_param(param, distrib.sample(rng))
+                })
+                .refine(char_quants)
+        })
+        .collect()
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 pub struct RgtpDistribDef {
     pub acts: DistribDef,
@@ -83,6 +194,19 @@ pub struct RgtpDistribDefs {
     pub rho: RgtpDistribDef,
 }
 
+impl RgtpDistribDefs {
+    /// Check that the marked vertices exist in cells with `nverts`
+    /// vertices.
+    pub fn validate(&self, nverts: usize) -> Result<(), String> {
+        [&self.rac, &self.rho]
+            .iter()
+            .try_for_each(|d| {
+                d.acts.validate(nverts)?;
+                d.inacts.validate(nverts)
+            })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 pub struct PairRgtpDistribDefs {
     pub cell0: RgtpDistribDefs,
@@ -99,8 +223,19 @@ impl Default for ExperimentType {
     }
 }
 
-/// Generate the experiment, so that it can be run.
-pub fn generate(dirs: Directories, args: ExperimentArgs) -> Vec<Experiment> {
+/// Generate the experiment, so that it can be run. Returns an error
+/// if `N` is not the number of vertices per cell given by `args`, or
+/// if `args` do not fit the cell groups of the experiment.
+pub fn generate<const N: usize>(
+    dirs: Directories,
+    args: ExperimentArgs,
+) -> Result<Vec<Experiment<N>>, String> {
+    if args.nverts != N {
+        return Err(format!(
+            "experiment expects {} vertices per cell, not {}",
+            args.nverts, N
+        ));
+    }
     dirs.make();
     match &args.ty {
         ExperimentType::NCells { .. } => n_cells::generate(dirs, args),
@@ -122,18 +257,22 @@ pub struct GroupBBox {
 
 /// Information required for a cell group to be created.
 #[derive(Clone)]
-pub struct CellGroup {
+pub struct CellGroup<const N: usize> {
     /// The number of cells in the group.
     pub num_cells: usize,
     /// Initial layout of the cell group.
     pub layout: GroupBBox,
-    /// Parameters shared by all cells in this group.
-    pub parameters: Parameters,
+    /// Parameters of the group, around which those of its cells
+    /// vary.
+    pub parameters: Parameters<N>,
+    /// Parameters of each cell in the group (see
+    /// `sample_cell_params`).
+    pub cell_parameters: Vec<Parameters<N>>,
 }
 
 /// Information required to create an experiment.
 #[derive(Clone)]
-pub struct Experiment {
+pub struct Experiment<const N: usize> {
     pub ty: ExperimentType,
     pub name: String,
     /// Time period in seconds to be simulated.
@@ -142,7 +281,7 @@ pub struct Experiment {
     pub char_quants: CharQuantities,
     pub world_params: WorldParameters,
     /// List of cell groups involved in this experiment.
-    pub cell_groups: Vec<CellGroup>,
+    pub cell_groups: Vec<CellGroup<N>>,
     /// Random number generator to be used for various purposes.
     /// Initialized from a seed, otherwise from "entropy".
     pub rng: Pcg32,
@@ -152,6 +291,19 @@ pub struct Experiment {
     pub snap_period: Time,
     pub max_on_ram: usize,
     pub int_opts: IntegratorOpts,
+    pub update_mode: UpdateMode,
+    /// Conditions which end the simulation before `final_t`.
+    pub stop_conditions: Vec<RawStopCondition>,
+    /// Rules by which cells divide and are removed.
+    pub population: RawPopulationRules,
+    /// When cells are remeshed.
+    pub remesh: Option<RemeshOpts>,
+    /// Whether states are saved at exact multiples of
+    /// `snap_period`.
+    pub dense_output: bool,
+    /// How time steps which fail due to volume exclusion violations
+    /// are retried.
+    pub recovery: RecoveryOpts,
     pub out_dir: PathBuf,
     pub py_main: Option<PathBuf>,
     pub run_python: bool,
@@ -2,18 +2,46 @@ use crate::cell::chemistry::RgtpDistribution;
 use crate::exp_setup::defaults::RAW_COA_PARAMS_WITH_ZERO_MAG;
 use crate::exp_setup::exp_parser::ExperimentArgs;
 use crate::exp_setup::{
-    defaults, CellGroup, Experiment, ExperimentType, GroupBBox, RgtpDistribDefs,
+    defaults, sample_cell_params, CellGroup, CellParamVariation, Experiment, ExperimentType,
+    GroupBBox, RgtpDistribDefs,
 };
 use crate::math::v2d::V2d;
 use crate::parameters::quantity::{Length, Quantity};
 use crate::parameters::{
-    CharQuantities, RawChemAttrParams, RawInteractionParams, RawParameters,
-    RawPhysicalContactParams,
+    CharQuantities, RawChemAttrParams, RawChemAttrSource, RawChemProfile, RawChemSchedule,
+    RawInteractionParams, RawParameters, RawPhysicalContactParams,
 };
 use crate::utils::pcg32::Pcg32;
 use crate::Directories;
 use rand::SeedableRng;
 
+/// Chemoattractant sources of the experiment: those given in
+/// `chem_attr`, along with a source at distance `chem_dist` (in
+/// micrometers) along the x-axis, if it is given.
+fn chem_attr_params(
+    chem_attr: &Option<RawChemAttrParams>,
+    chem_dist: Option<f64>,
+    chem_mag: Option<f64>,
+) -> Option<RawChemAttrParams> {
+    let mut sources = chem_attr.as_ref().map_or(vec![], |ca| ca.sources.clone());
+    if let Some(c) = chem_dist {
+        sources.push(RawChemAttrSource {
+            center: [Length(c).micro(), Length(0.0)],
+            mag: chem_mag.unwrap_or(7.5),
+            profile: RawChemProfile::Proportional {
+                rise_per_char_l: 0.02,
+                char_l: *defaults::CELL_DIAMETER,
+            },
+            schedule: RawChemSchedule::Constant,
+        });
+    }
+    if sources.is_empty() {
+        None
+    } else {
+        Some(RawChemAttrParams { sources })
+    }
+}
+
 /// Generate the group bounding box to use for this experiment.
 fn group_bbox(
     num_cells: usize,
@@ -40,39 +68,51 @@ fn group_bbox(
     }
 }
 
-fn raw_params(
+fn raw_params<const N: usize>(
     rng: &mut Pcg32,
     rgtp_distrib_defns: &RgtpDistribDefs,
     randomization: bool,
-) -> RawParameters {
+) -> RawParameters<N> {
     let RgtpDistribDefs { rac, rho } = rgtp_distrib_defns;
 
     let init_rac = RgtpDistribution::new(rac.acts.to_distrib(rng), rac.inacts.to_distrib(rng));
     let init_rho = RgtpDistribution::new(rho.acts.to_distrib(rng), rho.inacts.to_distrib(rng));
 
-    defaults::RAW_PARAMS
+    defaults::raw_params()
         .modify_randomization(randomization)
         .modify_init_rac(init_rac)
         .modify_init_rho(init_rho)
 }
 
 /// Define the cell groups that will exist in this experiment.
-fn make_cell_groups(
+fn make_cell_groups<const N: usize>(
     rng: &mut Pcg32,
     char_quants: &CharQuantities,
     num_cells: usize,
     cell_diam: Length,
     rgtp_distrib_defns: &RgtpDistribDefs,
     randomization: bool,
-) -> Vec<CellGroup> {
+    cell_param_variation: &CellParamVariation,
+) -> Vec<CellGroup<N>> {
+    let raw_params = raw_params(rng, rgtp_distrib_defns, randomization);
     vec![CellGroup {
         num_cells,
         layout: group_bbox(num_cells, cell_diam, char_quants).unwrap(),
-        parameters: raw_params(rng, rgtp_distrib_defns, randomization).refine(char_quants),
+        parameters: raw_params.refine(char_quants),
+        cell_parameters: sample_cell_params(
+            rng,
+            &raw_params,
+            cell_param_variation,
+            num_cells,
+            char_quants,
+        ),
     }]
 }
 
-pub fn generate(dirs: Directories, args: ExperimentArgs) -> Vec<Experiment> {
+pub fn generate<const N: usize>(
+    dirs: Directories,
+    args: ExperimentArgs,
+) -> Result<Vec<Experiment<N>>, String> {
     let ExperimentArgs {
         file_name: toml_name,
         ty,
@@ -91,6 +131,21 @@ pub fn generate(dirs: Directories, args: ExperimentArgs) -> Vec<Experiment> {
         rgtp_distrib_defs: rgtp_distribs,
         seeds,
         int_opts,
+        update_mode,
+        stop_conditions,
+        population,
+        remesh,
+        dense_output,
+        recovery,
+        bdry,
+        chem_attr,
+        chem_field,
+        group_pair_mags,
+        adh_bonds,
+        obstacles,
+        domain,
+        cell_param_variation,
+        nverts: _,
         randomization,
     } = args;
 
@@ -104,8 +159,12 @@ pub fn generate(dirs: Directories, args: ExperimentArgs) -> Vec<Experiment> {
     } else {
         panic!("Expected an n_cell experiment, but got: {:?}", ty)
     };
+    // There is a single group of cells.
+    if let Some(gpm) = &group_pair_mags {
+        gpm.validate(1)?;
+    }
 
-    seeds
+    Ok(seeds
         .iter()
         .map(|&seed| {
             let mut rng = Pcg32::seed_from_u64(seed);
@@ -117,32 +176,33 @@ pub fn generate(dirs: Directories, args: ExperimentArgs) -> Vec<Experiment> {
                 *defaults::CELL_DIAMETER,
                 &rgtp_distribs,
                 randomization,
+                &cell_param_variation,
             );
 
             let raw_world_params =
-                defaults::RAW_WORLD_PARAMS.modify_interactions(RawInteractionParams {
+                defaults::raw_world_params(N).modify_interactions(RawInteractionParams {
                     coa: coa_mag.map(|mag| {
                         RAW_COA_PARAMS_WITH_ZERO_MAG
                             .modify_mag(mag)
                             .modify_too_close_dist(too_close_dist)
                     }),
-                    chem_attr: chem_dist.map(|c| RawChemAttrParams {
-                        center: [Length(c).micro(), Length(0.0)],
-                        mag: chem_mag.unwrap_or(7.5),
-                        drop_per_char_l: 0.02,
-                        char_l: *defaults::CELL_DIAMETER,
-                    }),
-                    bdry: None,
+                    chem_attr: chem_attr_params(&chem_attr, chem_dist, chem_mag),
+                    chem_field: chem_field.clone(),
+                    bdry: bdry.clone(),
+                    group_pair_mags: group_pair_mags.clone(),
+                    adh_bonds,
                     phys_contact: RawPhysicalContactParams {
                         zero_at,
                         crl_one_at,
-                        adh_mag: { adh_scale.map(|x| defaults::ADH_MAG.scale(x)) },
+                        adh_mag: { adh_scale.map(|x| defaults::adh_mag(N).scale(x)) },
                         adh_break,
                         cal_mag,
                         cil_mag,
                     },
-                });
-            let world_params = raw_world_params.refine(&char_quants);
+                })
+                .modify_obstacles(obstacles.clone())
+                .modify_domain(domain);
+            let world_params = raw_world_params.refine(&char_quants, N);
 
             Experiment {
                 ty: ty.clone(),
@@ -156,10 +216,16 @@ pub fn generate(dirs: Directories, args: ExperimentArgs) -> Vec<Experiment> {
                 snap_period,
                 max_on_ram,
                 int_opts,
+                update_mode,
+                stop_conditions: stop_conditions.clone(),
+                population: population.clone(),
+                remesh,
+                dense_output,
+                recovery,
                 out_dir: (&dirs.out).clone(),
                 py_main: None,
                 run_python: false,
             }
         })
-        .collect()
+        .collect::<Vec<Experiment<N>>>())
 }
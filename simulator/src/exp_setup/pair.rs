@@ -2,8 +2,9 @@ use crate::cell::chemistry::RgtpDistribution;
 use crate::exp_setup::defaults::RAW_COA_PARAMS_WITH_ZERO_MAG;
 use crate::exp_setup::exp_parser::ExperimentArgs;
 use crate::exp_setup::{
-    defaults, CellGroup, Experiment, ExperimentType, GroupBBox,
-    PairRgtpDistribDefs, RgtpDistribDefs,
+    defaults, sample_cell_params, CellGroup, CellParamVariation,
+    Experiment, ExperimentType, GroupBBox, PairRgtpDistribDefs,
+    RgtpDistribDefs,
 };
 use crate::math::v2d::V2d;
 use crate::parameters::quantity::{Length, Quantity};
@@ -43,11 +44,11 @@ fn group_bbox(
     }
 }
 
-fn raw_params(
+fn raw_params<const N: usize>(
     rng: &mut Pcg32,
     rgtp_distrib_defs: &RgtpDistribDefs,
     randomization: bool,
-) -> RawParameters {
+) -> RawParameters<N> {
     let RgtpDistribDefs { rac, rho } = rgtp_distrib_defs;
 
     let init_rac = RgtpDistribution::new(
@@ -59,13 +60,13 @@ fn raw_params(
         rho.inacts.to_distrib(rng),
     );
 
-    defaults::RAW_PARAMS
+    defaults::raw_params()
         .modify_randomization(randomization)
         .modify_init_rac(init_rac)
         .modify_init_rho(init_rho)
 }
 
-fn make_cell_group(
+fn make_cell_group<const N: usize>(
     rng: &mut Pcg32,
     char_quants: &CharQuantities,
     randomization: bool,
@@ -74,10 +75,18 @@ fn make_cell_group(
     num_cells: usize,
     box_width: usize,
     box_height: usize,
-) -> CellGroup {
+    cell_param_variation: &CellParamVariation,
+) -> CellGroup<N> {
     let raw_params =
         raw_params(rng, rgtp_distrib_defs, randomization);
     let parameters = raw_params.refine(char_quants);
+    let cell_parameters = sample_cell_params(
+        rng,
+        &raw_params,
+        cell_param_variation,
+        num_cells,
+        char_quants,
+    );
     CellGroup {
         num_cells,
         layout: group_bbox(
@@ -89,17 +98,19 @@ fn make_cell_group(
         )
         .unwrap(),
         parameters,
+        cell_parameters,
     }
 }
 
 /// Define the cell groups that will exist in this experiment.
-fn make_cell_groups(
+fn make_cell_groups<const N: usize>(
     rng: &mut Pcg32,
     char_quants: &CharQuantities,
     rgtp_distrib_defs_per_cell: &PairRgtpDistribDefs,
     randomization: bool,
     sep_in_cell_diams: usize,
-) -> Vec<CellGroup> {
+    cell_param_variation: &CellParamVariation,
+) -> Vec<CellGroup<N>> {
     let group_zero = make_cell_group(
         rng,
         char_quants,
@@ -109,6 +120,7 @@ fn make_cell_groups(
         1,
         1,
         1,
+        cell_param_variation,
     );
     let group_one = make_cell_group(
         rng,
@@ -122,15 +134,16 @@ fn make_cell_groups(
         1,
         1,
         1,
+        cell_param_variation,
     );
 
     vec![group_zero, group_one]
 }
 
-pub fn generate(
+pub fn generate<const N: usize>(
     dirs: Directories,
     args: ExperimentArgs,
-) -> Vec<Experiment> {
+) -> Result<Vec<Experiment<N>>, String> {
     let ExperimentArgs {
         file_name: toml_name,
         ty,
@@ -149,6 +162,21 @@ pub fn generate(
         randomization,
         seeds,
         int_opts,
+        update_mode,
+        stop_conditions,
+        population,
+        remesh,
+        dense_output,
+        recovery,
+        bdry,
+        chem_attr,
+        chem_field,
+        group_pair_mags,
+        adh_bonds,
+        obstacles,
+        domain,
+        cell_param_variation,
+        nverts: _,
         ..
     } = args;
     let (sep_in_cell_diams, rgtp_distrib_defs_per_cell) =
@@ -161,39 +189,50 @@ pub fn generate(
         } else {
             panic!("Expected a Pair experiment, but got: {:?}", ty)
         };
+    // Each cell of the pair is in its own group.
+    if let Some(gpm) = &group_pair_mags {
+        gpm.validate(2)?;
+    }
 
-    seeds
+    Ok(seeds
         .iter()
         .map(|&seed| {
             let mut rng = Pcg32::seed_from_u64(seed);
 
             let char_quants = defaults::CHAR_QUANTS.modify_t(char_t);
-            let raw_world_params = defaults::RAW_WORLD_PARAMS
+            let raw_world_params = defaults::raw_world_params(N)
                 .modify_interactions(RawInteractionParams {
                     coa: coa_mag.map(|mag| {
                         RAW_COA_PARAMS_WITH_ZERO_MAG
                             .modify_mag(mag)
                             .modify_too_close_dist(too_close_dist)
                     }),
-                    chem_attr: None,
-                    bdry: None,
+                    chem_attr: chem_attr.clone(),
+                    chem_field: chem_field.clone(),
+                    bdry: bdry.clone(),
+                    group_pair_mags: group_pair_mags.clone(),
+                    adh_bonds,
                     phys_contact: RawPhysicalContactParams {
                         zero_at,
                         crl_one_at,
                         adh_mag: adh_scale
-                            .map(|x| defaults::ADH_MAG.scale(x)),
+                            .map(|x| defaults::adh_mag(N).scale(x)),
                         adh_break,
                         cal_mag,
                         cil_mag,
                     },
-                });
-            let world_params = raw_world_params.refine(&char_quants);
+                })
+                .modify_obstacles(obstacles.clone())
+                .modify_domain(domain);
+            let world_params =
+                raw_world_params.refine(&char_quants, N);
             let cgs = make_cell_groups(
                 &mut rng,
                 &char_quants,
                 &rgtp_distrib_defs_per_cell,
                 randomization,
                 sep_in_cell_diams,
+                &cell_param_variation,
             );
 
             Experiment {
@@ -208,10 +247,16 @@ pub fn generate(
                 snap_period,
                 max_on_ram,
                 int_opts,
+                update_mode,
+                stop_conditions: stop_conditions.clone(),
+                population: population.clone(),
+                remesh,
+                dense_output,
+                recovery,
                 out_dir: (&dirs.out).clone(),
                 py_main: None,
                 run_python: false,
             }
         })
-        .collect()
+        .collect::<Vec<Experiment<N>>>())
 }
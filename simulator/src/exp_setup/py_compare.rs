@@ -11,8 +11,8 @@ use crate::parameters::{
 use crate::Directories;
 
 use crate::exp_setup::defaults::{
-    ADH_MAG, CHAR_QUANTS, RAW_COA_PARAMS_WITH_ZERO_MAG, RAW_PARAMS,
-    RAW_WORLD_PARAMS,
+    self, adh_mag, raw_world_params, CHAR_QUANTS,
+    RAW_COA_PARAMS_WITH_ZERO_MAG,
 };
 use crate::exp_setup::exp_parser::ExperimentArgs;
 use crate::exp_setup::markers::mark_verts;
@@ -20,10 +20,10 @@ use crate::utils::pcg32::Pcg32;
 use rand::SeedableRng;
 
 /// Generate the group layout to use for this experiment.
-fn group_bbox(
+fn group_bbox<const N: usize>(
     group_ix: usize,
     char_quants: &CharQuantities,
-    raw_params: &RawParameters,
+    raw_params: &RawParameters<N>,
 ) -> Result<GroupBBox, String> {
     // specify initial location of group centroid
     let inter_group_sep = char_quants
@@ -48,9 +48,16 @@ fn group_bbox(
     }
 }
 
-fn raw_params(group_ix: usize, randomization: bool) -> RawParameters {
-    let right = mark_verts(&[0, 1, 2, 3]);
-    let left = mark_verts(&[8, 9, 10, 11]);
+fn raw_params<const N: usize>(
+    group_ix: usize,
+    randomization: bool,
+) -> RawParameters<N> {
+    // The front and back quarter of the cell's vertices.
+    let right =
+        mark_verts(&(0..N / 4).collect::<Vec<usize>>()).unwrap();
+    let left =
+        mark_verts(&(N / 2..3 * N / 4).collect::<Vec<usize>>())
+            .unwrap();
 
     let (specific_rac, specific_rho) = match group_ix {
         0 => (right, left),
@@ -66,18 +73,18 @@ fn raw_params(group_ix: usize, randomization: bool) -> RawParameters {
         distrib_gens::specific_uniform(0.3, specific_rho);
     let init_rho = RgtpDistribution::new(rho_distrib, rho_distrib);
 
-    RAW_PARAMS
+    defaults::raw_params()
         .modify_randomization(randomization)
         .modify_init_rac(init_rac)
         .modify_init_rho(init_rho)
 }
 
-fn make_cell_group(
+fn make_cell_group<const N: usize>(
     group_ix: usize,
     char_quants: &CharQuantities,
     randomization: bool,
     num_cells: usize,
-) -> CellGroup {
+) -> CellGroup<N> {
     let raw_params = raw_params(group_ix, randomization);
     let parameters = raw_params.refine(char_quants);
     CellGroup {
@@ -85,26 +92,27 @@ fn make_cell_group(
         layout: group_bbox(group_ix, char_quants, &raw_params)
             .unwrap(),
         parameters,
+        cell_parameters: vec![parameters; num_cells],
     }
 }
 
 /// Define the cell groups that will exist in this experiment.
-fn make_cell_groups(
+fn make_cell_groups<const N: usize>(
     char_quants: &CharQuantities,
     randomization: bool,
     num_cells: usize,
-) -> Vec<CellGroup> {
+) -> Vec<CellGroup<N>> {
     (0..num_cells)
         .map(|group_ix| {
             make_cell_group(group_ix, char_quants, randomization, 1)
         })
-        .collect::<Vec<CellGroup>>()
+        .collect::<Vec<CellGroup<N>>>()
 }
 
-pub fn generate(
+pub fn generate<const N: usize>(
     dirs: Directories,
     args: ExperimentArgs,
-) -> Vec<Experiment> {
+) -> Result<Vec<Experiment<N>>, String> {
     let ExperimentArgs {
         ty,
         final_t,
@@ -123,6 +131,20 @@ pub fn generate(
         snap_period,
         max_on_ram,
         int_opts,
+        update_mode,
+        stop_conditions,
+        population,
+        remesh,
+        dense_output,
+        recovery,
+        bdry,
+        chem_attr,
+        chem_field,
+        group_pair_mags,
+        adh_bonds,
+        obstacles,
+        domain,
+        nverts: _,
         ..
     } = args;
 
@@ -147,32 +169,43 @@ pub fn generate(
         )
             );
         };
+    // Each cell is in its own group.
+    if let Some(gpm) = &group_pair_mags {
+        gpm.validate(num_cells)?;
+    }
 
-    seeds
+    Ok(seeds
         .iter()
         .map(|&seed| {
             let rng = Pcg32::seed_from_u64(seed);
 
             let char_quants = CHAR_QUANTS.modify_t(char_t);
-            let raw_world_params = RAW_WORLD_PARAMS
+            let raw_world_params = raw_world_params(N)
                 .modify_interactions(RawInteractionParams {
                     coa: coa_mag.map(|mag| {
                         RAW_COA_PARAMS_WITH_ZERO_MAG
                             .modify_mag(mag)
                             .modify_too_close_dist(too_close_dist)
                     }),
-                    chem_attr: None,
-                    bdry: None,
+                    chem_attr: chem_attr.clone(),
+                    chem_field: chem_field.clone(),
+                    bdry: bdry.clone(),
+                    group_pair_mags: group_pair_mags.clone(),
+                    adh_bonds,
                     phys_contact: RawPhysicalContactParams {
                         zero_at,
                         crl_one_at,
-                        adh_mag: adh_scale.map(|x| ADH_MAG.scale(x)),
+                        adh_mag: adh_scale
+                            .map(|x| adh_mag(N).scale(x)),
                         adh_break,
                         cal_mag,
                         cil_mag,
                     },
-                });
-            let world_params = raw_world_params.refine(&char_quants);
+                })
+                .modify_obstacles(obstacles.clone())
+                .modify_domain(domain);
+            let world_params =
+                raw_world_params.refine(&char_quants, N);
             let cgs = make_cell_groups(
                 &char_quants,
                 randomization,
@@ -191,10 +224,16 @@ pub fn generate(
                 snap_period,
                 max_on_ram,
                 int_opts,
+                update_mode,
+                stop_conditions: stop_conditions.clone(),
+                population: population.clone(),
+                remesh,
+                dense_output,
+                recovery,
                 out_dir: (&dirs.out).clone(),
                 py_main: Some(py_main.clone()),
                 run_python,
             }
         })
-        .collect()
+        .collect::<Vec<Experiment<N>>>())
 }
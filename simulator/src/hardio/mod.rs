@@ -1,22 +1,29 @@
 pub mod py_comp;
 
 use crate::cell::chemistry::RacRandState;
+use crate::cell::rkdp5::IntStats;
 use crate::cell::states::{ChemState, Core, MechState};
 use crate::cell::Cell;
+use crate::interactions::gen_adh::AdhBond;
+use crate::interactions::gen_chem_field::ChemField;
 use crate::interactions::Interactions;
 use crate::parameters::Parameters;
 use crate::utils::pcg32::Pcg32;
-use crate::world::{WorldCells, WorldInfo, WorldState};
+use crate::world::observer::SimObserver;
+use crate::world::population::LineageEvent;
+use crate::world::recovery::Recovery;
+use crate::world::{SimError, WorldCells, WorldInfo, WorldState};
 use bincode::{deserialize_from, serialize_into};
 use serde::{Deserialize, Serialize};
 use serde_cbor::ser::IoWrite;
 use std::borrow::Borrow;
 use std::fs::File;
 use std::fs::OpenOptions;
+use std::io::Seek;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::thread::JoinHandle;
-use std::{io, thread};
+use std::{error, io, thread};
 
 #[derive(Clone, Copy)]
 pub enum Format {
@@ -62,26 +69,27 @@ pub fn get_read_file(
     OpenOptions::new().read(true).open(&path)
 }
 
-pub fn save_binc_to_cbor(binc_path: &PathBuf, cbor_path: &PathBuf) {
-    let mut src =
-        OpenOptions::new().read(true).open(binc_path).unwrap();
+pub fn save_binc_to_cbor<const N: usize>(
+    binc_path: &PathBuf,
+    cbor_path: &PathBuf,
+) -> Result<(), SimError> {
+    let mut src = OpenOptions::new().read(true).open(binc_path)?;
     let dst = OpenOptions::new()
         .write(true)
         .create(true)
         .truncate(true)
-        .open(cbor_path)
-        .unwrap();
+        .open(cbor_path)?;
     let mut serializer =
         serde_cbor::Serializer::new(IoWrite::new(dst));
-    let world_info: WorldInfo = deserialize_from(&mut src).unwrap();
-    world_info.serialize(&mut serializer).unwrap();
+    let world_info: WorldInfo<N> = deserialize_from(&mut src)?;
+    world_info.serialize(&mut serializer)?;
 
     loop {
-        let rd: bincode::Result<Vec<WorldSnapshot>> =
+        let rd: bincode::Result<Vec<WorldSnapshot<N>>> =
             deserialize_from(&mut src);
         match rd {
             Ok(snaps) => {
-                snaps.serialize(&mut serializer).unwrap();
+                snaps.serialize(&mut serializer)?;
             }
             Err(err) => {
                 if let bincode::ErrorKind::Io(std_err) = err.borrow()
@@ -92,36 +100,41 @@ pub fn save_binc_to_cbor(binc_path: &PathBuf, cbor_path: &PathBuf) {
                         break;
                     }
                 }
-                panic!("{}", err);
+                return Err(err.into());
             }
         }
     }
+    Ok(())
 }
 
-pub struct AsyncWriter {
+pub struct AsyncWriter<const N: usize> {
     pub output_dir: PathBuf,
     pub file_name: String,
-    sender: Sender<Vec<WorldState>>,
-    buf: Vec<WorldState>,
+    sender: Option<Sender<Vec<WorldSnapshot<N>>>>,
+    buf: Vec<WorldSnapshot<N>>,
+    /// The most recent snapshot, held back from `buf` so that it can
+    /// be marked with the reason the simulation ended when the writer
+    /// is finished.
+    last: Option<WorldSnapshot<N>>,
     max_capacity: usize,
-    thread_handle: JoinHandle<()>,
+    thread_handle: Option<JoinHandle<bincode::Result<()>>>,
     pub file_path: PathBuf,
     num_saved: usize,
 }
 
-impl AsyncWriter {
+impl<const N: usize> AsyncWriter<N> {
     pub fn new(
         output_dir: PathBuf,
         file_name: String,
         max_capacity: usize,
         truncate: bool,
-        info: WorldInfo,
-    ) -> AsyncWriter {
+        info: WorldInfo<N>,
+    ) -> AsyncWriter<N> {
         let path = output_dir
             .join(get_file_name(Format::Bincode, &file_name));
         let (sender, receiver): (
-            Sender<Vec<WorldState>>,
-            Receiver<Vec<WorldState>>,
+            Sender<Vec<WorldSnapshot<N>>>,
+            Receiver<Vec<WorldSnapshot<N>>>,
         ) = channel();
         println!("output path: {:?}", path);
         let mut file = OpenOptions::new()
@@ -138,75 +151,114 @@ impl AsyncWriter {
 
         let thread_handle = thread::spawn(move || {
             let mut f = file;
-            let cell_params = info.cell_params;
+            // Errors end the thread, so that they can be reported
+            // when the writer is finished.
             let r = receiver;
-            while let Ok(data_vec) = &r.recv() {
-                let snaps: Vec<WorldSnapshot> = data_vec
-                    .iter()
-                    .map(|s| {
-                        WorldSnapshot::from_state(s, &cell_params)
-                    })
-                    .collect();
-                serialize_into(&mut f, &snaps).unwrap();
+            while let Ok(snaps) = &r.recv() {
+                serialize_into(&mut f, snaps)?;
             }
+            Ok(())
         });
 
         AsyncWriter {
             output_dir,
             file_name,
-            sender,
+            sender: Some(sender),
             buf: Vec::with_capacity(max_capacity),
+            last: None,
             max_capacity,
-            thread_handle,
+            thread_handle: Some(thread_handle),
             file_path: path,
             num_saved: 0,
         }
     }
 
-    pub fn push(&mut self, data: WorldState) {
-        self.buf.push(data);
-        if self.buf.len() == self.max_capacity {
-            self.drain();
+    /// Buffer `data`, writing the buffer out once it is full. Batches
+    /// are only written out after a snapshot that is not interpolated,
+    /// so that a simulation can be resumed from the end of any batch.
+    /// The most recent snapshot is only buffered once the next one is
+    /// pushed, or the writer is finished (see `AsyncWriter::finish`).
+    pub fn push(&mut self, data: WorldSnapshot<N>) {
+        if let Some(prev) = self.last.replace(data) {
+            let interpolated = prev.interpolated;
+            self.buf.push(prev);
+            if self.buf.len() >= self.max_capacity && !interpolated {
+                self.drain();
+            }
         }
     }
 
     pub fn drain(&mut self) {
         let num_sent = self.buf.len();
-        if let Ok(()) = self.sender.send(self.buf.drain(..).collect())
-        {
-            self.num_saved += num_sent;
+        if let Some(sender) = &self.sender {
+            if let Ok(()) = sender.send(self.buf.drain(..).collect())
+            {
+                self.num_saved += num_sent;
+            }
         }
     }
 
-    pub fn finish(mut self, save_cbor: bool, reason: &str) {
-        self.drain();
-        let Self {
-            sender,
-            thread_handle,
-            output_dir,
-            file_name,
-            file_path,
-            ..
-        } = self;
-        drop(sender);
-        thread_handle.join().unwrap();
+    /// Number of snapshots sent to be saved to disk so far.
+    pub fn num_saved(&self) -> usize {
+        self.num_saved
+    }
+
+    /// Convert the bincode output file into a cbor file of the same
+    /// name. Should only be called once the writer is finished.
+    pub fn save_cbor(&self) -> Result<(), SimError> {
+        let cbor_path = self
+            .output_dir
+            .join(get_file_name(Format::Cbor, &self.file_name));
+        save_binc_to_cbor::<N>(&self.file_path, &cbor_path)
+    }
+
+    /// Write out any remaining snapshots, and wait for the writer
+    /// thread to finish. The last snapshot saved records `reason`
+    /// as its `stop_reason`. Returns the number of snapshots saved to
+    /// disk.
+    pub fn finish(
+        mut self,
+        save_cbor: bool,
+        reason: &str,
+    ) -> Result<usize, SimError> {
+        self.on_finish(reason)?;
         if save_cbor {
-            let cbor_path = output_dir
-                .join(get_file_name(Format::Cbor, &file_name));
-            save_binc_to_cbor(&file_path, &cbor_path);
+            self.save_cbor()?;
+        }
+        Ok(self.num_saved)
+    }
+}
+
+impl<const N: usize> SimObserver<N> for AsyncWriter<N> {
+    fn on_snapshot(&mut self, snapshot: &WorldSnapshot<N>) {
+        self.push(snapshot.clone());
+    }
+
+    fn on_finish(&mut self, reason: &str) -> Result<(), SimError> {
+        if let Some(mut last) = self.last.take() {
+            last.stop_reason = Some(reason.to_string());
+            self.buf.push(last);
+        }
+        self.drain();
+        // Dropping the sender ends the writer thread once it has
+        // written everything it has received.
+        drop(self.sender.take());
+        if let Some(thread_handle) = self.thread_handle.take() {
+            thread_handle.join().unwrap()?;
         }
         println!(
             "AsyncWriter finishing. Reason: {}. Saved {} snapshots to disk.",
             reason, self.num_saved
         );
+        Ok(())
     }
 }
 
-pub fn load(
+pub fn load<const N: usize>(
     out_dir: &PathBuf,
     format: Format,
     name: &str,
-) -> WorldInfo {
+) -> WorldInfo<N> {
     let mut f = get_read_file(out_dir, name, format).unwrap();
 
     match format {
@@ -215,6 +267,85 @@ pub fn load(
     }
 }
 
+/// Read the number of vertices per cell that the simulation saved in
+/// a bincode output file was run with, so that the file can be
+/// loaded with the matching `N`.
+pub fn read_nverts(file_path: &Path) -> bincode::Result<usize> {
+    let mut f = load_binc_from_path(file_path);
+    deserialize_from(&mut f)
+}
+
+/// Read the `WorldInfo` header, and the last `WorldSnapshot` saved
+/// in a bincode output file, so that a simulation can be resumed
+/// from it. An incomplete batch of snapshots at the end of the file
+/// (left behind by an interrupted write) is truncated away, so that
+/// new snapshots can be appended to the file. Returns an error if
+/// the last snapshot is interpolated, as a simulation cannot be
+/// resumed from it.
+pub fn load_last_snapshot<const N: usize>(
+    file_path: &Path,
+) -> Result<(WorldInfo<N>, WorldSnapshot<N>), Box<dyn error::Error>> {
+    let nverts = read_nverts(file_path)?;
+    if nverts != N {
+        return Err(format!(
+            "{} has {} vertices per cell, expected {}",
+            file_path.display(),
+            nverts,
+            N
+        )
+        .into());
+    }
+    let mut f = load_binc_from_path(file_path);
+    let world_info: WorldInfo<N> = deserialize_from(&mut f)?;
+    let mut last = None;
+    let mut end_of_last_batch = f.stream_position()?;
+    loop {
+        let rd: bincode::Result<Vec<WorldSnapshot<N>>> =
+            deserialize_from(&mut f);
+        match rd {
+            Ok(mut snaps) => {
+                if let Some(snap) = snaps.pop() {
+                    last = Some(snap);
+                }
+                end_of_last_batch = f.stream_position()?;
+            }
+            Err(err) => {
+                if let bincode::ErrorKind::Io(std_err) = err.borrow()
+                {
+                    if let io::ErrorKind::UnexpectedEof =
+                        std_err.kind()
+                    {
+                        break;
+                    }
+                }
+                return Err(err);
+            }
+        }
+    }
+    match last {
+        Some(snap) if snap.interpolated => Err(format!(
+            "last snapshot in {} (at t = {}) is interpolated, and cannot be resumed from",
+            file_path.display(),
+            snap.tpoint
+        )
+        .into()),
+        None => Err(format!(
+            "no snapshots found in: {}",
+            file_path.display()
+        )
+        .into()),
+        Some(snap) => {
+            if f.metadata()?.len() > end_of_last_batch {
+                OpenOptions::new()
+                    .write(true)
+                    .open(file_path)?
+                    .set_len(end_of_last_batch)?;
+            }
+            Ok((world_info, snap))
+        }
+    }
+}
+
 pub fn load_binc_from_path(file_path: &Path) -> File {
     if let Some(ext) = file_path.extension() {
         match ext.to_str().unwrap() {
@@ -240,38 +371,49 @@ pub fn load_binc_from_path(file_path: &Path) -> File {
 
 /// Cell state structure.
 #[derive(
-    Copy, Clone, Deserialize, Serialize, PartialEq, Default, Debug,
+    Clone, Deserialize, Serialize, PartialEq, Default, Debug,
 )]
-pub struct CellSnapshot {
+pub struct CellSnapshot<const N: usize> {
     pub tpoint: f64,
     /// Index of cell within world.
     pub ix: usize,
+    /// Identifier of the cell, which does not change over its
+    /// lifetime.
+    pub id: usize,
     /// Index of group that cell belongs to.
     pub group_ix: usize,
     /// State of Random Rac1 activity that affected `core`.
-    pub rac_rand: RacRandState,
+    pub rac_rand: RacRandState<N>,
     /// Core state of the cell (position, Rho GTPase).
-    pub core: Core,
+    pub core: Core<N>,
     /// Mechanical state due to core.
-    pub mech: MechState,
+    pub mech: MechState<N>,
     /// Interactions that affect the cell's chemical state.
-    pub interactions: Interactions,
+    pub interactions: Interactions<N>,
     /// Chemical state due to core, and interactions.
-    pub chem: ChemState,
+    pub chem: ChemState<N>,
+    /// Integrator statistics from each time step since the previous
+    /// snapshot.
+    pub int_stats: Vec<IntStats>,
+    /// State of the cell's random number generator.
+    pub rng: Pcg32,
 }
 
-impl CellSnapshot {
+impl<const N: usize> CellSnapshot<N> {
     pub fn new(
         tpoint: f64,
-        cell: Cell,
-        parameters: &Parameters,
-        interactions: Interactions,
+        cell: Cell<N>,
+        parameters: &Parameters<N>,
+        interactions: Interactions<N>,
+        int_stats: Vec<IntStats>,
     ) -> Self {
         let Cell {
             ix,
+            id,
             group_ix,
             rac_rand,
             core,
+            rng,
         } = cell;
         let mech = core.calc_mech_state(parameters);
         let chem = core.calc_chem_state(
@@ -283,52 +425,109 @@ impl CellSnapshot {
         CellSnapshot {
             tpoint,
             ix,
+            id,
             group_ix,
             rac_rand,
             core,
             mech,
             interactions,
             chem,
+            int_stats,
+            rng,
         }
     }
 
     pub fn snapshot_cells(
-        cells: &WorldCells,
-        parameters: &[Parameters],
-    ) -> Vec<CellSnapshot> {
+        cells: &WorldCells<N>,
+        parameters: &[Parameters<N>],
+    ) -> Vec<CellSnapshot<N>> {
         let WorldCells {
             tpoint,
             cells: states,
             interactions,
+            int_stats,
         } = cells;
 
         states
             .iter()
             .zip(interactions.iter())
-            .map(|(&s, &i)| {
-                CellSnapshot::new(*tpoint, s, &parameters[s.ix], i)
+            .zip(int_stats.iter())
+            .map(|((&s, &i), st)| {
+                CellSnapshot::new(
+                    *tpoint,
+                    s,
+                    &parameters[s.ix],
+                    i,
+                    st.clone(),
+                )
             })
             .collect()
     }
 }
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
-pub struct WorldSnapshot {
+pub struct WorldSnapshot<const N: usize> {
     pub tpoint: f64,
-    pub cells: Vec<CellSnapshot>,
+    pub cells: Vec<CellSnapshot<N>>,
     pub rng: Pcg32,
+    pub next_cell_id: usize,
+    /// Changes in the cell population since the previous snapshot.
+    pub lineage: Vec<LineageEvent>,
+    /// Retries of time steps after volume exclusion violations since
+    /// the previous snapshot.
+    pub recoveries: Vec<Recovery>,
+    /// Chemoattractant field, if the experiment has one. States saved
+    /// from dense output (see `World::save_interpolated`) have the field at
+    /// the end of the time step they were interpolated from.
+    pub chem_field: Option<ChemField>,
+    /// Adhesion bonds, if the experiment has them. Like `chem_field`,
+    /// states saved from dense output have the bonds at the end of
+    /// the time step they were interpolated from.
+    pub adh_bonds: Option<Vec<AdhBond>>,
+    /// Parameters of each cell, if any differ from those of its
+    /// group (see `WorldInfo::cell_params`, which only holds the
+    /// initial cells).
+    pub cell_params: Option<Vec<Parameters<N>>>,
+    /// Whether the state was interpolated within a time step from
+    /// dense output (see `World::save_interpolated`). Such states mix the
+    /// start and end of the step (for example, in their random
+    /// number generators), so a simulation cannot be resumed from
+    /// them.
+    pub interpolated: bool,
+    /// Why the simulation ended (see `SimSummary::reason`). Only set
+    /// on the last snapshot saved by a simulation.
+    pub stop_reason: Option<String>,
 }
 
-impl WorldSnapshot {
+impl<const N: usize> WorldSnapshot<N> {
     pub fn from_state(
-        state: &WorldState,
-        parameters: &[Parameters],
-    ) -> WorldSnapshot {
-        let WorldState { tpoint, cells, rng } = state;
+        state: &WorldState<N>,
+        parameters: &[Parameters<N>],
+        lineage: Vec<LineageEvent>,
+        recoveries: Vec<Recovery>,
+        chem_field: Option<ChemField>,
+        adh_bonds: Option<Vec<AdhBond>>,
+        cell_params: Option<Vec<Parameters<N>>>,
+        interpolated: bool,
+    ) -> WorldSnapshot<N> {
+        let WorldState {
+            tpoint,
+            cells,
+            rng,
+            next_cell_id,
+        } = state;
         WorldSnapshot {
             tpoint: *tpoint,
             cells: CellSnapshot::snapshot_cells(cells, parameters),
             rng: *rng,
+            next_cell_id: *next_cell_id,
+            lineage,
+            recoveries,
+            chem_field,
+            adh_bonds,
+            cell_params,
+            interpolated,
+            stop_reason: None,
         }
     }
 }
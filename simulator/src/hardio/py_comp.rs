@@ -2,8 +2,8 @@ use crate::parameters::quantity::Quantity;
 use crate::parameters::{
     CharQuantities, Parameters, WorldParameters,
 };
-use crate::NVERTS;
 use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
 use std::fs::{create_dir_all, OpenOptions};
 use std::path::PathBuf;
 
@@ -12,10 +12,8 @@ use std::path::PathBuf;
 /// and`parameters::Parameters`. However, some types are simplified (e.g.
 /// types representing quantities are turned to `f64`). Meant for comparison
 /// with output generated by Python model.
-#[derive(
-    Clone, Deserialize, Serialize, Default, Debug, PartialEq,
-)]
-pub struct Header {
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
+pub struct Header<const N: usize> {
     pub num_tsteps: usize,
     pub num_int_steps: usize,
     pub num_cells: usize,
@@ -44,9 +42,11 @@ pub struct Header {
     pub stiffness_cyto: f64,
     pub diffusion_rgtp: f64,
     /// Simplified from `parameters::Parameters`, where it is `RgtpDistribution`.
-    pub init_rac: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub init_rac: [f64; N],
     /// Simplified from `parameters::Parameters`, where it is `RgtpDistribution`.
-    pub init_rho: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub init_rho: [f64; N],
     pub halfmax_vertex_rgtp_act: f64,
     pub halfmax_vertex_rgtp_conc: f64,
     pub kgtp_rac: f64,
@@ -66,47 +66,141 @@ pub struct Header {
     pub num_rand_vs: u32,
 }
 
+impl<const N: usize> Default for Header<N> {
+    fn default() -> Self {
+        Header {
+            num_tsteps: Default::default(),
+            num_int_steps: Default::default(),
+            num_cells: Default::default(),
+            eta: Default::default(),
+            f: Default::default(),
+            l: Default::default(),
+            t: Default::default(),
+            l3d: Default::default(),
+            k_mem_on_vertex: Default::default(),
+            k_mem_off: Default::default(),
+            kgtp: Default::default(),
+            close_zero_at: Default::default(),
+            close_one_at: Default::default(),
+            cil_mag: Default::default(),
+            coa_los_penalty: Default::default(),
+            coa_halfmax_dist: Default::default(),
+            coa_distrib_exp: Default::default(),
+            coa_mag: Default::default(),
+            vertex_eta: Default::default(),
+            cell_r: Default::default(),
+            rest_edge_len: Default::default(),
+            rest_area: Default::default(),
+            stiffness_edge: Default::default(),
+            const_protrusive: Default::default(),
+            const_retractive: Default::default(),
+            stiffness_cyto: Default::default(),
+            diffusion_rgtp: Default::default(),
+            init_rac: [0.0; N],
+            init_rho: [0.0; N],
+            halfmax_vertex_rgtp_act: Default::default(),
+            halfmax_vertex_rgtp_conc: Default::default(),
+            kgtp_rac: Default::default(),
+            kgtp_rac_auto: Default::default(),
+            kdgtp_rac: Default::default(),
+            kdgtp_rho_on_rac: Default::default(),
+            halfmax_tension_inhib: Default::default(),
+            tension_inhib: Default::default(),
+            kgtp_rho: Default::default(),
+            kgtp_rho_auto: Default::default(),
+            kdgtp_rho: Default::default(),
+            kdgtp_rac_on_rho: Default::default(),
+            randomization: Default::default(),
+            rand_avg_t: Default::default(),
+            rand_std_t: Default::default(),
+            rand_mag: Default::default(),
+            num_rand_vs: Default::default(),
+        }
+    }
+}
+
 /// Stores data generated in intermediate integration steps.
-#[derive(
-    Clone, Deserialize, Serialize, Default, Debug, PartialEq,
-)]
-pub struct IntStepData {
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
+pub struct IntStepData<const N: usize> {
     pub poly: Vec<[f64; 2]>,
-    pub rac_acts: [f64; NVERTS],
-    pub rac_inacts: [f64; NVERTS],
-    pub rho_acts: [f64; NVERTS],
-    pub rho_inacts: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub rac_acts: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rac_inacts: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rho_acts: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rho_inacts: [f64; N],
     pub sum_forces: Vec<[f64; 2]>,
     pub uivs: Vec<[f64; 2]>,
-    pub kgtps_rac: [f64; NVERTS],
-    pub kdgtps_rac: [f64; NVERTS],
-    pub kgtps_rho: [f64; NVERTS],
-    pub kdgtps_rho: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub kgtps_rac: [f64; N],
+    #[serde(with = "BigArray")]
+    pub kdgtps_rac: [f64; N],
+    #[serde(with = "BigArray")]
+    pub kgtps_rho: [f64; N],
+    #[serde(with = "BigArray")]
+    pub kdgtps_rho: [f64; N],
     pub rgtp_forces: Vec<[f64; 2]>,
     pub edge_forces: Vec<[f64; 2]>,
     pub cyto_forces: Vec<[f64; 2]>,
-    pub x_cils: [f64; NVERTS],
-    pub x_coas: [f64; NVERTS],
-    pub rac_act_net_fluxes: [f64; NVERTS],
-    pub edge_strains: [f64; NVERTS],
+    #[serde(with = "BigArray")]
+    pub x_cils: [f64; N],
+    #[serde(with = "BigArray")]
+    pub x_coas: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rac_act_net_fluxes: [f64; N],
+    #[serde(with = "BigArray")]
+    pub edge_strains: [f64; N],
     pub poly_area: f64,
-    pub coa_updates: [bool; NVERTS],
-    pub cil_updates: [bool; NVERTS],
+    #[serde(with = "BigArray")]
+    pub coa_updates: [bool; N],
+    #[serde(with = "BigArray")]
+    pub cil_updates: [bool; N],
     pub uevs: Vec<[f64; 2]>,
 }
 
+impl<const N: usize> Default for IntStepData<N> {
+    fn default() -> Self {
+        IntStepData {
+            poly: Default::default(),
+            rac_acts: [0.0; N],
+            rac_inacts: [0.0; N],
+            rho_acts: [0.0; N],
+            rho_inacts: [0.0; N],
+            sum_forces: Default::default(),
+            uivs: Default::default(),
+            kgtps_rac: [0.0; N],
+            kdgtps_rac: [0.0; N],
+            kgtps_rho: [0.0; N],
+            kdgtps_rho: [0.0; N],
+            rgtp_forces: Default::default(),
+            edge_forces: Default::default(),
+            cyto_forces: Default::default(),
+            x_cils: [0.0; N],
+            x_coas: [0.0; N],
+            rac_act_net_fluxes: [0.0; N],
+            edge_strains: [0.0; N],
+            poly_area: Default::default(),
+            coa_updates: [false; N],
+            cil_updates: [false; N],
+            uevs: Default::default(),
+        }
+    }
+}
+
 #[derive(
     Clone, Deserialize, Serialize, Default, Debug, PartialEq,
 )]
-pub struct CellData {
-    int_steps: Vec<IntStepData>,
+pub struct CellData<const N: usize> {
+    int_steps: Vec<IntStepData<N>>,
 }
 
-impl CellData {
+impl<const N: usize> CellData<N> {
     pub fn save(
         &mut self,
         max_int_steps: usize,
-        int_step: IntStepData,
+        int_step: IntStepData<N>,
     ) {
         if self.int_steps.len() >= max_int_steps {
             panic!("cannot save int step, already have {} int steps stored (max: {})",
@@ -122,12 +216,12 @@ impl CellData {
 #[derive(
     Clone, Deserialize, Serialize, Default, Debug, PartialEq,
 )]
-pub struct StepData {
-    cells: Vec<CellData>,
+pub struct StepData<const N: usize> {
+    cells: Vec<CellData<N>>,
 }
 
-impl StepData {
-    pub fn save(&mut self, max_cells: usize, cell_data: CellData) {
+impl<const N: usize> StepData<N> {
+    pub fn save(&mut self, max_cells: usize, cell_data: CellData<N>) {
         if self.cells.len() >= max_cells {
             panic!("cannot save cell, already have {} cells stored (max: {})",
                    self.cells.len(),
@@ -142,18 +236,18 @@ impl StepData {
 #[derive(
     Clone, Deserialize, Serialize, Default, Debug, PartialEq,
 )]
-pub struct SimulationData {
-    header: Header,
-    pub tsteps: Vec<StepData>,
+pub struct SimulationData<const N: usize> {
+    header: Header<N>,
+    pub tsteps: Vec<StepData<N>>,
 }
 
 #[derive(Default)]
-pub struct Writer {
+pub struct Writer<const N: usize> {
     out_dir: PathBuf,
     file_name: PathBuf,
-    pub data: SimulationData,
-    curr_step_data: StepData,
-    curr_cell_data: CellData,
+    pub data: SimulationData<N>,
+    curr_step_data: StepData<N>,
+    curr_cell_data: CellData<N>,
     curr_int_step_ix: usize,
     curr_cell_ix: usize,
     pub num_tsteps: usize,
@@ -164,7 +258,7 @@ pub struct Writer {
     pub can_save_int_step: bool,
 }
 
-impl Writer {
+impl<const N: usize> Writer<N> {
     pub fn init(
         &mut self,
         out_dir: PathBuf,
@@ -174,7 +268,7 @@ impl Writer {
         num_cells: usize,
         cil_mag: u32,
         coa_mag: u32,
-    ) -> Writer {
+    ) -> Writer<N> {
         let out_dir: PathBuf =
             [out_dir, PathBuf::from("rust")].iter().collect();
         let file_name: PathBuf = PathBuf::from(format!(
@@ -214,7 +308,7 @@ impl Writer {
         }
     }
 
-    pub fn save_int_step(&mut self, int_step: IntStepData) {
+    pub fn save_int_step(&mut self, int_step: IntStepData<N>) {
         if self.can_save_int_step {
             self.curr_cell_data.save(self.num_int_steps, int_step);
             self.curr_int_step_ix += 1;
@@ -314,7 +408,7 @@ impl Writer {
         &mut self,
         char_quants: &CharQuantities,
         world_params: &WorldParameters,
-        params: &Parameters,
+        params: &Parameters<N>,
     ) {
         self.data.header = Header {
             num_tsteps: self.num_tsteps,
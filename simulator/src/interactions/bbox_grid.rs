@@ -0,0 +1,225 @@
+use crate::math::geometry::{BBox, PeriodicDomain};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Range of bins, `(imin, imax, jmin, jmax)`, overlapped by a box.
+type BinRange = (i64, i64, i64, i64);
+
+/// Uniform grid of square bins, used to quickly find the bounding
+/// boxes (one per cell) which may intersect a region, without
+/// checking every cell. Each box is recorded in every bin it
+/// overlaps.
+///
+/// If there is a periodic domain, boxes are binned by their image in
+/// the domain, and regions are searched for along with their images
+/// in neighbouring copies of the domain.
+#[derive(
+    Clone, Deserialize, Serialize, PartialEq, Default, Debug,
+)]
+pub struct BBoxGrid {
+    /// Side length of a bin.
+    bin_size: f64,
+    domain: Option<PeriodicDomain>,
+    bboxes: Vec<BBox>,
+    ranges: Vec<BinRange>,
+    bins: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl BBoxGrid {
+    /// Create a grid holding `bboxes`, with bins as large as the
+    /// largest of the boxes.
+    pub fn new(
+        bboxes: Vec<BBox>,
+        domain: Option<PeriodicDomain>,
+    ) -> BBoxGrid {
+        let bin_size = bboxes
+            .iter()
+            .map(|bb| (bb.xmax - bb.xmin).max(bb.ymax - bb.ymin))
+            .fold(0.0, f64::max);
+        let mut grid = BBoxGrid {
+            bin_size: if bin_size.is_finite() && bin_size > 0.0 {
+                bin_size
+            } else {
+                1.0
+            },
+            domain,
+            bboxes: vec![],
+            ranges: vec![],
+            bins: HashMap::new(),
+        };
+        for bb in bboxes {
+            grid.push(bb);
+        }
+        grid
+    }
+
+    /// Range of bins in which box `bb` is recorded: those overlapped
+    /// by its image in the domain, if there is a periodic domain.
+    fn stored_range(&self, bb: &BBox) -> BinRange {
+        match &self.domain {
+            Some(domain) => self.bin_range(
+                &bb.translate(&domain.wrap_shift(&bb.center())),
+            ),
+            None => self.bin_range(bb),
+        }
+    }
+
+    fn bin_range(&self, bb: &BBox) -> BinRange {
+        (
+            (bb.xmin / self.bin_size).floor() as i64,
+            (bb.xmax / self.bin_size).floor() as i64,
+            (bb.ymin / self.bin_size).floor() as i64,
+            (bb.ymax / self.bin_size).floor() as i64,
+        )
+    }
+
+    fn insert(&mut self, ix: usize) {
+        let (imin, imax, jmin, jmax) = self.ranges[ix];
+        for i in imin..=imax {
+            for j in jmin..=jmax {
+                self.bins.entry((i, j)).or_default().push(ix);
+            }
+        }
+    }
+
+    fn take_out(&mut self, ix: usize) {
+        let (imin, imax, jmin, jmax) = self.ranges[ix];
+        for i in imin..=imax {
+            for j in jmin..=jmax {
+                if let Some(bin) = self.bins.get_mut(&(i, j)) {
+                    bin.retain(|&k| k != ix);
+                    if bin.is_empty() {
+                        self.bins.remove(&(i, j));
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn bbox(&self, ix: usize) -> &BBox {
+        &self.bboxes[ix]
+    }
+
+    /// Whether box `ix`, or its image nearest to `bb` if there is a
+    /// periodic domain, intersects `bb`.
+    pub fn intersects(&self, ix: usize, bb: &BBox) -> bool {
+        let other = &self.bboxes[ix];
+        match &self.domain {
+            Some(domain) => other
+                .translate(
+                    &domain
+                        .image_shift(&bb.center(), &other.center()),
+                )
+                .intersects(bb),
+            None => other.intersects(bb),
+        }
+    }
+
+    /// Replace box `ix`.
+    pub fn update(&mut self, ix: usize, bb: BBox) {
+        let range = self.stored_range(&bb);
+        self.bboxes[ix] = bb;
+        if range != self.ranges[ix] {
+            self.take_out(ix);
+            self.ranges[ix] = range;
+            self.insert(ix);
+        }
+    }
+
+    /// Add a box, which will have index `len`.
+    pub fn push(&mut self, bb: BBox) {
+        self.ranges.push(self.stored_range(&bb));
+        self.bboxes.push(bb);
+        self.insert(self.bboxes.len() - 1);
+    }
+
+    /// Remove box `ix`. Boxes with greater indices have their indices
+    /// shifted down by one.
+    pub fn remove(&mut self, ix: usize) {
+        self.take_out(ix);
+        self.bboxes.remove(ix);
+        self.ranges.remove(ix);
+        for bin in self.bins.values_mut() {
+            for k in bin.iter_mut() {
+                if *k > ix {
+                    *k -= 1;
+                }
+            }
+        }
+    }
+
+    /// Call `f` once, in no particular order, with the index of each
+    /// box which shares a bin with `bb`. This includes every box
+    /// intersecting `bb`, but may also include some which do not, so
+    /// callers should check the boxes themselves (see `intersects`).
+    ///
+    /// If there is a periodic domain, `f` is called for the boxes
+    /// near each image of `bb` in turn, so it may be called more than
+    /// once with the same index.
+    pub fn for_each_near<F: FnMut(usize)>(
+        &self,
+        bb: &BBox,
+        mut f: F,
+    ) {
+        match &self.domain {
+            Some(domain) => {
+                for image in domain.bbox_images(bb) {
+                    self.for_each_near_image(&image, &mut f);
+                }
+            }
+            None => self.for_each_near_image(bb, &mut f),
+        }
+    }
+
+    /// Like `for_each_near`, without considering the images of `bb`.
+    fn for_each_near_image<F: FnMut(usize)>(
+        &self,
+        bb: &BBox,
+        f: &mut F,
+    ) {
+        let (imin, imax, jmin, jmax) = self.bin_range(bb);
+        // A box sharing several bins with `bb` is only visited in the
+        // first of them.
+        let mut visit = |i: i64, j: i64, bin: &[usize]| {
+            for &k in bin {
+                let (kimin, _, kjmin, _) = self.ranges[k];
+                if i == kimin.max(imin) && j == kjmin.max(jmin) {
+                    f(k);
+                }
+            }
+        };
+        let num_bins = imax
+            .saturating_sub(imin)
+            .saturating_add(1)
+            .saturating_mul(
+                jmax.saturating_sub(jmin).saturating_add(1),
+            );
+        if num_bins > self.bins.len() as i64 {
+            // The region is large compared to the occupied part of
+            // the grid.
+            for (&(i, j), bin) in self.bins.iter() {
+                if imin <= i && i <= imax && jmin <= j && j <= jmax {
+                    visit(i, j, bin);
+                }
+            }
+        } else {
+            for i in imin..=imax {
+                for j in jmin..=jmax {
+                    if let Some(bin) = self.bins.get(&(i, j)) {
+                        visit(i, j, bin);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Indices, in increasing order, of the boxes which share a bin
+    /// with `bb` (see `for_each_near`).
+    pub fn query(&self, bb: &BBox) -> Vec<usize> {
+        let mut r = vec![];
+        self.for_each_near(bb, |k| r.push(k));
+        r.sort_unstable();
+        r.dedup();
+        r
+    }
+}
@@ -1,5 +1,6 @@
-use crate::NVERTS;
+use crate::interactions::dat_utils::ix_after_removal;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 
 // /// `CcVvDat` allows storage of (cell, vertex)-(cell, vertex) data
@@ -25,7 +26,7 @@ use std::fmt::Debug;
 //     /// Generate an empty `CcVvDat` structure.
 //     pub fn empty(num_cells: u32, undefined: T) -> CcVvDat<T> {
 //         // number of possible vertex-vertex pairs.
-//         let vv_stride = NVERTS * NVERTS;
+//         let vv_stride = N * N;
 //         // number of cells a cell can interact with (ignores self)
 //         let cc_stride = (num_cells - 1) * vv_stride;
 //         CcVvDat {
@@ -47,7 +48,7 @@ use std::fmt::Debug;
 //         ovi: u32,
 //     ) -> u32 {
 //         let oci = if ci < oci { oci - 1 } else { oci };
-//         ci * self.cc_stride + oci * self.vv_stride + vi * NVERTS + ovi
+//         ci * self.cc_stride + oci * self.vv_stride + vi * N + ovi
 //     }
 //
 //     /// Set data into the element indexed by `(ci, vi, oci, ovi)`.
@@ -99,58 +100,69 @@ use std::fmt::Debug;
 /// `CvCvDat` allows storage of (cell, vertex)-(cell, vertex) data
 /// indexed by `(ci, vi, oci, ovi)`, (`ci` for "cell index", `oci` for
 /// "other cell index", `vi` for "vertex index", and `ovi` for "other
-/// vertex index"). Data is stored in chunks relative to one cell, and
-/// a particular vertex on it. All data stored must have
-/// the same type. However, the structure is generic over different
-/// data types as long as they implement `Copy` and `Default`.
+/// vertex index"). Data is only stored for pairs of cells which have
+/// been paired (see `pair`), in chunks relative to one cell and
+/// another, so memory grows with the number of pairs rather than the
+/// square of the number of cells. Data of unpaired cells is
+/// `undefined`. All data stored must have the same type. However, the
+/// structure is generic over different data types as long as they
+/// implement `Copy` and `Default`.
 #[derive(
     Clone, Serialize, Deserialize, PartialEq, Default, Debug,
 )]
-pub struct CvCvDat<T: Copy + Default + Debug + PartialEq> {
+pub struct CvCvDat<
+    T: Copy + Default + Debug + PartialEq,
+    const N: usize,
+> {
     pub num_cells: usize,
-    c_stride: usize,
-    cv_stride: usize,
-    dat: Vec<T>,
+    /// Chunks of data relative to each cell, keyed by the index of
+    /// the other cell. Chunks store data for vertex `vi` and other
+    /// vertex `ovi` at `vi * N + ovi`.
+    dat: Vec<BTreeMap<usize, Vec<T>>>,
     undefined: T,
 }
 
-impl<T: Copy + Debug + Default + PartialEq> CvCvDat<T> {
+impl<T: Copy + Debug + Default + PartialEq, const N: usize>
+    CvCvDat<T, N>
+{
     /// Generate an empty `CvCvDat` structure.
-    pub fn empty(num_cells: usize, undefined: T) -> CvCvDat<T> {
-        let cv_stride = (num_cells - 1) * NVERTS;
-        let c_stride = NVERTS * cv_stride;
+    pub fn empty(num_cells: usize, undefined: T) -> CvCvDat<T, N> {
         CvCvDat {
             num_cells,
-            c_stride,
-            cv_stride,
-            dat: vec![undefined; num_cells * c_stride],
+            dat: vec![BTreeMap::new(); num_cells],
             undefined,
         }
     }
 
-    pub fn calc_ix(
+    /// Drop data of cell `ci` relative to `oci`.
+    pub fn unpair(&mut self, ci: usize, oci: usize) {
+        self.dat[ci].remove(&oci);
+    }
+
+    /// Indices of the cells `ci` is paired with, in increasing order.
+    pub fn partners(
         &self,
         ci: usize,
-        vi: usize,
-        oci: usize,
-        ovi: usize,
-    ) -> usize {
-        let oci = if ci < oci { oci - 1 } else { oci };
-        ci * self.c_stride + vi * self.cv_stride + oci * NVERTS + ovi
+    ) -> impl Iterator<Item = usize> + '_ {
+        self.dat[ci].keys().copied()
+    }
+
+    /// Data of cell `ci` relative to `oci`, with the data for vertex
+    /// `vi` and other vertex `ovi` at `vi * N + ovi`, if they are
+    /// paired.
+    pub fn chunk(&self, ci: usize, oci: usize) -> Option<&[T]> {
+        self.dat[ci].get(&oci).map(|chunk| chunk.as_slice())
     }
 
-    // pub fn calc_ix_range(
-    //     &self,
-    //     ci: u32,
-    //     vi: u32,
-    //     oci: u32,
-    // ) -> (u32, u32) {
-    //     let oci = if ci < oci { oci - 1 } else { oci };
-    //     let begin =
-    //         ci * self.c_stride + vi * self.cv_stride + oci * NVERTS;
-    //     (begin, begin + NVERTS)
-    // }
-    //
+    /// Mutable version of `chunk`, pairing `ci` with `oci` if needed.
+    pub fn chunk_mut(&mut self, ci: usize, oci: usize) -> &mut [T] {
+        let undefined = self.undefined;
+        self.dat[ci]
+            .entry(oci)
+            .or_insert_with(|| vec![undefined; N * N])
+    }
+
+    /// Set data, pairing `ci` with `oci` if needed.
     pub fn set(
         &mut self,
         ci: usize,
@@ -160,24 +172,10 @@ impl<T: Copy + Debug + Default + PartialEq> CvCvDat<T> {
         x: T,
     ) {
         if ci != oci {
-            let ix = self.calc_ix(ci, vi, oci, ovi);
-            self.dat[ix] = x;
+            self.chunk_mut(ci, oci)[vi * N + ovi] = x;
         }
     }
 
-    // pub fn reset(
-    //     &mut self,
-    //     ci: u32,
-    //     vi: u32,
-    //     oci: u32,
-    //     ovi: u32,
-    // ) {
-    //     if ci != oci {
-    //         let ix = self.calc_ix(ci, vi, oci, ovi);
-    //         self.dat[ix] = self.undefined;
-    //     }
-    // }
-
     pub fn get(
         &self,
         ci: usize,
@@ -185,25 +183,31 @@ impl<T: Copy + Debug + Default + PartialEq> CvCvDat<T> {
         oci: usize,
         ovi: usize,
     ) -> T {
-        if ci == oci {
-            self.undefined
-        } else {
-            let ix = self.calc_ix(ci, vi, oci, ovi);
-            self.dat[ix]
-        }
+        self.dat[ci]
+            .get(&oci)
+            .map_or(self.undefined, |chunk| chunk[vi * N + ovi])
     }
 
-    // pub fn get_per_other_vertex(
-    //     &self,
-    //     ci: u32,
-    //     vi: u32,
-    //     oci: u32,
-    // ) -> [T; NVERTS] {
-    //     let mut r = [self.undefined; NVERTS];
-    //     if ci != oci {
-    //         let (begin, end) = self.calc_ix_range(ci, vi, oci);
-    //         r.copy_from_slice(&self.dat[begin..end]);
-    //     }
-    //     r
-    // }
+    /// Make room for a new cell, which will have index `num_cells`.
+    /// It is not paired with any cell.
+    pub fn add_cell(&mut self) {
+        self.dat.push(BTreeMap::new());
+        self.num_cells += 1;
+    }
+
+    /// Remove data involving cell `removed`. Cells with indices
+    /// greater than `removed` have their indices shifted down by one.
+    pub fn remove_cell(&mut self, removed: usize) {
+        self.dat.remove(removed);
+        for chunks in self.dat.iter_mut() {
+            *chunks = std::mem::take(chunks)
+                .into_iter()
+                .filter(|(oci, _)| *oci != removed)
+                .map(|(oci, chunk)| {
+                    (ix_after_removal(oci, removed), chunk)
+                })
+                .collect();
+        }
+        self.num_cells -= 1;
+    }
 }
@@ -1,4 +1,6 @@
-use crate::interactions::dat_utils::{sort_ixs, sym_sum};
+use crate::interactions::dat_utils::{
+    ix_before_removal, sort_ixs, sym_sum,
+};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
@@ -104,4 +106,35 @@ impl<T: Copy + Default + PartialEq + Debug> SymCcDat<T> {
             self.dat[ix]
         }
     }
+
+    /// Make room for a new cell, which will have index `num_cells`.
+    /// Its data is initialized to `undefined`.
+    pub fn add_cell(&mut self) {
+        let mut r = SymCcDat::new(self.num_cells + 1, self.undefined);
+        for ci in 0..self.num_cells {
+            for oci in (ci + 1)..self.num_cells {
+                r.set(ci, oci, self.get(ci, oci));
+            }
+        }
+        *self = r;
+    }
+
+    /// Remove data involving cell `removed`. Cells with indices
+    /// greater than `removed` have their indices shifted down by one.
+    pub fn remove_cell(&mut self, removed: usize) {
+        let mut r = SymCcDat::new(self.num_cells - 1, self.undefined);
+        for ci in 0..r.num_cells {
+            for oci in (ci + 1)..r.num_cells {
+                r.set(
+                    ci,
+                    oci,
+                    self.get(
+                        ix_before_removal(ci, removed),
+                        ix_before_removal(oci, removed),
+                    ),
+                );
+            }
+        }
+        *self = r;
+    }
 }
@@ -1,43 +1,83 @@
-use crate::interactions::dat_utils::{sort_ixs4d, sym_sum};
-use crate::NVERTS;
+use crate::interactions::dat_utils::{
+    ix_after_removal, sort_ixs, sort_ixs4d,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 
-/// Matrix to store inter-vertex data.
+/// Matrix to store inter-vertex data, which is symmetric: the data
+/// indexed by `(ci, vi, oci, ovi)` is the same as that indexed by
+/// `(oci, ovi, ci, vi)`. Data is only stored for pairs of cells which
+/// have been paired (see `pair`), so memory grows with the number of
+/// pairs rather than the square of the number of cells. Data of
+/// unpaired cells is `undefined`.
 #[derive(Clone, Deserialize, Serialize)]
-pub struct SymCcVvDat<T: Copy> {
-    num_cells: usize,
-    sym_sum_n: usize,
-    vv_stride: usize,
-    dat: Vec<T>,
+pub struct SymCcVvDat<T: Copy, const N: usize> {
+    /// Indices of the cells each cell is paired with.
+    partners: Vec<BTreeSet<usize>>,
+    /// Data of the pair of cells `(s, b)`, where `s < b`, is stored
+    /// in `dat[s]` under key `b`, with the data for vertex `sv` on
+    /// `s` and vertex `bv` on `b` at `sv * N + bv`.
+    dat: Vec<BTreeMap<usize, Vec<T>>>,
     undefined: T,
 }
 
-impl<T: Copy> SymCcVvDat<T> {
-    pub fn empty(num_cells: usize, undefined: T) -> SymCcVvDat<T> {
-        let sym_sum_n = sym_sum(num_cells);
-        let vv_stride = NVERTS * NVERTS;
+impl<T: Copy, const N: usize> SymCcVvDat<T, N> {
+    pub fn empty(num_cells: usize, undefined: T) -> SymCcVvDat<T, N> {
         SymCcVvDat {
-            num_cells,
-            sym_sum_n,
-            vv_stride,
-            dat: vec![undefined; sym_sum_n * vv_stride],
+            partners: vec![BTreeSet::new(); num_cells],
+            dat: vec![BTreeMap::new(); num_cells],
             undefined,
         }
     }
 
-    pub fn calc_ix(
+    pub fn num_cells(&self) -> usize {
+        self.partners.len()
+    }
+
+    /// Make room for data of the pair `(ci, oci)`, initialized to
+    /// `undefined`, if there is none yet.
+    pub fn pair(&mut self, ci: usize, oci: usize) {
+        if ci != oci && self.partners[ci].insert(oci) {
+            self.partners[oci].insert(ci);
+            let (s, b) = sort_ixs(ci, oci);
+            self.dat[s].insert(b, vec![self.undefined; N * N]);
+        }
+    }
+
+    /// Drop data of the pair `(ci, oci)`.
+    pub fn unpair(&mut self, ci: usize, oci: usize) {
+        if self.partners[ci].remove(&oci) {
+            self.partners[oci].remove(&ci);
+            let (s, b) = sort_ixs(ci, oci);
+            self.dat[s].remove(&b);
+        }
+    }
+
+    /// Indices of the cells `ci` is paired with, in increasing order.
+    pub fn partners(
         &self,
         ci: usize,
-        vi: usize,
-        oci: usize,
-        ovi: usize,
-    ) -> usize {
-        let (ci, vi, oci, ovi) = sort_ixs4d(ci, vi, oci, ovi);
-        let k = self.sym_sum_n - sym_sum(self.num_cells - ci);
-        let ixc = k + (oci - ci - 1);
-        ixc * self.vv_stride + vi * NVERTS + ovi
+    ) -> impl Iterator<Item = usize> + '_ {
+        self.partners[ci].iter().copied()
     }
 
+    /// Data of the pair `(s, b)`, where `s < b`, with the data for
+    /// vertex `sv` on `s` and vertex `bv` on `b` at `sv * N + bv`, if
+    /// they are paired.
+    pub fn chunk(&self, s: usize, b: usize) -> Option<&[T]> {
+        self.dat[s].get(&b).map(|chunk| chunk.as_slice())
+    }
+
+    /// Mutable version of `chunk`.
+    pub fn chunk_mut(
+        &mut self,
+        s: usize,
+        b: usize,
+    ) -> Option<&mut [T]> {
+        self.dat[s].get_mut(&b).map(|chunk| chunk.as_mut_slice())
+    }
+
+    /// Set data, pairing `ci` with `oci` if needed.
     pub fn set(
         &mut self,
         ci: usize,
@@ -47,24 +87,14 @@ impl<T: Copy> SymCcVvDat<T> {
         x: T,
     ) {
         if ci != oci {
-            let ix = self.calc_ix(ci, vi, oci, ovi);
-            self.dat[ix] = x;
+            self.pair(ci, oci);
+            let (s, sv, b, bv) = sort_ixs4d(ci, vi, oci, ovi);
+            if let Some(chunk) = self.dat[s].get_mut(&b) {
+                chunk[sv * N + bv] = x;
+            }
         }
     }
 
-    // pub fn reset(
-    //     &mut self,
-    //     ci: u32,
-    //     vi: u32,
-    //     oci: u32,
-    //     ovi: u32,
-    // ) {
-    //     if ci != oci {
-    //         let ix = self.calc_ix(ci, vi, oci, ovi);
-    //         self.dat[ix] = self.undefined;
-    //     }
-    // }
-
     pub fn get(
         &self,
         ci: usize,
@@ -72,11 +102,39 @@ impl<T: Copy> SymCcVvDat<T> {
         oci: usize,
         ovi: usize,
     ) -> T {
-        if ci == oci {
-            self.undefined
-        } else {
-            let ix = self.calc_ix(ci, vi, oci, ovi);
-            self.dat[ix]
+        let (s, sv, b, bv) = sort_ixs4d(ci, vi, oci, ovi);
+        self.dat[s]
+            .get(&b)
+            .map_or(self.undefined, |chunk| chunk[sv * N + bv])
+    }
+
+    /// Make room for a new cell, which will have index `num_cells`.
+    /// It is not paired with any cell.
+    pub fn add_cell(&mut self) {
+        self.partners.push(BTreeSet::new());
+        self.dat.push(BTreeMap::new());
+    }
+
+    /// Remove data involving cell `removed`. Cells with indices
+    /// greater than `removed` have their indices shifted down by one.
+    pub fn remove_cell(&mut self, removed: usize) {
+        self.partners.remove(removed);
+        self.dat.remove(removed);
+        for partners in self.partners.iter_mut() {
+            *partners = std::mem::take(partners)
+                .into_iter()
+                .filter(|&oci| oci != removed)
+                .map(|oci| ix_after_removal(oci, removed))
+                .collect();
+        }
+        for chunks in self.dat.iter_mut() {
+            *chunks = std::mem::take(chunks)
+                .into_iter()
+                .filter(|(oci, _)| *oci != removed)
+                .map(|(oci, chunk)| {
+                    (ix_after_removal(oci, removed), chunk)
+                })
+                .collect();
         }
     }
 }
@@ -46,8 +46,8 @@ pub fn sort_ixs4d(
 //         panic!("{} cells tracked, received ci: {}", num_cells, ci);
 //     }
 //
-//     if vi > NVERTS {
-//         panic!("{} vertices tracked, received vi: {}", NVERTS, vi);
+//     if vi > N {
+//         panic!("{} vertices tracked, received vi: {}", N, vi);
 //     }
 //
 //     if oci > num_cells - 1 {
@@ -59,8 +59,8 @@ pub fn sort_ixs4d(
 //         );
 //     }
 //
-//     if ovi > NVERTS {
-//         panic!("{} vertices tracked, received vi: {}", NVERTS, vi);
+//     if ovi > N {
+//         panic!("{} vertices tracked, received vi: {}", N, vi);
 //     }
 // }
 
@@ -68,7 +68,29 @@ pub fn sort_ixs4d(
 /// removing the `n` diagonal elements, and de-duplication of the
 /// remainder (`/2)`: `R = (n^2 - n) / 2 = n * (n - 1) / 2`
 pub fn sym_sum(n: usize) -> usize {
-    n * (n - 1) / 2
+    n * n.saturating_sub(1) / 2
+}
+
+/// Given the index `ci` of a cell in storage from which cell
+/// `removed` has been taken out, return the index the cell had
+/// before the removal.
+pub fn ix_before_removal(ci: usize, removed: usize) -> usize {
+    if ci < removed {
+        ci
+    } else {
+        ci + 1
+    }
+}
+
+/// Given the index `ci` of a cell in storage from which cell
+/// `removed`, which is not `ci`, is about to be taken out, return the
+/// index the cell will have after the removal.
+pub fn ix_after_removal(ci: usize, removed: usize) -> usize {
+    if ci < removed {
+        ci
+    } else {
+        ci - 1
+    }
 }
 
 // #[cfg(feature = "validate")]
@@ -85,8 +107,8 @@ pub fn sym_sum(n: usize) -> usize {
 //         panic!("{} cells tracked, received ci: {}", num_cells, ci);
 //     }
 //
-//     if vi > NVERTS {
-//         panic!("{} vertices tracked, received vi: {}", NVERTS, vi);
+//     if vi > N {
+//         panic!("{} vertices tracked, received vi: {}", N, vi);
 //     }
 //
 //     if oci > num_cells {
@@ -96,7 +118,7 @@ pub fn sym_sum(n: usize) -> usize {
 //         );
 //     }
 //
-//     if ovi > NVERTS {
-//         panic!("{} vertices tracked, received vi: {}", NVERTS, vi);
+//     if ovi > N {
+//         panic!("{} vertices tracked, received vi: {}", N, vi);
 //     }
 // }
@@ -0,0 +1,329 @@
+use crate::interactions::dat_utils::ix_after_removal;
+use crate::interactions::gen_phys::{
+    CloseEdge, PhysicalContactGenerator,
+};
+use crate::interactions::RelativeRgtpActivity;
+use crate::math::close_to_zero;
+use crate::math::geometry::{PeriodicDomain, Poly};
+use crate::math::v2d::V2d;
+use crate::parameters::{
+    AdhBondParams, GroupPairMags, PhysicalContactParams,
+};
+use crate::utils::circ_ix_plus;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Adhesion bond between vertex `vi` of cell `ci` and the edge of
+/// cell `oci` running from vertex `ovi` to `ovi + 1`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct AdhBond {
+    pub ci: usize,
+    pub vi: usize,
+    pub oci: usize,
+    pub ovi: usize,
+    /// Where the bond is attached along the edge (see
+    /// `CloseEdge::edge_point_param`).
+    pub edge_point_param: f64,
+    /// Length of the bond when it formed. The bond only bears force
+    /// when it is stretched beyond this length.
+    pub rest_len: f64,
+    /// Time since the bond formed.
+    pub age: f64,
+}
+
+impl AdhBond {
+    /// Vector from the bound vertex to the point it is bound to on
+    /// the edge, or on its image nearest to the vertex if there is a
+    /// periodic domain.
+    fn vector<const N: usize>(
+        &self,
+        cell_polys: &[Poly<N>],
+        domain: Option<&PeriodicDomain>,
+    ) -> V2d {
+        let v = cell_polys[self.ci].verts[self.vi];
+        let other = &cell_polys[self.oci].verts;
+        let ov = other[self.ovi];
+        let ow = other[circ_ix_plus(self.ovi, N)];
+        let p = self.edge_point_param * (ow - ov) + ov;
+        match domain {
+            Some(domain) => p + domain.image_shift(&v, &p) - v,
+            None => p - v,
+        }
+    }
+
+    fn involves(&self, ci: usize) -> bool {
+        self.ci == ci || self.oci == ci
+    }
+
+    /// The vertex and edge the bond binds, which no other bond binds.
+    fn key(&self) -> (usize, usize, usize, usize) {
+        (self.ci, self.vi, self.oci, self.ovi)
+    }
+}
+
+/// Generates adhesion from stateful bonds, replacing the adhesion of
+/// `PhysicalContactGenerator`, which is recalculated from the current
+/// distances between cells alone.
+///
+/// A bond may form between a vertex and an edge of another cell
+/// within `form_dist` of it, at rate `form_rate`. The bond pulls the
+/// vertex and edge together like a spring, with the adhesion
+/// magnitude of the two cells reached when it is stretched by
+/// `adh_break` beyond its rest length. It ruptures at rate
+/// `rupture_rate * exp(force / bell_force)`, or as soon as it is
+/// longer than `max_len`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AdhBondGenerator<const N: usize> {
+    bonds: Vec<AdhBond>,
+    /// Indices in `bonds`, in order, of the bonds of each cell which
+    /// has any.
+    cell_bonds: BTreeMap<usize, Vec<usize>>,
+    params: AdhBondParams,
+    /// Adhesion magnitude, if not given for the groups of a pair of
+    /// cells.
+    adh_mag: Option<f64>,
+    adh_break: f64,
+    domain: Option<PeriodicDomain>,
+}
+
+impl<const N: usize> AdhBondGenerator<N> {
+    pub fn new(
+        params: AdhBondParams,
+        phys_contact: &PhysicalContactParams,
+        domain: Option<PeriodicDomain>,
+    ) -> AdhBondGenerator<N> {
+        AdhBondGenerator {
+            bonds: vec![],
+            cell_bonds: BTreeMap::new(),
+            params,
+            adh_mag: phys_contact.adh_mag,
+            adh_break: phys_contact.adh_break,
+            domain,
+        }
+    }
+
+    pub fn bonds(&self) -> &[AdhBond] {
+        &self.bonds
+    }
+
+    pub fn set_bonds(&mut self, bonds: Vec<AdhBond>) {
+        self.bonds = bonds;
+        self.index_bonds();
+    }
+
+    /// Rebuild `cell_bonds` from `bonds`.
+    fn index_bonds(&mut self) {
+        self.cell_bonds.clear();
+        for (bi, b) in self.bonds.iter().enumerate() {
+            self.cell_bonds.entry(b.ci).or_default().push(bi);
+            if b.oci != b.ci {
+                self.cell_bonds.entry(b.oci).or_default().push(bi);
+            }
+        }
+    }
+
+    /// The bonds of cell `ci`, in order.
+    fn bonds_of(&self, ci: usize) -> impl Iterator<Item = &AdhBond> {
+        self.cell_bonds
+            .get(&ci)
+            .into_iter()
+            .flatten()
+            .map(move |&bi| &self.bonds[bi])
+    }
+
+    /// Indices of the cells bonded to cell `ci`.
+    pub fn partners(&self, ci: usize) -> Vec<usize> {
+        self.bonds_of(ci)
+            .map(|b| if b.ci == ci { b.oci } else { b.ci })
+            .collect()
+    }
+
+    /// Adhesion magnitude between a vertex of cell `ci` and an edge
+    /// of cell `oci`, if any.
+    fn adh_mag(
+        &self,
+        ci: usize,
+        oci: usize,
+        cell_groups: &[usize],
+        group_mags: Option<&GroupPairMags>,
+    ) -> Option<f64> {
+        group_mags
+            .and_then(|mags| {
+                mags.adh(cell_groups[ci], cell_groups[oci])
+            })
+            .or(self.adh_mag)
+    }
+
+    /// Force exerted by `bond`, which has vector `vector`, on its
+    /// vertex.
+    fn force(
+        &self,
+        bond: &AdhBond,
+        vector: V2d,
+        cell_groups: &[usize],
+        group_mags: Option<&GroupPairMags>,
+    ) -> V2d {
+        let len = vector.mag();
+        match self.adh_mag(bond.ci, bond.oci, cell_groups, group_mags)
+        {
+            Some(adh_mag) if len > bond.rest_len => {
+                (adh_mag * (len - bond.rest_len) / self.adh_break)
+                    * vector.unitize()
+            }
+            _ => V2d::default(),
+        }
+    }
+
+    /// Advance bonds by a time step of length `dt`: bonds age, may
+    /// rupture, and new bonds may form between the close points found
+    /// by `phys_contact_generator`. Bonds are considered in order,
+    /// followed by close points in order of cell and vertex index, so
+    /// that the result only depends on the state of `rng`. Returns
+    /// the indices of the cells whose bonds changed.
+    pub fn step<R: Rng>(
+        &mut self,
+        dt: f64,
+        cell_polys: &[Poly<N>],
+        phys_contact_generator: &PhysicalContactGenerator<N>,
+        rel_rgtps: &[[RelativeRgtpActivity; N]],
+        cell_groups: &[usize],
+        group_mags: Option<&GroupPairMags>,
+        rng: &mut R,
+    ) -> Vec<usize> {
+        let AdhBondParams {
+            form_dist,
+            form_rate,
+            rupture_rate,
+            bell_force,
+            max_len,
+        } = self.params;
+        let mut changed = vec![];
+        let mut kept = vec![];
+        // A bond which has just ruptured may not form again in the
+        // same step, so the bonds at the start of the step all stay
+        // bound.
+        let mut bound = self
+            .bonds
+            .iter()
+            .map(|b| b.key())
+            .collect::<BTreeSet<(usize, usize, usize, usize)>>();
+        for bond in self.bonds.iter() {
+            let vector =
+                bond.vector(cell_polys, self.domain.as_ref());
+            let force = self
+                .force(bond, vector, cell_groups, group_mags)
+                .mag();
+            let rate = rupture_rate * (force / bell_force).exp();
+            if vector.mag() > max_len
+                || rng.gen::<f64>() < 1.0 - (-rate * dt).exp()
+            {
+                changed.extend([bond.ci, bond.oci]);
+            } else {
+                kept.push(AdhBond {
+                    age: bond.age + dt,
+                    ..*bond
+                });
+            }
+        }
+        let form_prob = 1.0 - (-form_rate * dt).exp();
+        for ci in 0..cell_polys.len() {
+            for vi in 0..N {
+                for CloseEdge {
+                    cell_ix: oci,
+                    vert_ix: ovi,
+                    vector_to,
+                    edge_point_param,
+                    ..
+                } in phys_contact_generator
+                    .get_close_edges_to(ci, vi, rel_rgtps)
+                {
+                    let dist = vector_to.mag();
+                    if dist > form_dist
+                        || self
+                            .adh_mag(ci, oci, cell_groups, group_mags)
+                            .is_none()
+                        || bound.contains(&(ci, vi, oci, ovi))
+                    {
+                        continue;
+                    }
+                    if rng.gen::<f64>() < form_prob {
+                        bound.insert((ci, vi, oci, ovi));
+                        kept.push(AdhBond {
+                            ci,
+                            vi,
+                            oci,
+                            ovi,
+                            edge_point_param,
+                            rest_len: dist,
+                            age: 0.0,
+                        });
+                        changed.extend([ci, oci]);
+                    }
+                }
+            }
+        }
+        self.bonds = kept;
+        self.index_bonds();
+        changed.sort_unstable();
+        changed.dedup();
+        changed
+    }
+
+    /// Remove the bonds of cell `ci`, for example because its
+    /// vertices have been replaced, returning the indices of the
+    /// cells it was bonded to.
+    pub fn release(&mut self, ci: usize) -> Vec<usize> {
+        let partners = self.partners(ci);
+        self.bonds.retain(|b| !b.involves(ci));
+        self.index_bonds();
+        partners
+    }
+
+    /// Remove the bonds of cell `ci`, which is about to be removed,
+    /// and shift the indices of cells after it down by one.
+    pub fn remove_cell(&mut self, ci: usize) {
+        self.bonds.retain(|b| !b.involves(ci));
+        for b in self.bonds.iter_mut() {
+            b.ci = ix_after_removal(b.ci, ci);
+            b.oci = ix_after_removal(b.oci, ci);
+        }
+        self.index_bonds();
+    }
+
+    /// Adhesion forces on the vertices of cell `ci` due to its
+    /// bonds. The force on a bound edge is split between its
+    /// vertices as in `PhysicalContactGenerator::generate`.
+    pub fn generate(
+        &self,
+        ci: usize,
+        cell_polys: &[Poly<N>],
+        cell_groups: &[usize],
+        group_mags: Option<&GroupPairMags>,
+    ) -> [V2d; N] {
+        let mut x_adhs = [V2d::default(); N];
+        for bond in self.bonds_of(ci) {
+            let force = self.force(
+                bond,
+                bond.vector(cell_polys, self.domain.as_ref()),
+                cell_groups,
+                group_mags,
+            );
+            if bond.ci == ci {
+                x_adhs[bond.vi] = x_adhs[bond.vi] + force;
+            }
+            if bond.oci == ci {
+                let t = bond.edge_point_param;
+                if close_to_zero(t, 1e-3) {
+                    x_adhs[bond.ovi] = x_adhs[bond.ovi] - force;
+                } else {
+                    let owi = circ_ix_plus(bond.ovi, N);
+                    x_adhs[bond.ovi] =
+                        x_adhs[bond.ovi] - (1.0 - t) * force;
+                    x_adhs[owi] = x_adhs[owi] - t * force;
+                }
+            }
+        }
+        x_adhs
+    }
+}
@@ -1,59 +1,144 @@
-use crate::math::geometry::{is_point_in_poly, BBox, Poly};
+use crate::math::geometry::{
+    is_point_in_poly, BBox, LineSeg2D, Poly,
+};
 use crate::math::v2d::V2d;
 use crate::parameters::BdryParams;
-use crate::NVERTS;
-use serde::{Deserialize, Serialize};
+use crate::utils::circ_ix_plus;
 
-#[derive(Clone, Deserialize, Serialize)]
-pub struct BdryEffectGenerator {
-    shape: Vec<V2d>,
+/// A wall of the boundary: a polygon which cells may not enter.
+#[derive(Clone)]
+struct Wall {
+    verts: Vec<V2d>,
+    edges: Vec<LineSeg2D>,
     bbox: BBox,
-    skip_bb_check: bool,
+}
+
+impl Wall {
+    fn new(verts: Vec<V2d>) -> Wall {
+        let nverts = verts.len();
+        let edges = (0..nverts)
+            .map(|vi| {
+                LineSeg2D::new(
+                    &verts[vi],
+                    &verts[circ_ix_plus(vi, nverts)],
+                )
+            })
+            .collect::<Vec<LineSeg2D>>();
+        let bbox = BBox::from_points(&verts);
+        Wall { verts, edges, bbox }
+    }
+}
+
+/// Generates boundary interaction factors: vertices which touch a
+/// wall of the boundary experience a CIL-like interaction of
+/// magnitude `mag`. Static obstacles are handled by a generator of
+/// their own, with the obstacles as walls. Since walls do not move,
+/// the factors of a cell only change when the cell moves, so they
+/// are cached, along with the wall edges close to each cell, which
+/// are used to enforce volume exclusion against the boundary.
+#[derive(Clone)]
+pub struct BdryEffectGenerator<const N: usize> {
+    walls: Vec<Wall>,
+    touch_dist_sq: f64,
+    /// Distance by which a cell's bounding box is expanded to find
+    /// the wall edges close to it.
+    search_dist: f64,
     mag: f64,
+    x_bdrys: Vec<[f64; N]>,
+    close_edges: Vec<Vec<LineSeg2D>>,
 }
 
-impl BdryEffectGenerator {
-    pub fn new(params: BdryParams) -> BdryEffectGenerator {
+impl<const N: usize> BdryEffectGenerator<N> {
+    /// Create a generator for the boundary given by `params`. Wall
+    /// edges within `contact_dist` of a cell's bounding box are
+    /// considered close to it.
+    pub fn new(
+        cell_polys: &[Poly<N>],
+        params: BdryParams,
+        contact_dist: f64,
+    ) -> BdryEffectGenerator<N> {
         let BdryParams {
-            shape,
-            bbox,
-            skip_bb_check,
+            shapes,
+            touch_dist,
             mag,
         } = params;
-        BdryEffectGenerator {
-            shape,
-            bbox,
-            skip_bb_check,
+        let mut gen = BdryEffectGenerator {
+            walls: shapes.into_iter().map(Wall::new).collect(),
+            touch_dist_sq: touch_dist * touch_dist,
+            search_dist: touch_dist.max(contact_dist),
             mag,
+            x_bdrys: Vec::with_capacity(cell_polys.len()),
+            close_edges: Vec::with_capacity(cell_polys.len()),
+        };
+        for poly in cell_polys {
+            let (x_bdrys, close_edges) = gen.calc(poly);
+            gen.x_bdrys.push(x_bdrys);
+            gen.close_edges.push(close_edges);
         }
+        gen
     }
 
-    pub fn generate(
-        &self,
-        cell_polys: &[Poly],
-    ) -> Vec<[f64; NVERTS]> {
-        cell_polys
-            .iter()
-            .map(|poly| {
-                let mut x_bdrys = [0.0f64; NVERTS];
-                poly.verts.iter().zip(x_bdrys.iter_mut()).for_each(
-                    |(v, x)| {
-                        let in_bdry = if self.skip_bb_check {
-                            is_point_in_poly(v, None, &self.shape)
-                        } else {
-                            is_point_in_poly(
-                                v,
-                                Some(&self.bbox),
-                                &self.shape,
-                            )
-                        };
-                        if in_bdry {
-                            *x = self.mag;
-                        }
-                    },
+    /// Calculate the boundary interaction factors of a cell with
+    /// polygon `poly`, along with the wall edges close to it.
+    fn calc(&self, poly: &Poly<N>) -> ([f64; N], Vec<LineSeg2D>) {
+        let bb = poly.bbox.expand_by(self.search_dist);
+        let mut x_bdrys = [0.0f64; N];
+        let mut close_edges = vec![];
+        for wall in
+            self.walls.iter().filter(|wall| wall.bbox.intersects(&bb))
+        {
+            let edges = wall
+                .edges
+                .iter()
+                .filter(|edge| edge.intersects_bbox(&bb))
+                .copied()
+                .collect::<Vec<LineSeg2D>>();
+            for (v, x) in poly.verts.iter().zip(x_bdrys.iter_mut()) {
+                let touching = edges.iter().any(|edge| {
+                    edge.dist_sq_to(v) <= self.touch_dist_sq
+                }) || is_point_in_poly(
+                    v,
+                    Some(&wall.bbox),
+                    &wall.verts,
                 );
-                x_bdrys
-            })
-            .collect()
+                if touching {
+                    *x = self.mag;
+                }
+            }
+            close_edges.extend(edges);
+        }
+        (x_bdrys, close_edges)
+    }
+
+    /// Recalculate the boundary interactions of cell `ci`, after it
+    /// has moved.
+    pub fn update(&mut self, ci: usize, cell_polys: &[Poly<N>]) {
+        let (x_bdrys, close_edges) = self.calc(&cell_polys[ci]);
+        self.x_bdrys[ci] = x_bdrys;
+        self.close_edges[ci] = close_edges;
+    }
+
+    /// Add a new cell, which is the last of `cell_polys`.
+    pub fn add_cell(&mut self, cell_polys: &[Poly<N>]) {
+        let (x_bdrys, close_edges) =
+            self.calc(cell_polys.last().unwrap());
+        self.x_bdrys.push(x_bdrys);
+        self.close_edges.push(close_edges);
+    }
+
+    /// Remove cell `ci`.
+    pub fn remove_cell(&mut self, ci: usize) {
+        self.x_bdrys.remove(ci);
+        self.close_edges.remove(ci);
+    }
+
+    /// Boundary signals of cell `ci`.
+    pub fn generate(&self, ci: usize) -> [f64; N] {
+        self.x_bdrys[ci]
+    }
+
+    /// Wall edges close to cell `ci`.
+    pub fn close_edges(&self, ci: usize) -> &[LineSeg2D] {
+        &self.close_edges[ci]
     }
 }
@@ -0,0 +1,222 @@
+use crate::math::geometry::{is_point_in_poly, Poly};
+use crate::math::v2d::V2d;
+use crate::parameters::{ChemFieldParams, ChemSource};
+use serde::{Deserialize, Serialize};
+
+/// Largest fraction of the explicit scheme's stability limit used as
+/// the step size when solving for the field.
+const STABILITY_FRAC: f64 = 0.9;
+
+/// Concentration of chemoattractant on a regular grid of squares.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ChemField {
+    /// Bottom left corner of the grid.
+    pub origin: V2d,
+    /// Side length of a grid square.
+    pub spacing: f64,
+    /// Number of grid squares along the x and y axes.
+    pub shape: [usize; 2],
+    /// Concentration in each grid square. The square in column `i`
+    /// and row `j` is at `j * shape[0] + i`.
+    pub concs: Vec<f64>,
+}
+
+impl ChemField {
+    /// A grid with zero concentration everywhere.
+    pub fn zeros(
+        origin: V2d,
+        spacing: f64,
+        shape: [usize; 2],
+    ) -> ChemField {
+        ChemField {
+            origin,
+            spacing,
+            shape,
+            concs: vec![0.0; shape[0] * shape[1]],
+        }
+    }
+
+    /// Center of the square in column `i` and row `j`.
+    fn center(&self, i: usize, j: usize) -> V2d {
+        V2d {
+            x: self.origin.x + (i as f64 + 0.5) * self.spacing,
+            y: self.origin.y + (j as f64 + 0.5) * self.spacing,
+        }
+    }
+
+    /// Index of the square containing `p`, if any.
+    fn square_of(&self, p: &V2d) -> Option<usize> {
+        let i = ((p.x - self.origin.x) / self.spacing).floor();
+        let j = ((p.y - self.origin.y) / self.spacing).floor();
+        if i < 0.0
+            || j < 0.0
+            || i >= self.shape[0] as f64
+            || j >= self.shape[1] as f64
+        {
+            None
+        } else {
+            Some(j as usize * self.shape[0] + i as usize)
+        }
+    }
+
+    /// Concentration at `p`, bilinearly interpolated between the
+    /// centers of the squares. Points outside of the grid are
+    /// clamped to it.
+    pub fn sample(&self, p: &V2d) -> f64 {
+        let [nx, ny] = self.shape;
+        let fx = ((p.x - self.origin.x) / self.spacing - 0.5)
+            .clamp(0.0, (nx - 1) as f64);
+        let fy = ((p.y - self.origin.y) / self.spacing - 0.5)
+            .clamp(0.0, (ny - 1) as f64);
+        let (i0, j0) = (fx.floor() as usize, fy.floor() as usize);
+        let (i1, j1) = ((i0 + 1).min(nx - 1), (j0 + 1).min(ny - 1));
+        let (sx, sy) = (fx - i0 as f64, fy - j0 as f64);
+        let c = |i: usize, j: usize| self.concs[j * nx + i];
+        (1.0 - sy) * ((1.0 - sx) * c(i0, j0) + sx * c(i1, j0))
+            + sy * ((1.0 - sx) * c(i0, j1) + sx * c(i1, j1))
+    }
+
+    /// Advance the field from time `t` to `t + dt`, solving
+    /// `dc/dt = D * lap(c) - (decay + consumption * covered) * c + s`
+    /// with an explicit finite difference scheme, where `covered` is
+    /// `1` in squares whose centers are covered by one of
+    /// `cell_polys`, and `s` is the total rate of the sources in a
+    /// square, scaled by their schedules at the start of each step.
+    /// No chemoattractant flows across the edges of the grid. `dt` is
+    /// split into as many steps as are needed for the scheme to be
+    /// stable.
+    pub fn step<const N: usize>(
+        &mut self,
+        t: f64,
+        dt: f64,
+        params: &ChemFieldParams,
+        cell_polys: &[Poly<N>],
+    ) {
+        let [nx, ny] = self.shape;
+        let ChemFieldParams {
+            diffusion,
+            decay,
+            consumption,
+            ..
+        } = *params;
+        let mut removal = vec![decay; nx * ny];
+        if consumption > 0.0 {
+            for j in 0..ny {
+                for i in 0..nx {
+                    let c = self.center(i, j);
+                    if cell_polys.iter().any(|poly| {
+                        is_point_in_poly(
+                            &c,
+                            Some(&poly.bbox),
+                            &poly.verts,
+                        )
+                    }) {
+                        removal[j * nx + i] += consumption;
+                    }
+                }
+            }
+        }
+        let sources = params
+            .sources
+            .iter()
+            .filter_map(|s| self.square_of(&s.pos).map(|k| (k, s)))
+            .collect::<Vec<(usize, &ChemSource)>>();
+
+        let d = diffusion / (self.spacing * self.spacing);
+        let max_removal = removal.iter().cloned().fold(0.0, f64::max);
+        // Stability limit of forward Euler for the diffusion and
+        // removal terms.
+        let h_max = STABILITY_FRAC / (4.0 * d + max_removal);
+        let num_steps = if h_max.is_finite() {
+            (dt / h_max).ceil().max(1.0) as usize
+        } else {
+            1
+        };
+        let h = dt / num_steps as f64;
+        let mut next = self.concs.clone();
+        let mut production = vec![0.0; nx * ny];
+        for n in 0..num_steps {
+            let t_n = t + n as f64 * h;
+            production.iter_mut().for_each(|p| *p = 0.0);
+            for (k, s) in sources.iter() {
+                production[*k] += s.rate * s.schedule.strength(t_n);
+            }
+            for j in 0..ny {
+                for i in 0..nx {
+                    let k = j * nx + i;
+                    let c = self.concs[k];
+                    // Reflect at the edges of the grid, so that
+                    // there is no flux across them.
+                    let left =
+                        if i > 0 { self.concs[k - 1] } else { c };
+                    let right = if i + 1 < nx {
+                        self.concs[k + 1]
+                    } else {
+                        c
+                    };
+                    let down =
+                        if j > 0 { self.concs[k - nx] } else { c };
+                    let up = if j + 1 < ny {
+                        self.concs[k + nx]
+                    } else {
+                        c
+                    };
+                    let lap = left + right + down + up - 4.0 * c;
+                    next[k] = c + h
+                        * (d * lap - removal[k] * c + production[k]);
+                }
+            }
+            std::mem::swap(&mut self.concs, &mut next);
+        }
+    }
+}
+
+/// Generates chemoattractant signals sampled from a `ChemField`,
+/// which is advanced with the simulation (see
+/// `InteractionGenerator::advance_chem`).
+#[derive(Clone)]
+pub struct ChemFieldGenerator {
+    params: ChemFieldParams,
+    field: ChemField,
+}
+
+impl ChemFieldGenerator {
+    pub fn new(params: ChemFieldParams) -> ChemFieldGenerator {
+        let field = ChemField::zeros(
+            params.origin,
+            params.spacing,
+            params.shape,
+        );
+        ChemFieldGenerator { params, field }
+    }
+
+    pub fn step<const N: usize>(
+        &mut self,
+        t: f64,
+        dt: f64,
+        cell_polys: &[Poly<N>],
+    ) {
+        self.field.step(t, dt, &self.params, cell_polys);
+    }
+
+    pub fn field(&self) -> &ChemField {
+        &self.field
+    }
+
+    /// Replace the field, for example with one saved in a snapshot.
+    pub fn set_field(&mut self, field: ChemField) {
+        self.field = field;
+    }
+
+    /// Chemoattractant signals at the vertices of `poly`.
+    pub fn generate<const N: usize>(
+        &self,
+        poly: &Poly<N>,
+    ) -> [f64; N] {
+        let mut x_chemoas = [0.0f64; N];
+        poly.verts.iter().zip(x_chemoas.iter_mut()).for_each(
+            |(v, x)| *x = self.params.mag * self.field.sample(v),
+        );
+        x_chemoas
+    }
+}
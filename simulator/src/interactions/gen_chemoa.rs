@@ -1,52 +1,55 @@
 use crate::math::geometry::Poly;
-use crate::math::v2d::V2d;
-use crate::parameters::ChemAttrParams;
-use crate::NVERTS;
+use crate::parameters::{ChemAttrParams, ChemAttrSource};
 use serde::{Deserialize, Serialize};
 
+/// Generates chemoattractant signals from analytic profiles around
+/// sources, whose strengths may change over time (see
+/// `InteractionGenerator::advance_chem`).
 #[derive(Clone, Deserialize, Serialize)]
 pub struct ChemAttrGenerator {
-    center_mag: f64,
-    slope: f64,
-    center: V2d,
+    sources: Vec<ChemAttrSource>,
+    /// Time at which source strengths are evaluated.
+    tpoint: f64,
 }
 
 impl ChemAttrGenerator {
     pub fn new(params: ChemAttrParams) -> ChemAttrGenerator {
-        let ChemAttrParams {
-            center,
-            center_mag,
-            slope,
-        } = params;
         ChemAttrGenerator {
-            center_mag,
-            slope,
-            center,
+            sources: params.sources,
+            tpoint: 0.0,
         }
     }
 
-    pub fn update(&self) {}
+    /// Set the time at which source strengths are evaluated. Returns
+    /// whether any source strengths may have changed.
+    pub fn set_tpoint(&mut self, tpoint: f64) -> bool {
+        self.tpoint = tpoint;
+        self.sources.iter().any(|s| !s.schedule.is_constant())
+    }
 
-    pub fn generate(
+    /// Chemoattractant signals at the vertices of `poly`.
+    pub fn generate<const N: usize>(
         &self,
-        cell_polys: &[Poly],
-    ) -> Vec<[f64; NVERTS]> {
-        cell_polys
+        poly: &Poly<N>,
+    ) -> [f64; N] {
+        let mags = self
+            .sources
             .iter()
-            .map(|poly| {
-                let mut x_chemoas = [0.0f64; NVERTS];
-                poly.verts.iter().zip(x_chemoas.iter_mut()).for_each(
-                    |(&v, x)| {
-                        let r = self.center_mag
-                            * self.slope
-                            * (v - self.center).mag();
-                        if r > 0.0 {
-                            *x = r
-                        }
-                    },
-                );
-                x_chemoas
-            })
-            .collect()
+            .map(|s| s.mag * s.schedule.strength(self.tpoint))
+            .collect::<Vec<f64>>();
+        let mut x_chemoas = [0.0f64; N];
+        poly.verts.iter().zip(x_chemoas.iter_mut()).for_each(
+            |(&v, x)| {
+                *x = self
+                    .sources
+                    .iter()
+                    .zip(mags.iter())
+                    .map(|(s, mag)| {
+                        mag * s.profile.at((v - s.center).mag())
+                    })
+                    .sum();
+            },
+        );
+        x_chemoas
     }
 }
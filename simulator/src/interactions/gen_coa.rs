@@ -1,14 +1,15 @@
-use crate::interactions::dat_sym2d::SymCcDat;
+use crate::interactions::bbox_grid::BBoxGrid;
 use crate::interactions::dat_sym4d::SymCcVvDat;
-use crate::interactions::gen_contact_matrix;
 use crate::interactions::gen_phys::PhysicalContactGenerator;
-use crate::math::geometry::{BBox, LineSeg2D, Poly};
-use crate::parameters::CoaParams;
+use crate::math::geometry::{
+    bbox_image_near, poly_image_near, BBox, LineSeg2D,
+    PeriodicDomain, Poly,
+};
+use crate::parameters::{CoaParams, GroupPairMags};
 use crate::utils::circ_ix_minus;
-use crate::NVERTS;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Deserialize, Serialize)]
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq)]
 pub struct VertexPairInfo {
     dist: f64,
     num_intersects: f64,
@@ -23,11 +24,21 @@ impl VertexPairInfo {
     }
 }
 
+/// Generates COA signals. Cells are within COA range of each other
+/// when their contact bounding boxes intersect. Vertex pair
+/// information is only stored for cells within range, which are
+/// found using a grid of the contact bounding boxes. If there is a
+/// periodic domain, lines of sight run between a cell and the
+/// nearest image of another, and may be blocked by the images of
+/// other cells nearest to them.
 #[derive(Clone, Deserialize, Serialize)]
-pub struct CoaGenerator {
-    dat: SymCcVvDat<VertexPairInfo>,
-    contact_bbs: Vec<BBox>,
-    contact_matrix: SymCcDat<bool>,
+pub struct CoaGenerator<const N: usize> {
+    dat: SymCcVvDat<VertexPairInfo, N>,
+    contact_grid: BBoxGrid,
+    /// Grid of the cells' bounding boxes, used to find cells which
+    /// may block lines of sight.
+    poly_grid: BBoxGrid,
+    domain: Option<PeriodicDomain>,
     params: CoaParams,
 }
 
@@ -45,9 +56,17 @@ pub struct CoaGenerator {
 /// does not contain the endpoints of `lseg`. Note that case `1` is unlikely, but
 /// possible especially in the initial if cells have been initialized in a
 /// regular lattice.
-pub fn check_other_poly_intersect(
+/// Distance from the contact bounding box of a cell within which the
+/// line of sight to any cell in COA range of it lies, if COA is at
+/// half its maximum at `halfmax_dist`, and no cell is wider or taller
+/// than `cell_extent`.
+pub fn coa_reach(halfmax_dist: f64, cell_extent: f64) -> f64 {
+    4.0 * halfmax_dist + cell_extent
+}
+
+pub fn check_other_poly_intersect<const N: usize>(
     lseg: &LineSeg2D,
-    poly: &Poly,
+    poly: &Poly<N>,
 ) -> bool {
     if lseg.intersects_bbox(&poly.bbox) {
         for edge in poly.edges.iter() {
@@ -66,21 +85,21 @@ pub fn check_other_poly_intersect(
 /// It could be that that `lseg` one of `A` or `B`, the "root" polygons of `lseg`.
 /// This function checks if this has occurred, but ignores intersections
 /// involving the source/destination vertices of `lseg`.
-pub fn check_root_poly_intersect(
+pub fn check_root_poly_intersect<const N: usize>(
     lseg: &LineSeg2D,
-    poly_a: &Poly,
-    poly_b: &Poly,
+    poly_a: &Poly<N>,
+    poly_b: &Poly<N>,
     vi_a: usize,
     vi_b: usize,
 ) -> bool {
-    let ui_a = circ_ix_minus(vi_a, NVERTS);
+    let ui_a = circ_ix_minus(vi_a, N);
     for (ei, edge) in poly_a.edges.iter().enumerate() {
         if ei != vi_a && ei != ui_a && edge.check_intersection(lseg) {
             return true;
         }
     }
 
-    let ui_b = circ_ix_minus(vi_b, NVERTS);
+    let ui_b = circ_ix_minus(vi_b, N);
     for (ei, edge) in poly_b.edges.iter().enumerate() {
         if ei != vi_b && ei != ui_b && edge.check_intersection(lseg) {
             return true;
@@ -90,205 +109,464 @@ pub fn check_root_poly_intersect(
     false
 }
 
-/// Calculate clearance and distance.
-pub fn calc_pair_info(
+/// Polygons of the cells which may block lines of sight between cell
+/// `ci` and `poly_b`, the image of cell `oci` nearest to it: those
+/// whose bounding boxes are near the box bounding both, found using
+/// `grid`, which holds the bounding boxes of `cell_polys`. If there
+/// is a periodic domain, the images of the cells nearest to the pair
+/// are given.
+fn find_blockers<const N: usize>(
     ci: usize,
-    vi: usize,
     oci: usize,
+    poly_b: &Poly<N>,
+    cell_polys: &[Poly<N>],
+    grid: &BBoxGrid,
+    domain: Option<&PeriodicDomain>,
+) -> Vec<Poly<N>> {
+    let region = cell_polys[ci].bbox.union(&poly_b.bbox);
+    grid.query(&region)
+        .into_iter()
+        .filter(|&pi| pi != ci && pi != oci)
+        .map(|pi| {
+            poly_image_near(domain, &cell_polys[pi], &region.center())
+        })
+        .collect()
+}
+
+/// Calculate clearance and distance along `lseg`, which runs from
+/// vertex `vi` of `poly_a` to vertex `ovi` of `poly_b`. `blockers`
+/// are the other polygons which may block the line of sight (see
+/// `find_blockers`).
+pub fn calc_pair_info<const N: usize>(
+    vi: usize,
     ovi: usize,
     lseg: LineSeg2D,
-    cell_polys: &[Poly],
+    poly_a: &Poly<N>,
+    poly_b: &Poly<N>,
+    blockers: &[Poly<N>],
 ) -> VertexPairInfo {
-    if check_root_poly_intersect(
-        &lseg,
-        &cell_polys[ci],
-        &cell_polys[oci],
-        vi,
-        ovi,
-    ) {
+    if check_root_poly_intersect(&lseg, poly_a, poly_b, vi, ovi) {
         return VertexPairInfo {
             dist: lseg.len,
             num_intersects: f64::INFINITY,
         };
     }
-    let num_intersects = cell_polys
+    let num_intersects = blockers
         .iter()
-        .enumerate()
-        .map(|(pi, poly)| {
-            if pi != ci
-                && pi != oci
-                && check_other_poly_intersect(&lseg, poly)
-            {
-                1.0
-            } else {
-                0.0
-            }
-        })
-        .sum::<f64>();
+        .filter(|poly| check_other_poly_intersect(&lseg, poly))
+        .count() as f64;
     VertexPairInfo {
         dist: lseg.len,
         num_intersects,
     }
 }
 
-impl CoaGenerator {
+/// Calculate the `VertexPairInfo` for the pair `(ci, vi)`, `(oci,
+/// ovi)`, where `ci < oci` are within COA range of each other, and
+/// have polygons `poly_a` and `poly_b` (the image of `oci` nearest to
+/// `ci`). If either vertex is too close to another cell, the pair
+/// does not interact.
+fn calc_vertex_pair_info<const N: usize>(
+    ci: usize,
+    vi: usize,
+    oci: usize,
+    ovi: usize,
+    poly_a: &Poly<N>,
+    poly_b: &Poly<N>,
+    blockers: &[Poly<N>],
+    params: &CoaParams,
+    phys_contact_generator: &PhysicalContactGenerator<N>,
+) -> VertexPairInfo {
+    if !(phys_contact_generator.min_dist_to(ci, vi)
+        < params.too_close_dist_sq
+        || phys_contact_generator.min_dist_to(oci, ovi)
+            < params.too_close_dist_sq)
+    {
+        let lseg =
+            LineSeg2D::new(&poly_a.verts[vi], &poly_b.verts[ovi]);
+        calc_pair_info(vi, ovi, lseg, poly_a, poly_b, blockers)
+    } else {
+        VertexPairInfo::infinity()
+    }
+}
+
+impl<const N: usize> CoaGenerator<N> {
     /// Calculates a matrix storing whether two vertices have clear line of sight if in contact range.
     pub fn new(
-        cell_polys: &[Poly],
+        cell_polys: &[Poly<N>],
         params: CoaParams,
-        phys_contact_generator: &PhysicalContactGenerator,
-    ) -> CoaGenerator {
+        phys_contact_generator: &PhysicalContactGenerator<N>,
+        domain: Option<PeriodicDomain>,
+    ) -> CoaGenerator<N> {
         let num_cells = cell_polys.len();
-        let contact_bbs = cell_polys
-            .iter()
-            .map(|cp| cp.bbox.expand_by(2.0 * params.halfmax_dist))
-            .collect::<Vec<BBox>>();
-        let contact_matrix = gen_contact_matrix(&contact_bbs);
-        let mut dat =
-            SymCcVvDat::empty(num_cells, VertexPairInfo::infinity());
-
-        for (ci, poly) in cell_polys.iter().enumerate() {
-            for (vi, v) in poly.verts.iter().enumerate() {
-                for (ocj, opoly) in
-                    cell_polys[(ci + 1)..].iter().enumerate()
-                {
-                    let oci = (ci + 1) + ocj;
-                    for (ovi, ov) in opoly.verts.iter().enumerate() {
-                        if !(phys_contact_generator
-                            .min_dist_to(ci, vi)
-                            < params.too_close_dist_sq
-                            || phys_contact_generator
-                                .min_dist_to(oci, ovi)
-                                < params.too_close_dist_sq)
-                            && contact_matrix.get(ci, oci)
-                        {
-                            let lseg = LineSeg2D::new(v, ov);
-                            dat.set(
-                                ci,
-                                vi,
-                                oci,
-                                ovi,
-                                calc_pair_info(
-                                    ci, vi, oci, ovi, lseg,
-                                    cell_polys,
-                                ),
-                            );
-                        } else {
-                            dat.set(
-                                ci,
-                                vi,
-                                oci,
-                                ovi,
-                                VertexPairInfo::infinity(),
-                            );
-                        }
-                    }
+        let contact_grid = BBoxGrid::new(
+            cell_polys
+                .iter()
+                .map(|cp| {
+                    cp.bbox.expand_by(2.0 * params.halfmax_dist)
+                })
+                .collect::<Vec<BBox>>(),
+            domain,
+        );
+        let mut gen = CoaGenerator {
+            dat: SymCcVvDat::empty(
+                num_cells,
+                VertexPairInfo::infinity(),
+            ),
+            contact_grid,
+            poly_grid: BBoxGrid::new(
+                cell_polys.iter().map(|cp| cp.bbox).collect(),
+                domain,
+            ),
+            domain,
+            params,
+        };
+        for ci in 0..num_cells {
+            for oci in gen.find_contacts(ci) {
+                if ci < oci {
+                    gen.calc_pair(
+                        ci,
+                        oci,
+                        cell_polys,
+                        phys_contact_generator,
+                    );
                 }
             }
         }
-        CoaGenerator {
-            dat,
-            contact_bbs,
-            contact_matrix,
-            params,
+        gen
+    }
+
+    /// Indices, in increasing order, of the cells within COA range of
+    /// cell `ci`.
+    fn find_contacts(&self, ci: usize) -> Vec<usize> {
+        let bb = self.contact_grid.bbox(ci);
+        self.contact_grid
+            .query(bb)
+            .into_iter()
+            .filter(|&oci| {
+                oci != ci && self.contact_grid.intersects(oci, bb)
+            })
+            .collect()
+    }
+
+    /// Calculate the information of all vertex pairs between cells
+    /// `ci` and `oci`.
+    fn calc_pair(
+        &mut self,
+        ci: usize,
+        oci: usize,
+        cell_polys: &[Poly<N>],
+        phys_contact_generator: &PhysicalContactGenerator<N>,
+    ) {
+        let poly_a = &cell_polys[ci];
+        let poly_b = &poly_image_near(
+            self.domain.as_ref(),
+            &cell_polys[oci],
+            &poly_a.bbox.center(),
+        );
+        let blockers = find_blockers(
+            ci,
+            oci,
+            poly_b,
+            cell_polys,
+            &self.poly_grid,
+            self.domain.as_ref(),
+        );
+        for vi in 0..N {
+            for ovi in 0..N {
+                let info = calc_vertex_pair_info(
+                    ci,
+                    vi,
+                    oci,
+                    ovi,
+                    poly_a,
+                    poly_b,
+                    &blockers,
+                    &self.params,
+                    phys_contact_generator,
+                );
+                self.dat.set(ci, vi, oci, ovi, info);
+            }
         }
     }
 
+    /// Update COA information after the polygon of cell `ci` has
+    /// changed. `old_bbox` is the bounding box of the cell's previous
+    /// polygon, and `min_dist_changed` lists cells whose minimum
+    /// vertex distances were changed by the update.
+    ///
+    /// Besides pairs involving `ci` (or cells in `min_dist_changed`),
+    /// the line of sight of any other pair passing near the old or
+    /// new position of `ci` is also recalculated, so that the
+    /// result is the same as if the generator were created anew.
+    /// Returns the indices of the cells whose COA signals may have
+    /// changed as a result.
     pub fn update(
         &mut self,
         ci: usize,
-        cell_polys: &[Poly],
-        phys_contact_generator: &PhysicalContactGenerator,
-    ) {
-        let poly = cell_polys[ci];
-        let bb = poly.bbox.expand_by(self.params.halfmax_dist);
-        self.contact_bbs[ci] = bb;
+        old_bbox: &BBox,
+        cell_polys: &[Poly<N>],
+        phys_contact_generator: &PhysicalContactGenerator<N>,
+        min_dist_changed: &[usize],
+    ) -> Vec<usize> {
+        let new_bbox = cell_polys[ci].bbox;
+        self.poly_grid.update(ci, new_bbox);
+        self.contact_grid.update(
+            ci,
+            new_bbox.expand_by(2.0 * self.params.halfmax_dist),
+        );
         // Update contacts.
-        for (oci, obb) in self.contact_bbs.iter().enumerate() {
-            if oci != ci {
-                self.contact_matrix.set(ci, oci, obb.intersects(&bb))
+        let contacts = self.find_contacts(ci);
+        let old_contacts =
+            self.dat.partners(ci).collect::<Vec<usize>>();
+        let mut changed = vec![ci];
+        for oci in old_contacts {
+            if !contacts.contains(&oci) {
+                self.dat.unpair(ci, oci);
+                changed.push(oci);
+            }
+        }
+        for oci in contacts {
+            self.dat.pair(ci, oci);
+        }
+        let mut involved = min_dist_changed.to_vec();
+        involved.push(ci);
+        changed.extend(self.recalc_pairs(
+            &[*old_bbox, new_bbox],
+            cell_polys,
+            phys_contact_generator,
+            &involved,
+        ));
+        changed
+    }
+
+    /// Add COA information for a new cell, whose polygon is the last
+    /// one in `cell_polys`. `min_dist_changed` lists cells whose
+    /// minimum vertex distances were changed by adding the cell.
+    pub fn add_cell(
+        &mut self,
+        cell_polys: &[Poly<N>],
+        phys_contact_generator: &PhysicalContactGenerator<N>,
+        min_dist_changed: &[usize],
+    ) {
+        let ci = self.dat.num_cells();
+        let bbox = cell_polys[ci].bbox;
+        self.dat.add_cell();
+        self.contact_grid.push(bbox);
+        self.poly_grid.push(bbox);
+        // The new cell has no previous position, so its current
+        // bounding box stands in for the old one.
+        self.update(
+            ci,
+            &bbox,
+            cell_polys,
+            phys_contact_generator,
+            min_dist_changed,
+        );
+    }
+
+    /// Remove COA information involving cell `ci`, whose polygon had
+    /// bounding box `old_bbox`. `cell_polys` no longer contains the
+    /// polygon of the removed cell, and `min_dist_changed` lists
+    /// (post-removal) indices of cells whose minimum vertex distances
+    /// were changed by the removal.
+    pub fn remove_cell(
+        &mut self,
+        ci: usize,
+        old_bbox: &BBox,
+        cell_polys: &[Poly<N>],
+        phys_contact_generator: &PhysicalContactGenerator<N>,
+        min_dist_changed: &[usize],
+    ) {
+        self.dat.remove_cell(ci);
+        self.contact_grid.remove(ci);
+        self.poly_grid.remove(ci);
+        // Pairs whose line of sight was blocked by the removed cell
+        // may now be clear.
+        self.recalc_pairs(
+            &[*old_bbox],
+            cell_polys,
+            phys_contact_generator,
+            min_dist_changed,
+        );
+    }
+
+    /// Recalculate the information of all vertex pairs where one of
+    /// the cells is `involved`, along with pairs whose line of sight
+    /// passes through any of `bboxes`. Returns the indices of the
+    /// cells whose vertex pair information changed.
+    fn recalc_pairs(
+        &mut self,
+        bboxes: &[BBox],
+        cell_polys: &[Poly<N>],
+        phys_contact_generator: &PhysicalContactGenerator<N>,
+        involved: &[usize],
+    ) -> Vec<usize> {
+        // The line of sight between two cells in range of each other
+        // lies within distance `reach` of the contact bounding box of
+        // each of them, so only pairs of cells whose boxes are near
+        // `bboxes` need to be considered.
+        let reach = coa_reach(
+            self.params.halfmax_dist,
+            cell_polys
+                .iter()
+                .map(|cp| {
+                    (cp.bbox.xmax - cp.bbox.xmin)
+                        .max(cp.bbox.ymax - cp.bbox.ymin)
+                })
+                .fold(0.0, f64::max),
+        );
+        let mut near = involved.to_vec();
+        for bb in bboxes {
+            near.extend(
+                self.contact_grid.query(&bb.expand_by(reach)),
+            );
+        }
+        let mut pairs = vec![];
+        for ai in near {
+            for bi in self.dat.partners(ai) {
+                pairs.push(if ai < bi { (ai, bi) } else { (bi, ai) });
             }
         }
-        for (vi, v) in poly.verts.iter().enumerate() {
-            for (ocj, opoly) in
-                cell_polys[(ci + 1)..].iter().enumerate()
+        pairs.sort_unstable();
+        pairs.dedup();
+        let CoaGenerator {
+            dat,
+            poly_grid,
+            domain,
+            params,
+            ..
+        } = self;
+        let domain = domain.as_ref();
+        let mut changed = vec![];
+        for (ai, bi) in pairs {
+            let involved =
+                involved.contains(&ai) || involved.contains(&bi);
+            let poly_a = &cell_polys[ai];
+            let poly_b = &poly_image_near(
+                domain,
+                &cell_polys[bi],
+                &poly_a.bbox.center(),
+            );
+            // Lines of sight between the cells lie within the box
+            // bounding both of them.
+            let region = poly_a.bbox.union(&poly_b.bbox);
+            let bboxes = bboxes
+                .iter()
+                .map(|bb| {
+                    bbox_image_near(domain, bb, &region.center())
+                })
+                .collect::<Vec<BBox>>();
+            if !(involved
+                || bboxes.iter().any(|bb| region.intersects(bb)))
             {
-                let oci = (ci + 1) + ocj;
-                for (ovi, ov) in opoly.verts.iter().enumerate() {
-                    if !(phys_contact_generator.min_dist_to(ci, vi)
-                        < self.params.too_close_dist_sq
-                        || phys_contact_generator
-                            .min_dist_to(oci, ovi)
-                            < self.params.too_close_dist_sq)
-                        && self.contact_matrix.get(ci, oci)
-                    {
-                        let lseg = LineSeg2D::new(v, ov);
-                        self.dat.set(
-                            ci,
-                            vi,
-                            oci,
-                            ovi,
-                            calc_pair_info(
-                                ci, vi, oci, ovi, lseg, cell_polys,
-                            ),
-                        );
-                    } else {
-                        self.dat.set(
-                            ci,
-                            vi,
-                            oci,
-                            ovi,
-                            VertexPairInfo::infinity(),
+                continue;
+            }
+            let chunk = match dat.chunk_mut(ai, bi) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            let mut blockers = None;
+            let mut pair_changed = false;
+            for avi in 0..N {
+                for bvi in 0..N {
+                    let info = &mut chunk[avi * N + bvi];
+                    let recalc = involved || {
+                        // Only pairs with clear enough line of
+                        // sight can be affected by a cell moving.
+                        info.num_intersects < f64::INFINITY && {
+                            let lseg = LineSeg2D::new(
+                                &poly_a.verts[avi],
+                                &poly_b.verts[bvi],
+                            );
+                            bboxes
+                                .iter()
+                                .any(|bb| lseg.intersects_bbox(bb))
+                        }
+                    };
+                    if recalc {
+                        let blockers =
+                            blockers.get_or_insert_with(|| {
+                                find_blockers(
+                                    ai, bi, poly_b, cell_polys,
+                                    poly_grid, domain,
+                                )
+                            });
+                        let new_info = calc_vertex_pair_info(
+                            ai,
+                            avi,
+                            bi,
+                            bvi,
+                            poly_a,
+                            poly_b,
+                            blockers,
+                            params,
+                            phys_contact_generator,
                         );
+                        pair_changed |= new_info != *info;
+                        *info = new_info;
                     }
                 }
             }
+            if pair_changed {
+                changed.push(ai);
+                changed.push(bi);
+            }
         }
+        changed
     }
 
-    pub fn generate(&self) -> Vec<[f64; NVERTS]> {
-        let num_cells = self.contact_matrix.num_cells;
-        let mut all_x_coas = vec![[0.0f64; NVERTS]; num_cells];
+    /// COA signals of cell `ci`. A magnitude given in `group_mags`
+    /// for the groups of `ci` and another cell, where `cell_groups`
+    /// holds the group index of each cell, takes precedence over
+    /// `params.vertex_mag` for the signal from that cell.
+    pub fn generate(
+        &self,
+        ci: usize,
+        cell_groups: &[usize],
+        group_mags: Option<&GroupPairMags>,
+    ) -> [f64; N] {
+        let mut x_coas = [0.0f64; N];
         let CoaParams {
             los_penalty,
             vertex_mag,
             distrib_exp,
             ..
         } = self.params;
-        for (ci, x_coas) in all_x_coas.iter_mut().enumerate() {
+        for oci in self.dat.partners(ci) {
+            let (chunk, vi_stride, ovi_stride) = if ci < oci {
+                (self.dat.chunk(ci, oci), N, 1)
+            } else {
+                (self.dat.chunk(oci, ci), 1, N)
+            };
+            let chunk = match chunk {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            let vertex_mag = group_mags
+                .and_then(|mags| {
+                    mags.coa(cell_groups[ci], cell_groups[oci])
+                })
+                .unwrap_or(vertex_mag);
             for (vi, x_coa) in x_coas.iter_mut().enumerate() {
-                for oci in 0..num_cells {
-                    if oci != ci {
-                        for ovi in 0..NVERTS {
-                            let VertexPairInfo {
-                                dist,
-                                num_intersects,
-                            } = self.dat.get(ci, vi, oci, ovi);
-                            if num_intersects < f64::INFINITY {
-                                let los_factor = 1.0
-                                    / (num_intersects + 1.0)
-                                        .powf(los_penalty);
-                                let coa_signal = (distrib_exp * dist)
-                                    .exp()
-                                    * los_factor;
-                                let additional_signal =
-                                    vertex_mag * coa_signal;
-                                *x_coa += additional_signal;
-                            }
-                        }
+                for ovi in 0..N {
+                    let VertexPairInfo {
+                        dist,
+                        num_intersects,
+                    } = chunk[vi * vi_stride + ovi * ovi_stride];
+                    if num_intersects < f64::INFINITY {
+                        let los_factor = 1.0
+                            / (num_intersects + 1.0)
+                                .powf(los_penalty);
+                        let coa_signal =
+                            (distrib_exp * dist).exp() * los_factor;
+                        let additional_signal =
+                            vertex_mag * coa_signal;
+                        *x_coa += additional_signal;
                     }
                 }
             }
         }
-        // let mut max_coa = 0.0;
-        // for x_coas in all_x_coas.iter() {
-        //     for &x_coa in x_coas.iter() {
-        //         if x_coa > max_coa {
-        //             max_coa = x_coa;
-        //         }
-        //     }
-        // }
-        //println!("{}", max_coa);
-        all_x_coas
+        x_coas
     }
 }
@@ -1,14 +1,16 @@
+use crate::interactions::bbox_grid::BBoxGrid;
 use crate::interactions::dat_4d::CvCvDat;
-use crate::interactions::dat_sym2d::SymCcDat;
-use crate::interactions::{gen_contact_matrix, RelativeRgtpActivity};
-use crate::math::geometry::{BBox, Poly};
+use crate::interactions::dat_utils::ix_after_removal;
+use crate::interactions::RelativeRgtpActivity;
+use crate::math::geometry::{
+    poly_image_near, BBox, PeriodicDomain, Poly,
+};
 use crate::math::v2d::V2d;
 use crate::math::{
     capped_linear_fn, close_to_zero, in_unit_interval, InUnitInterval,
 };
-use crate::parameters::PhysicalContactParams;
+use crate::parameters::{GroupPairMags, PhysicalContactParams};
 use crate::utils::circ_ix_plus;
-use crate::NVERTS;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -129,8 +131,15 @@ impl fmt::Display for ClosePoint {
                 smooth_factor,
                 dist_sq,
             } => {
-                write!(f, "OnEdge(edge_point_param: {}, vector_to: {}, smooth_factor: {}, \
-                dist: {})", edge_point_param, vector_to.mag(), smooth_factor, dist_sq.sqrt())
+                write!(
+                    f,
+                    "OnEdge(edge_point_param: {}, vector_to: {}, smooth_factor: {}, \
+                dist: {})",
+                    edge_point_param,
+                    vector_to.mag(),
+                    smooth_factor,
+                    dist_sq.sqrt()
+                )
             }
             ClosePoint::None { dist_sq } => {
                 write!(f, "None(dist: {})", dist_sq.sqrt())
@@ -142,179 +151,206 @@ impl fmt::Display for ClosePoint {
 /// Generates CIL/CAL/adhesion related interaction information. These
 /// are the interactions that require cells to engage in
 /// physical contact.
-#[derive(
-    Clone, Deserialize, Serialize, PartialEq, Default, Debug,
-)]
-pub struct PhysicalContactGenerator {
-    dat: CvCvDat<ClosePoint>,
-    min_dist_matrix: Vec<[f64; NVERTS]>,
-    pub contact_bbs: Vec<BBox>,
-    pub contact_matrix: SymCcDat<bool>,
+///
+/// Cells are roughly in contact when their contact bounding boxes
+/// intersect. Close point data is only stored for cells in contact,
+/// which are found using a grid of the contact bounding boxes. If
+/// there is a periodic domain, a cell is in contact with the nearest
+/// image of another.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct PhysicalContactGenerator<const N: usize> {
+    /// Close points of vertices on a cell to the edges of another,
+    /// for cells in contact.
+    dat: CvCvDat<ClosePoint, N>,
+    min_dist_matrix: Vec<[f64; N]>,
+    contact_grid: BBoxGrid,
+    domain: Option<PeriodicDomain>,
     pub params: PhysicalContactParams,
 }
 
-pub struct PhysContactFactors {
-    pub adh: Vec<[V2d; NVERTS]>,
-    pub cil: Vec<[f64; NVERTS]>,
-    pub cal: Vec<[f64; NVERTS]>,
+pub struct PhysContactFactors<const N: usize> {
+    pub adh: [V2d; N],
+    pub cil: [f64; N],
+    pub cal: [f64; N],
 }
 
-impl PhysicalContactGenerator {
+impl<const N: usize> PhysicalContactGenerator<N> {
     /// Calculate distances between vertices of cells in contact.
     pub fn new(
-        cell_polys: &[Poly],
+        cell_polys: &[Poly<N>],
         params: PhysicalContactParams,
-    ) -> PhysicalContactGenerator {
+        domain: Option<PeriodicDomain>,
+    ) -> PhysicalContactGenerator<N> {
         let num_cells = cell_polys.len();
-        let mut dat =
-            CvCvDat::empty(num_cells, ClosePoint::default());
-        let contact_bbs = cell_polys
-            .iter()
-            .map(|cp| cp.bbox.expand_by(params.zero_at_sq))
-            .collect::<Vec<BBox>>();
-        let contact_matrix = gen_contact_matrix(&contact_bbs);
-        for (ci, poly) in cell_polys.iter().enumerate() {
-            for (oci, other) in cell_polys.iter().enumerate() {
-                if ci != oci {
-                    for (vi, v) in poly.verts.iter().enumerate() {
-                        for (ovi, ov) in
-                            other.verts.iter().enumerate()
-                        {
-                            let ow = &other.verts
-                                [circ_ix_plus(ovi, NVERTS)];
-                            if contact_matrix.get(ci, oci) {
-                                dat.set(
-                                    ci,
-                                    vi,
-                                    oci,
-                                    ovi,
-                                    ClosePoint::calc(
-                                        params.crl_one_at,
-                                        params.zero_at,
-                                        params.zero_at_sq,
-                                        *v,
-                                        *ov,
-                                        *ow,
-                                    ),
-                                );
-                            } else {
-                                dat.set(
-                                    ci,
-                                    vi,
-                                    oci,
-                                    ovi,
-                                    ClosePoint::default(),
-                                )
-                            }
-                        }
-                    }
-                }
+        let contact_grid = BBoxGrid::new(
+            cell_polys
+                .iter()
+                .map(|cp| cp.bbox.expand_by(params.zero_at_sq))
+                .collect::<Vec<BBox>>(),
+            domain,
+        );
+        let mut gen = PhysicalContactGenerator {
+            dat: CvCvDat::empty(num_cells, ClosePoint::default()),
+            min_dist_matrix: vec![[f64::INFINITY; N]; num_cells],
+            contact_grid,
+            domain,
+            params,
+        };
+        for ci in 0..num_cells {
+            for oci in gen.find_contacts(ci) {
+                gen.calc_close_points(ci, oci, cell_polys);
             }
         }
-        let mut min_dist_matrix =
-            vec![[f64::INFINITY; NVERTS]; num_cells];
         for ci in 0..num_cells {
-            min_dist_matrix[ci] =
-                PhysicalContactGenerator::eval_min_dist(
-                    ci,
-                    &contact_matrix,
-                    &dat,
-                );
+            gen.min_dist_matrix[ci] = gen.eval_min_dist(ci);
         }
-        PhysicalContactGenerator {
-            dat,
-            min_dist_matrix,
-            contact_bbs,
-            contact_matrix,
-            params,
+        gen
+    }
+
+    /// Indices, in increasing order, of the cells whose contact
+    /// bounding boxes intersect that of cell `ci`.
+    fn find_contacts(&self, ci: usize) -> Vec<usize> {
+        let bb = self.contact_grid.bbox(ci);
+        self.contact_grid
+            .query(bb)
+            .into_iter()
+            .filter(|&oci| {
+                oci != ci && self.contact_grid.intersects(oci, bb)
+            })
+            .collect()
+    }
+
+    /// The image of cell `oci` nearest to cell `ci`, which is cell
+    /// `oci` itself unless there is a periodic domain.
+    pub fn image_of(
+        &self,
+        oci: usize,
+        ci: usize,
+        cell_polys: &[Poly<N>],
+    ) -> Poly<N> {
+        poly_image_near(
+            self.domain.as_ref(),
+            &cell_polys[oci],
+            &cell_polys[ci].bbox.center(),
+        )
+    }
+
+    /// Calculate the close points of the vertices of cell `ci` to the
+    /// edges of cell `oci`.
+    fn calc_close_points(
+        &mut self,
+        ci: usize,
+        oci: usize,
+        cell_polys: &[Poly<N>],
+    ) {
+        let poly = &cell_polys[ci];
+        let other = &self.image_of(oci, ci, cell_polys);
+        let params = self.params;
+        let chunk = self.dat.chunk_mut(ci, oci);
+        for (vi, v) in poly.verts.iter().enumerate() {
+            for (ovi, ov) in other.verts.iter().enumerate() {
+                let ow = &other.verts[circ_ix_plus(ovi, N)];
+                chunk[vi * N + ovi] = ClosePoint::calc(
+                    params.crl_one_at,
+                    params.zero_at,
+                    params.zero_at_sq,
+                    *v,
+                    *ov,
+                    *ow,
+                );
+            }
         }
     }
 
-    pub fn update(&mut self, ci: usize, cell_polys: &[Poly]) {
-        let poly = cell_polys[ci];
-        let bb =
-            cell_polys[ci].bbox.expand_by(self.params.zero_at_sq);
-        self.contact_bbs[ci] = bb;
-        for (oci, obb) in self.contact_bbs.iter().enumerate() {
-            if oci != ci {
-                self.contact_matrix.set(ci, oci, obb.intersects(&bb));
+    /// Update contact information involving cell `ci`, whose polygon
+    /// in `cell_polys` has changed. Returns the indices of the cells
+    /// whose minimum vertex distances changed as a result.
+    pub fn update(
+        &mut self,
+        ci: usize,
+        cell_polys: &[Poly<N>],
+    ) -> Vec<usize> {
+        self.contact_grid.update(
+            ci,
+            cell_polys[ci].bbox.expand_by(self.params.zero_at_sq),
+        );
+        let old_contacts =
+            self.dat.partners(ci).collect::<Vec<usize>>();
+        let contacts = self.find_contacts(ci);
+        for &oci in old_contacts.iter() {
+            if !contacts.contains(&oci) {
+                self.dat.unpair(ci, oci);
+                self.dat.unpair(oci, ci);
             }
         }
-        for (oci, other) in cell_polys.iter().enumerate() {
-            if ci != oci {
-                for (vi, v) in poly.verts.iter().enumerate() {
-                    let w = &poly.verts[circ_ix_plus(vi, NVERTS)];
-                    for (ovi, ov) in other.verts.iter().enumerate() {
-                        let ow =
-                            &other.verts[circ_ix_plus(ovi, NVERTS)];
-                        if self.contact_matrix.get(ci, oci) {
-                            self.dat.set(
-                                ci,
-                                vi,
-                                oci,
-                                ovi,
-                                ClosePoint::calc(
-                                    self.params.crl_one_at,
-                                    self.params.zero_at,
-                                    self.params.zero_at_sq,
-                                    *v,
-                                    *ov,
-                                    *ow,
-                                ),
-                            );
-                            self.dat.set(
-                                oci,
-                                ovi,
-                                ci,
-                                vi,
-                                ClosePoint::calc(
-                                    self.params.crl_one_at,
-                                    self.params.zero_at,
-                                    self.params.zero_at_sq,
-                                    *ov,
-                                    *v,
-                                    *w,
-                                ),
-                            );
-                        } else {
-                            self.dat.set(
-                                ci,
-                                vi,
-                                oci,
-                                ovi,
-                                ClosePoint::default(),
-                            );
-                            self.dat.set(
-                                oci,
-                                ovi,
-                                ci,
-                                vi,
-                                ClosePoint::default(),
-                            );
-                        }
-                    }
-                }
+        for &oci in contacts.iter() {
+            self.calc_close_points(ci, oci, cell_polys);
+            self.calc_close_points(oci, ci, cell_polys);
+        }
+        // Only cells in contact with `ci` (before or after the
+        // update) may have new minimum distances.
+        let mut affected = old_contacts;
+        affected.extend(contacts);
+        affected.push(ci);
+        self.refresh_min_dists(affected)
+    }
+
+    /// Add contact information for a new cell, whose polygon is the
+    /// last one in `cell_polys`. Returns the indices of the cells
+    /// whose minimum vertex distances changed as a result.
+    pub fn add_cell(&mut self, cell_polys: &[Poly<N>]) -> Vec<usize> {
+        let ci = self.dat.num_cells;
+        self.dat.add_cell();
+        self.contact_grid.push(
+            cell_polys[ci].bbox.expand_by(self.params.zero_at_sq),
+        );
+        self.min_dist_matrix.push([f64::INFINITY; N]);
+        self.update(ci, cell_polys)
+    }
+
+    /// Remove contact information involving cell `ci`. `cell_polys`
+    /// no longer contains the polygon of the removed cell. Returns
+    /// the indices (after removal) of the cells whose minimum vertex
+    /// distances changed as a result.
+    pub fn remove_cell(&mut self, ci: usize) -> Vec<usize> {
+        let affected = self
+            .dat
+            .partners(ci)
+            .map(|oci| ix_after_removal(oci, ci))
+            .collect::<Vec<usize>>();
+        self.dat.remove_cell(ci);
+        self.contact_grid.remove(ci);
+        self.min_dist_matrix.remove(ci);
+        self.refresh_min_dists(affected)
+    }
+
+    /// Recalculate minimum vertex distances for the cells in
+    /// `affected`, and return the indices, in increasing order, of
+    /// those cells whose distances changed.
+    fn refresh_min_dists(
+        &mut self,
+        mut affected: Vec<usize>,
+    ) -> Vec<usize> {
+        affected.sort_unstable();
+        affected.dedup();
+        let mut changed = vec![];
+        for oci in affected {
+            let min_dists = self.eval_min_dist(oci);
+            if min_dists != self.min_dist_matrix[oci] {
+                self.min_dist_matrix[oci] = min_dists;
+                changed.push(oci);
             }
         }
-        self.min_dist_matrix[ci] =
-            PhysicalContactGenerator::eval_min_dist(
-                ci,
-                &self.contact_matrix,
-                &self.dat,
-            );
+        changed
     }
 
-    pub fn eval_min_dist(
-        ci: usize,
-        contact_matrix: &SymCcDat<bool>,
-        dat: &CvCvDat<ClosePoint>,
-    ) -> [f64; NVERTS] {
-        let mut r = [f64::INFINITY; NVERTS];
-        for vi in 0..NVERTS {
-            for oci in 0..contact_matrix.num_cells {
-                if oci != ci && contact_matrix.get(ci, oci) {
-                    for ovi in 0..NVERTS {
-                        match dat.get(ci, vi, oci, ovi) {
+    fn eval_min_dist(&self, ci: usize) -> [f64; N] {
+        let mut r = [f64::INFINITY; N];
+        for oci in self.dat.partners(ci) {
+            if let Some(chunk) = self.dat.chunk(ci, oci) {
+                for (vi, cps) in chunk.chunks(N).enumerate() {
+                    for cp in cps {
+                        match cp {
                             ClosePoint::OnEdge {
                                 dist_sq, ..
                             }
@@ -322,7 +358,7 @@ impl PhysicalContactGenerator {
                                 dist_sq, ..
                             }
                             | ClosePoint::None { dist_sq } => {
-                                r[vi] = r[vi].min(dist_sq);
+                                r[vi] = r[vi].min(*dist_sq);
                             }
                         }
                     }
@@ -332,6 +368,12 @@ impl PhysicalContactGenerator {
         r
     }
 
+    /// Indices, in increasing order, of the cells roughly in contact
+    /// with cell `ci`.
+    pub fn contacts(&self, ci: usize) -> Vec<usize> {
+        self.dat.partners(ci).collect()
+    }
+
     pub fn min_dist_to(&self, ci: usize, vi: usize) -> f64 {
         self.min_dist_matrix[ci][vi]
     }
@@ -342,11 +384,15 @@ impl PhysicalContactGenerator {
         ci: usize,
         vi: usize,
         oci: usize,
-        rel_rgtps_per_cell: &[[RelativeRgtpActivity; NVERTS]],
+        rel_rgtps_per_cell: &[[RelativeRgtpActivity; N]],
     ) -> Vec<CloseEdge> {
         let v_rgtp = rel_rgtps_per_cell[ci][vi];
-        (0..NVERTS)
-            .filter_map(|ovi| match self.dat.get(ci, vi, oci, ovi) {
+        let close_points = match self.dat.chunk(ci, oci) {
+            Some(chunk) => &chunk[vi * N..(vi + 1) * N],
+            None => return vec![],
+        };
+        (0..N)
+            .filter_map(|ovi| match close_points[ovi] {
                 ClosePoint::None { .. } => None,
                 ClosePoint::OnEdge {
                     vector_to,
@@ -356,15 +402,13 @@ impl PhysicalContactGenerator {
                 } => {
                     let edge_rgtp = RelativeRgtpActivity::mix_rel_rgtp_act_across_edge(
                         rel_rgtps_per_cell[oci][ovi],
-                        rel_rgtps_per_cell[oci]
-                            [circ_ix_plus(ovi, NVERTS)], edge_point_param,
+                        rel_rgtps_per_cell[oci][circ_ix_plus(ovi, N)],
+                        edge_point_param,
                     );
                     Some(CloseEdge {
                         cell_ix: oci,
                         vert_ix: ovi,
-                        crl: CrlEffect::calc_crl_on_focus(
-                            v_rgtp, edge_rgtp,
-                        ),
+                        crl: CrlEffect::calc_crl_on_focus(v_rgtp, edge_rgtp),
                         vector_to,
                         edge_point_param,
                         smooth_factor,
@@ -377,10 +421,7 @@ impl PhysicalContactGenerator {
                 } => Some(CloseEdge {
                     cell_ix: oci,
                     vert_ix: ovi,
-                    crl: CrlEffect::calc_crl_on_focus(
-                        v_rgtp,
-                        rel_rgtps_per_cell[oci][ovi],
-                    ),
+                    crl: CrlEffect::calc_crl_on_focus(v_rgtp, rel_rgtps_per_cell[oci][ovi]),
                     vector_to,
                     edge_point_param: 0.0,
                     smooth_factor,
@@ -394,10 +435,10 @@ impl PhysicalContactGenerator {
         &self,
         ci: usize,
         vi: usize,
-        cell_rgtps: &[[RelativeRgtpActivity; NVERTS]],
+        cell_rgtps: &[[RelativeRgtpActivity; N]],
     ) -> Vec<CloseEdge> {
         let mut r = vec![];
-        for oci in 0..self.dat.num_cells {
+        for oci in self.dat.partners(ci) {
             r.append(
                 &mut self
                     .get_close_edges_on_cell(ci, vi, oci, cell_rgtps),
@@ -406,43 +447,89 @@ impl PhysicalContactGenerator {
         r
     }
 
+    /// Adhesion, CIL and CAL factors of cell `ci`.
+    ///
+    /// Adhesion forces act on both cells of a close point, so the
+    /// factors of `ci` depend on the close points of the cells in
+    /// contact with it too. Their contributions are added in
+    /// increasing order of cell index, so the result does not depend
+    /// on which other cells' factors are generated.
+    ///
+    /// Magnitudes given in `group_mags` for the groups of a pair of
+    /// cells, where `cell_groups` holds the group index of each cell,
+    /// take precedence over those of `params`.
     pub fn generate(
         &self,
-        rel_rgtps_per_cell: &[[RelativeRgtpActivity; NVERTS]],
-    ) -> PhysContactFactors {
-        let num_cells = self.contact_matrix.num_cells;
-        let mut adh_per_cell =
-            vec![[V2d::default(); NVERTS]; num_cells];
-        let mut cal_per_cell = vec![[0.0f64; NVERTS]; num_cells];
-        let mut cil_per_cell = vec![[0.0f64; NVERTS]; num_cells];
-        for ci in 0..num_cells {
-            let x_cals = &mut cal_per_cell[ci];
-            let x_cils = &mut cil_per_cell[ci];
-            for vi in 0..NVERTS {
+        ci: usize,
+        rel_rgtps_per_cell: &[[RelativeRgtpActivity; N]],
+        cell_groups: &[usize],
+        group_mags: Option<&GroupPairMags>,
+    ) -> PhysContactFactors<N> {
+        let mut x_adhs = [V2d::default(); N];
+        let mut x_cals = [0.0f64; N];
+        let mut x_cils = [0.0f64; N];
+        let mut acting = self.contacts(ci);
+        acting.push(ci);
+        acting.sort_unstable();
+        for aci in acting {
+            for avi in 0..N {
+                let close_edges = if aci == ci {
+                    self.get_close_edges_to(
+                        ci,
+                        avi,
+                        rel_rgtps_per_cell,
+                    )
+                } else {
+                    self.get_close_edges_on_cell(
+                        aci,
+                        avi,
+                        ci,
+                        rel_rgtps_per_cell,
+                    )
+                };
                 for CloseEdge {
-                    cell_ix: oci,
+                    cell_ix: eci,
                     vert_ix: ovi,
                     crl,
                     vector_to,
                     edge_point_param,
                     smooth_factor,
-                } in self
-                    .get_close_edges_to(ci, vi, rel_rgtps_per_cell)
-                    .into_iter()
+                    ..
+                } in close_edges.into_iter()
                 {
-                    match (self.params.cal_mag, crl) {
-                        (Some(cal_mag), CrlEffect::Cal) => {
-                            x_cals[vi] = x_cals[vi]
-                                .max(smooth_factor * cal_mag);
-                        }
-                        (Some(_), CrlEffect::Cil) | (None, _) => {
-                            x_cils[vi] = x_cils[vi].max(
-                                smooth_factor * self.params.cil_mag,
-                            );
+                    if aci == ci {
+                        let (g, og) =
+                            (cell_groups[ci], cell_groups[eci]);
+                        let cal_mag = group_mags
+                            .and_then(|mags| mags.cal(g, og))
+                            .or(self.params.cal_mag);
+                        match (cal_mag, crl) {
+                            (Some(cal_mag), CrlEffect::Cal) => {
+                                x_cals[avi] = x_cals[avi]
+                                    .max(smooth_factor * cal_mag);
+                            }
+                            (Some(_), CrlEffect::Cil) | (None, _) => {
+                                let cil_mag = group_mags
+                                    .and_then(|mags| mags.cil(g, og))
+                                    .unwrap_or(self.params.cil_mag);
+                                x_cils[avi] = x_cils[avi]
+                                    .max(smooth_factor * cil_mag);
+                            }
                         }
                     }
 
-                    if let Some(adh_mag) = self.params.adh_mag {
+                    // Both ends of an adhesion use the magnitude for
+                    // the group of the vertex's cell and the group of
+                    // the edge's cell.
+                    let adh_mag = group_mags
+                        .and_then(|mags| {
+                            mags.adh(
+                                cell_groups[aci],
+                                cell_groups[eci],
+                            )
+                        })
+                        .or(self.params.adh_mag);
+                    if let Some(adh_mag) = adh_mag {
                         let vc_mag = vector_to.mag();
                         let adh_strain =
                             if vc_mag > self.params.adh_break {
@@ -456,40 +543,33 @@ impl PhysicalContactGenerator {
                             } else {
                                 1.0
                             };
-                        // println!("ci_vi_oci_ovi: ({}, {}, {}, {}), zero_at: {}, adh_break: {}, \
-                        //     adh_rest: {}, vc_mag: {}, adh_strain: {}, smooth_factor: {}",
-                        //          ci, vi, oci, ovi, self.params.zero_at, self.params.adh_break,
-                        //          self.params.adh_rest, vc_mag, adh_strain, smooth_factor);
                         let adh_force = adh_mag
                             * adh_strain
                             * vector_to.unitize();
-                        //* ((1.0 / self.params.range) * delta);
-                        // We are close to the vertex.
-                        if close_to_zero(edge_point_param, 1e-3) {
-                            adh_per_cell[oci][ovi] =
-                                adh_per_cell[oci][ovi] - adh_force;
-                            adh_per_cell[ci][vi] =
-                                adh_per_cell[ci][vi] + adh_force;
+                        if aci == ci {
+                            x_adhs[avi] = x_adhs[avi] + adh_force;
+                        } else if close_to_zero(
+                            edge_point_param,
+                            1e-3,
+                        ) {
+                            // We are close to the vertex.
+                            x_adhs[ovi] = x_adhs[ovi] - adh_force;
                         } else {
-                            adh_per_cell[oci][ovi] = adh_per_cell
-                                [oci][ovi]
+                            x_adhs[ovi] = x_adhs[ovi]
                                 - (1.0 - edge_point_param)
                                     * adh_force;
-                            let owi = circ_ix_plus(ovi, NVERTS);
-                            adh_per_cell[oci][owi] = adh_per_cell
-                                [oci][owi]
+                            let owi = circ_ix_plus(ovi, N);
+                            x_adhs[owi] = x_adhs[owi]
                                 - edge_point_param * adh_force;
-                            adh_per_cell[ci][vi] =
-                                adh_per_cell[ci][vi] + adh_force;
                         }
                     };
                 }
             }
         }
         PhysContactFactors {
-            adh: adh_per_cell,
-            cil: cil_per_cell,
-            cal: cal_per_cell,
+            adh: x_adhs,
+            cil: x_cils,
+            cal: x_cals,
         }
     }
 }
@@ -6,31 +6,45 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod bbox_grid;
 mod gen_coa;
 // pub mod dat2d;
 pub mod dat_4d;
 pub mod dat_sym2d;
 pub mod dat_sym4d;
 mod dat_utils;
+pub mod gen_adh;
 pub mod gen_bdry;
+pub mod gen_chem_field;
 pub mod gen_chemoa;
 mod gen_phys;
+pub mod source;
 
-use crate::interactions::dat_sym2d::SymCcDat;
+use crate::interactions::gen_adh::{AdhBond, AdhBondGenerator};
 use crate::interactions::gen_bdry::BdryEffectGenerator;
+use crate::interactions::gen_chem_field::{
+    ChemField, ChemFieldGenerator,
+};
 use crate::interactions::gen_chemoa::ChemAttrGenerator;
+pub use crate::interactions::gen_coa::coa_reach;
 use crate::interactions::gen_coa::CoaGenerator;
 use crate::interactions::gen_phys::{
     PhysContactFactors, PhysicalContactGenerator,
 };
+use crate::interactions::source::{
+    InteractionSource, SourceContext, SourceFactors,
+};
 use crate::interactions::RelativeRgtpActivity::{
     RacDominant, RhoDominant,
 };
-use crate::math::geometry::{BBox, Poly};
+use crate::math::geometry::{LineSeg2D, PeriodicDomain, Poly};
 use crate::math::v2d::V2d;
-use crate::parameters::InteractionParams;
-use crate::NVERTS;
+use crate::parameters::{
+    BdryParams, GroupPairMags, InteractionParams, ObstacleParams,
+};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
 
 /// The relative Rho GTPase activity at a cell is positive if Rac1
 /// dominates, otherwise it is negative.
@@ -70,160 +84,698 @@ impl RelativeRgtpActivity {
     }
 }
 
-#[derive(
-    Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq,
-)]
-pub struct Interactions {
-    pub x_cals: [f64; NVERTS],
-    pub x_cils: [f64; NVERTS],
-    pub x_adhs: [V2d; NVERTS],
-    pub x_chem_attrs: [f64; NVERTS],
-    pub x_coas: [f64; NVERTS],
-    pub x_bdrys: [f64; NVERTS],
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Interactions<const N: usize> {
+    #[serde(with = "BigArray")]
+    pub x_cals: [f64; N],
+    #[serde(with = "BigArray")]
+    pub x_cils: [f64; N],
+    #[serde(with = "BigArray")]
+    pub x_adhs: [V2d; N],
+    #[serde(with = "BigArray")]
+    pub x_chem_attrs: [f64; N],
+    #[serde(with = "BigArray")]
+    pub x_coas: [f64; N],
+    /// CIL-type factors due to boundary walls and obstacles.
+    #[serde(with = "BigArray")]
+    pub x_bdrys: [f64; N],
+    /// Factors contributed by registered interaction sources.
+    pub x_srcs: SourceFactors<N>,
+}
+
+impl<const N: usize> Default for Interactions<N> {
+    fn default() -> Self {
+        Interactions {
+            x_cals: [0.0; N],
+            x_cils: [0.0; N],
+            x_adhs: [V2d::default(); N],
+            x_chem_attrs: [0.0; N],
+            x_coas: [0.0; N],
+            x_bdrys: [0.0; N],
+            x_srcs: SourceFactors::default(),
+        }
+    }
 }
 
 /// Generates interaction related factors.
-#[derive(Clone, Deserialize, Serialize)]
-pub struct InteractionGenerator {
+#[derive(Clone)]
+pub struct InteractionGenerator<const N: usize> {
     /// Vertex coordinates, per cell, for all cells in the simulation.
-    cell_polys: Vec<Poly>,
-    all_rgtps: Vec<[RelativeRgtpActivity; NVERTS]>,
+    cell_polys: Vec<Poly<N>>,
+    all_rgtps: Vec<[RelativeRgtpActivity; N]>,
     /// Generates CIL/CAL related interaction information. In other
     /// words, interactions that require cells to engage in physical
     /// contact.
-    pub phys_contact_generator: PhysicalContactGenerator,
-    coa_generator: Option<CoaGenerator>,
+    pub phys_contact_generator: PhysicalContactGenerator<N>,
+    coa_generator: Option<CoaGenerator<N>>,
     chem_attr_generator: Option<ChemAttrGenerator>,
-    bdry_generator: Option<BdryEffectGenerator>,
+    chem_field_generator: Option<ChemFieldGenerator>,
+    bdry_generator: Option<BdryEffectGenerator<N>>,
+    /// Static obstacles, which act on cells like boundary walls.
+    obstacle_generator: Option<BdryEffectGenerator<N>>,
+    /// Stateful adhesion bonds, which replace the adhesion generated
+    /// by `phys_contact_generator` if present.
+    adh_bond_generator: Option<AdhBondGenerator<N>>,
+    /// Group index of each cell.
+    cell_groups: Vec<usize>,
+    /// Interaction magnitudes which depend on the groups of
+    /// interacting cells.
+    group_mags: Option<GroupPairMags>,
+    /// User-defined interaction sources (see `InteractionSource`).
+    sources: Vec<Box<dyn InteractionSource<N>>>,
+    /// Interactions of each cell, as last generated.
+    interactions: Vec<Interactions<N>>,
+    /// Whether the interactions of each cell may have changed since
+    /// they were last generated.
+    stale: Vec<bool>,
 }
 
-pub struct ContactData {
+#[derive(Clone)]
+pub struct ContactData<const N: usize> {
     pub oci: usize,
-    pub poly: Poly,
+    pub poly: Poly<N>,
+}
+
+/// Everything a cell must not overlap with: the cells it is in
+/// contact with, and the edges of boundary walls and obstacles close
+/// to it.
+#[derive(Clone, Default)]
+pub struct Contacts<const N: usize> {
+    pub cells: Vec<ContactData<N>>,
+    pub walls: Vec<LineSeg2D>,
+    pub obstacles: Vec<LineSeg2D>,
 }
 
-impl InteractionGenerator {
+impl<const N: usize> InteractionGenerator<N> {
+    /// Create a generator for cells with vertices `cell_verts`, and
+    /// group indices `cell_groups`, among `obstacles`. If `domain` is
+    /// given, cells interact with the nearest images of each other in
+    /// it; walls, obstacles and chemoattractant are not periodic.
     pub fn new(
-        cell_verts: &[[V2d; NVERTS]],
-        cell_rgtps: &[[RelativeRgtpActivity; NVERTS]],
+        cell_verts: &[[V2d; N]],
+        cell_rgtps: &[[RelativeRgtpActivity; N]],
+        cell_groups: &[usize],
         params: InteractionParams,
-    ) -> InteractionGenerator {
+        obstacles: Option<ObstacleParams>,
+        domain: Option<PeriodicDomain>,
+    ) -> InteractionGenerator<N> {
         let cell_polys = cell_verts
             .iter()
             .map(|vs| Poly::from_verts(vs))
-            .collect::<Vec<Poly>>();
+            .collect::<Vec<Poly<N>>>();
         let phys_contact_generator = PhysicalContactGenerator::new(
             &cell_polys,
             params.phys_contact,
+            domain,
         );
+        let adh_bond_generator = params.adh_bonds.map(|ab_params| {
+            AdhBondGenerator::new(
+                ab_params,
+                &params.phys_contact,
+                domain,
+            )
+        });
         let coa_generator = params.coa.map(|coa_params| {
             CoaGenerator::new(
                 &cell_polys,
                 coa_params,
                 &phys_contact_generator,
+                domain,
             )
         });
         let chem_attr_generator =
             params.chem_attr.map(ChemAttrGenerator::new);
-        let bdry_generator =
-            params.bdry.map(BdryEffectGenerator::new);
+        let chem_field_generator =
+            params.chem_field.map(ChemFieldGenerator::new);
+        let contact_dist = params.phys_contact.zero_at;
+        let bdry_generator = params.bdry.map(|bdry_params| {
+            BdryEffectGenerator::new(
+                &cell_polys,
+                bdry_params,
+                contact_dist,
+            )
+        });
+        let obstacle_generator = obstacles.map(|obstacle_params| {
+            BdryEffectGenerator::new(
+                &cell_polys,
+                BdryParams {
+                    shapes: obstacle_params.shapes,
+                    touch_dist: obstacle_params.touch_dist,
+                    mag: obstacle_params.cil_mag.unwrap_or(0.0),
+                },
+                contact_dist,
+            )
+        });
+        let num_cells = cell_polys.len();
         InteractionGenerator {
             cell_polys: cell_polys.iter().copied().collect(),
             all_rgtps: cell_rgtps.iter().copied().collect(),
             phys_contact_generator,
             coa_generator,
             chem_attr_generator,
+            chem_field_generator,
             bdry_generator,
+            obstacle_generator,
+            adh_bond_generator,
+            cell_groups: cell_groups.to_vec(),
+            group_mags: params.group_pair_mags,
+            sources: vec![],
+            interactions: vec![Interactions::default(); num_cells],
+            stale: vec![true; num_cells],
         }
     }
 
-    pub fn update(&mut self, cell_ix: usize, vs: &[V2d; NVERTS]) {
-        self.phys_contact_generator
-            .update(cell_ix, &self.cell_polys);
+    pub fn update(&mut self, cell_ix: usize, vs: &[V2d; N]) {
+        let old_bbox = self.cell_polys[cell_ix].bbox;
         self.cell_polys[cell_ix] = Poly::from_verts(vs);
+        // Cells in contact with the updated cell, before or after the
+        // update, may have new CIL, CAL or adhesion factors.
+        let mut changed =
+            self.phys_contact_generator.contacts(cell_ix);
+        let min_dist_changed = self
+            .phys_contact_generator
+            .update(cell_ix, &self.cell_polys);
+        changed.extend(self.phys_contact_generator.contacts(cell_ix));
         if let Some(coa_gen) = self.coa_generator.as_mut() {
-            coa_gen.update(
+            changed.extend(coa_gen.update(
                 cell_ix,
+                &old_bbox,
                 &self.cell_polys,
                 &self.phys_contact_generator,
+                &min_dist_changed,
+            ))
+        }
+        if let Some(bdry_gen) = self.bdry_generator.as_mut() {
+            bdry_gen.update(cell_ix, &self.cell_polys)
+        }
+        if let Some(obs_gen) = self.obstacle_generator.as_mut() {
+            obs_gen.update(cell_ix, &self.cell_polys)
+        }
+        if let Some(ab_gen) = self.adh_bond_generator.as_ref() {
+            changed.extend(ab_gen.partners(cell_ix));
+        }
+        for source in self.sources.iter_mut() {
+            changed.extend(source.update(cell_ix, &self.cell_polys));
+        }
+        self.stale[cell_ix] = true;
+        for ci in changed {
+            self.stale[ci] = true;
+        }
+    }
+
+    /// Register an interaction source. A source which has the same
+    /// name as one already registered replaces it.
+    pub fn add_source(
+        &mut self,
+        source: Box<dyn InteractionSource<N>>,
+    ) {
+        match self
+            .sources
+            .iter_mut()
+            .find(|s| s.name() == source.name())
+        {
+            Some(s) => *s = source,
+            None => self.sources.push(source),
+        }
+        self.invalidate();
+    }
+
+    /// Names of the registered interaction sources.
+    pub fn source_names(&self) -> Vec<&str> {
+        self.sources.iter().map(|s| s.name()).collect()
+    }
+
+    /// Factors contributed by each registered interaction source to
+    /// the vertices of cell `ci`, by name, given the Rho GTPase
+    /// activities interactions were last generated with.
+    pub fn source_factors(&self, ci: usize) -> Vec<(&str, [f64; N])> {
+        let contacts = self.phys_contact_generator.contacts(ci);
+        let ctx = SourceContext {
+            cell_polys: &self.cell_polys,
+            rel_rgtps: &self.all_rgtps,
+            contacts: &contacts,
+        };
+        self.sources
+            .iter()
+            .map(|s| (s.name(), s.generate(ci, &ctx)))
+            .collect()
+    }
+
+    /// Mark the interactions of all cells as stale.
+    fn invalidate(&mut self) {
+        let num_cells = self.cell_polys.len();
+        self.interactions = vec![Interactions::default(); num_cells];
+        self.stale = vec![true; num_cells];
+    }
+
+    /// Add a new cell with vertices `vs`, belonging to group
+    /// `group_ix`, which will have index `num_cells`.
+    pub fn add_cell(
+        &mut self,
+        vs: &[V2d; N],
+        rgtps: [RelativeRgtpActivity; N],
+        group_ix: usize,
+    ) {
+        self.cell_polys.push(Poly::from_verts(vs));
+        self.all_rgtps.push(rgtps);
+        self.cell_groups.push(group_ix);
+        let min_dist_changed =
+            self.phys_contact_generator.add_cell(&self.cell_polys);
+        if let Some(coa_gen) = self.coa_generator.as_mut() {
+            coa_gen.add_cell(
+                &self.cell_polys,
+                &self.phys_contact_generator,
+                &min_dist_changed,
+            )
+        }
+        if let Some(bdry_gen) = self.bdry_generator.as_mut() {
+            bdry_gen.add_cell(&self.cell_polys)
+        }
+        if let Some(obs_gen) = self.obstacle_generator.as_mut() {
+            obs_gen.add_cell(&self.cell_polys)
+        }
+        for source in self.sources.iter_mut() {
+            source.add_cell(&self.cell_polys);
+        }
+        self.invalidate();
+    }
+
+    /// Remove cell `cell_ix`. Cells with greater indices have their
+    /// indices shifted down by one.
+    pub fn remove_cell(&mut self, cell_ix: usize) {
+        let old_bbox = self.cell_polys.remove(cell_ix).bbox;
+        self.all_rgtps.remove(cell_ix);
+        self.cell_groups.remove(cell_ix);
+        let min_dist_changed =
+            self.phys_contact_generator.remove_cell(cell_ix);
+        if let Some(coa_gen) = self.coa_generator.as_mut() {
+            coa_gen.remove_cell(
+                cell_ix,
+                &old_bbox,
+                &self.cell_polys,
+                &self.phys_contact_generator,
+                &min_dist_changed,
             )
         }
-        if let Some(chema_gen) = self.chem_attr_generator.as_mut() {
-            chema_gen.update()
+        if let Some(bdry_gen) = self.bdry_generator.as_mut() {
+            bdry_gen.remove_cell(cell_ix)
+        }
+        if let Some(obs_gen) = self.obstacle_generator.as_mut() {
+            obs_gen.remove_cell(cell_ix)
+        }
+        if let Some(ab_gen) = self.adh_bond_generator.as_mut() {
+            ab_gen.remove_cell(cell_ix)
+        }
+        for source in self.sources.iter_mut() {
+            source.remove_cell(cell_ix);
+        }
+        self.invalidate();
+    }
+
+    /// Advance chemoattractant to time `tpoint`: the field, if there
+    /// is one, is stepped from `tpoint - dt`, and source strengths
+    /// are evaluated at `tpoint`. Returns whether chemoattractant
+    /// signals may have changed, in which case interactions should
+    /// be regenerated.
+    pub fn advance_chem(&mut self, tpoint: f64, dt: f64) -> bool {
+        let attr_changed = self
+            .chem_attr_generator
+            .as_mut()
+            .is_some_and(|gen| gen.set_tpoint(tpoint));
+        let changed = match self.chem_field_generator.as_mut() {
+            Some(gen) => {
+                if dt > 0.0 {
+                    gen.step(tpoint - dt, dt, &self.cell_polys);
+                }
+                true
+            }
+            None => attr_changed,
+        };
+        if changed {
+            self.stale.iter_mut().for_each(|s| *s = true);
         }
-        if let Some(_bdry_gen) = self.bdry_generator.as_mut() {
-            unimplemented!()
+        changed
+    }
+
+    /// Advance adhesion bonds, if there are any, over a time step of
+    /// length `dt` (see `AdhBondGenerator::step`), with the Rho
+    /// GTPase activities interactions were last generated with.
+    /// Returns whether any bonds changed, in which case interactions
+    /// should be regenerated.
+    pub fn step_adh_bonds<R: Rng>(
+        &mut self,
+        dt: f64,
+        rng: &mut R,
+    ) -> bool {
+        let changed = match self.adh_bond_generator.as_mut() {
+            Some(gen) => gen.step(
+                dt,
+                &self.cell_polys,
+                &self.phys_contact_generator,
+                &self.all_rgtps,
+                &self.cell_groups,
+                self.group_mags.as_ref(),
+                rng,
+            ),
+            None => return false,
+        };
+        for &ci in changed.iter() {
+            self.stale[ci] = true;
         }
+        !changed.is_empty()
     }
 
+    /// Remove the adhesion bonds of cell `ci`, whose vertices have
+    /// been replaced, for example by remeshing or division.
+    pub fn release_adh_bonds(&mut self, ci: usize) {
+        if let Some(gen) = self.adh_bond_generator.as_mut() {
+            self.stale[ci] = true;
+            for oci in gen.release(ci) {
+                self.stale[oci] = true;
+            }
+        }
+    }
+
+    /// Adhesion bonds, if the experiment has them.
+    pub fn adh_bonds(&self) -> Option<&[AdhBond]> {
+        self.adh_bond_generator.as_ref().map(|gen| gen.bonds())
+    }
+
+    /// Replace the adhesion bonds, if the experiment has them.
+    pub fn set_adh_bonds(&mut self, bonds: Vec<AdhBond>) {
+        if let Some(gen) = self.adh_bond_generator.as_mut() {
+            gen.set_bonds(bonds);
+            self.stale.iter_mut().for_each(|s| *s = true);
+        }
+    }
+
+    pub fn chem_field(&self) -> Option<&ChemField> {
+        self.chem_field_generator.as_ref().map(|gen| gen.field())
+    }
+
+    /// Replace the chemoattractant field, if there is one.
+    pub fn set_chem_field(&mut self, field: ChemField) {
+        if let Some(gen) = self.chem_field_generator.as_mut() {
+            gen.set_field(field);
+            self.stale.iter_mut().for_each(|s| *s = true);
+        }
+    }
+
+    pub fn num_cells(&self) -> usize {
+        self.cell_polys.len()
+    }
+
+    /// Interactions of all cells. Only the interactions of cells
+    /// which may have changed since they were last generated are
+    /// recalculated.
     pub fn generate(
+        &mut self,
+        rel_rgtps: &[[RelativeRgtpActivity; N]],
+    ) -> Vec<Interactions<N>> {
+        self.regenerate(rel_rgtps);
+        self.interactions.clone()
+    }
+
+    /// Like `generate`, but `interactions` must already hold the
+    /// interactions last returned by `generate` or `generate_into`,
+    /// and only those which were recalculated are written to it.
+    pub fn generate_into(
+        &mut self,
+        rel_rgtps: &[[RelativeRgtpActivity; N]],
+        interactions: &mut [Interactions<N>],
+    ) {
+        for ci in self.regenerate(rel_rgtps) {
+            interactions[ci] = self.interactions[ci];
+        }
+    }
+
+    /// Recalculate the interactions of stale cells, returning their
+    /// indices. The CIL, CAL and adhesion factors of a cell depend
+    /// on the Rho GTPase activities of the cells it is in contact
+    /// with, so these cells are stale too if activities changed.
+    fn regenerate(
+        &mut self,
+        rel_rgtps: &[[RelativeRgtpActivity; N]],
+    ) -> Vec<usize> {
+        for (ci, (old, new)) in
+            self.all_rgtps.iter_mut().zip(rel_rgtps).enumerate()
+        {
+            let same = old.iter().zip(new.iter()).all(|(a, b)| {
+                std::mem::discriminant(a) == std::mem::discriminant(b)
+                    && a.to_f64().to_bits() == b.to_f64().to_bits()
+            });
+            if !same {
+                *old = *new;
+                self.stale[ci] = true;
+                for oci in self.phys_contact_generator.contacts(ci) {
+                    self.stale[oci] = true;
+                }
+            }
+        }
+        let regenerated = (0..self.stale.len())
+            .filter(|&ci| self.stale[ci])
+            .collect::<Vec<usize>>();
+        for &ci in regenerated.iter() {
+            self.interactions[ci] = self.generate_cell(ci, rel_rgtps);
+            self.stale[ci] = false;
+        }
+        regenerated
+    }
+
+    fn generate_cell(
         &self,
-        rel_rgtps: &Vec<[RelativeRgtpActivity; NVERTS]>,
-    ) -> Vec<Interactions> {
-        let num_cells = self.cell_polys.len();
-        let PhysContactFactors { adh, cil, cal } =
-            self.phys_contact_generator.generate(rel_rgtps);
-        let r_coas = self
-            .coa_generator
-            .as_ref()
-            .map_or(vec![[0.0; NVERTS]; num_cells], |gen| {
-                gen.generate()
+        ci: usize,
+        rel_rgtps: &[[RelativeRgtpActivity; N]],
+    ) -> Interactions<N> {
+        let poly = &self.cell_polys[ci];
+        let group_mags = self.group_mags.as_ref();
+        let PhysContactFactors { mut adh, cil, cal } = self
+            .phys_contact_generator
+            .generate(ci, rel_rgtps, &self.cell_groups, group_mags);
+        if let Some(gen) = self.adh_bond_generator.as_ref() {
+            adh = gen.generate(
+                ci,
+                &self.cell_polys,
+                &self.cell_groups,
+                group_mags,
+            );
+        }
+        let x_coas =
+            self.coa_generator.as_ref().map_or([0.0; N], |gen| {
+                gen.generate(ci, &self.cell_groups, group_mags)
             });
-        let r_chemoas = self
+        let mut x_chem_attrs = self
             .chem_attr_generator
             .as_ref()
-            .map_or(vec![[0.0; NVERTS]; num_cells], |gen| {
-                gen.generate(&self.cell_polys)
-            });
-        let r_bdrys = self
+            .map_or([0.0; N], |gen| gen.generate(poly));
+        if let Some(gen) = self.chem_field_generator.as_ref() {
+            x_chem_attrs
+                .iter_mut()
+                .zip(gen.generate(poly).iter())
+                .for_each(|(r, f)| *r += f);
+        }
+        let mut x_bdrys = self
             .bdry_generator
             .as_ref()
-            .map_or(vec![[0.0; NVERTS]; num_cells], |gen| {
-                gen.generate(&self.cell_polys)
-            });
-        (0..num_cells)
-            .map(|ci| Interactions {
-                x_cals: cal[ci],
-                x_cils: cil[ci],
-                x_adhs: adh[ci],
-                x_chem_attrs: r_chemoas[ci],
-                x_coas: r_coas[ci],
-                x_bdrys: r_bdrys[ci],
-            })
-            .collect()
+            .map_or([0.0; N], |gen| gen.generate(ci));
+        if let Some(gen) = self.obstacle_generator.as_ref() {
+            x_bdrys
+                .iter_mut()
+                .zip(gen.generate(ci).iter())
+                .for_each(|(r, f)| *r = r.max(*f));
+        }
+        let mut x_srcs = SourceFactors::default();
+        if !self.sources.is_empty() {
+            let contacts = self.phys_contact_generator.contacts(ci);
+            let ctx = SourceContext {
+                cell_polys: &self.cell_polys,
+                rel_rgtps,
+                contacts: &contacts,
+            };
+            for source in self.sources.iter() {
+                x_srcs
+                    .add(source.effect(), &source.generate(ci, &ctx));
+            }
+        }
+        Interactions {
+            x_cals: cal,
+            x_cils: cil,
+            x_adhs: adh,
+            x_chem_attrs,
+            x_coas,
+            x_bdrys,
+            x_srcs,
+        }
     }
 
     pub fn get_physical_contacts(&self, ci: usize) -> Vec<usize> {
-        let num_cells = self.cell_polys.len();
-        (0..num_cells)
-            .filter(|&oci| {
-                self.phys_contact_generator
-                    .contact_matrix
-                    .get(ci, oci)
-            })
-            .collect()
+        self.phys_contact_generator.contacts(ci)
     }
 
-    pub fn get_contact_data(&self, ci: usize) -> Vec<ContactData> {
-        self.get_physical_contacts(ci)
+    pub fn get_contact_data(&self, ci: usize) -> Contacts<N> {
+        let cells = self
+            .get_physical_contacts(ci)
             .into_iter()
             .map(|oci| ContactData {
                 oci,
-                poly: self.cell_polys[oci],
+                poly: self.phys_contact_generator.image_of(
+                    oci,
+                    ci,
+                    &self.cell_polys,
+                ),
             })
-            .collect()
+            .collect();
+        let walls = self
+            .bdry_generator
+            .as_ref()
+            .map_or(vec![], |gen| gen.close_edges(ci).to_vec());
+        let obstacles = self
+            .obstacle_generator
+            .as_ref()
+            .map_or(vec![], |gen| gen.close_edges(ci).to_vec());
+        Contacts {
+            cells,
+            walls,
+            obstacles,
+        }
     }
 }
 
-/// Generate a `SymCcDat<bool>` which records whether two cells
-/// are roughly in contact, based on intersection of their contact
-/// bounding boxes.
-pub fn gen_contact_matrix(contact_bbs: &[BBox]) -> SymCcDat<bool> {
-    let mut contacts = SymCcDat::new(contact_bbs.len(), false);
-    for (ci, bb) in contact_bbs.iter().enumerate() {
-        for (oxi, obb) in contact_bbs[(ci + 1)..].iter().enumerate() {
-            contacts.set(ci, ci + 1 + oxi, obb.intersects(bb));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exp_setup::defaults::{
+        adh_mag, raw_world_params, CELL_DIAMETER, CHAR_QUANTS,
+        RAW_COA_PARAMS_WITH_ZERO_MAG,
+    };
+    use crate::parameters::quantity::{Length, Quantity};
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+    use std::f64::consts::PI;
+
+    const N: usize = 16;
+
+    /// Vertices of a regular polygon of radius `r` centered at `c`.
+    fn circle(c: V2d, r: f64) -> [V2d; N] {
+        let mut vs = [V2d::default(); N];
+        for (vi, v) in vs.iter_mut().enumerate() {
+            let theta = 2.0 * PI * vi as f64 / N as f64;
+            *v = c + V2d::new(r * theta.cos(), r * theta.sin());
+        }
+        vs
+    }
+
+    fn random_rgtps(rng: &mut Pcg32) -> [RelativeRgtpActivity; N] {
+        let mut rgtps = [RelativeRgtpActivity::RhoDominant(0.0); N];
+        for r in rgtps.iter_mut() {
+            let x = rng.gen_range(-1.0..1.0);
+            *r = RelativeRgtpActivity::from_f64(x);
+        }
+        rgtps
+    }
+
+    /// Interactions must be the same down to the bit whether only
+    /// stale cells are regenerated after each cell is updated, as in
+    /// a sequential time step, or all of them are.
+    fn check_incremental_generation(coa: bool) {
+        let cq = &*CHAR_QUANTS;
+        let mut raw = raw_world_params(N).interactions;
+        if coa {
+            raw.coa =
+                Some(RAW_COA_PARAMS_WITH_ZERO_MAG.modify_mag(24.0));
+        }
+        raw.phys_contact.adh_mag = Some(adh_mag(N));
+        raw.phys_contact.cal_mag = Some(10.0);
+        let params = raw.refine(cq, N);
+
+        // Two rows of cells, which move in and out of contact with
+        // each other.
+        let r = 0.5 * cq.normalize(&*CELL_DIAMETER);
+        let gap = cq.normalize(&Length(1.5).micro());
+        let shift = cq.normalize(&Length(0.6).micro());
+        let jitter = cq.normalize(&Length(0.05).micro());
+        let mut rng = Pcg32::seed_from_u64(3);
+        let mut cell_verts = vec![];
+        for i in 0..3 {
+            for j in 0..2 {
+                let c = V2d::new(
+                    i as f64 * (2.0 * r + gap),
+                    j as f64 * (2.0 * r + gap),
+                );
+                cell_verts.push(circle(c, r));
+            }
+        }
+        let home = cell_verts.clone();
+        let num_cells = cell_verts.len();
+        let mut rel_rgtps = (0..num_cells)
+            .map(|_| random_rgtps(&mut rng))
+            .collect::<Vec<[RelativeRgtpActivity; N]>>();
+
+        let mut incremental = InteractionGenerator::new(
+            &cell_verts,
+            &rel_rgtps,
+            &vec![0; num_cells],
+            params,
+            None,
+            None,
+        );
+        let mut full = incremental.clone();
+        let mut interactions = incremental.generate(&rel_rgtps);
+
+        for _ in 0..10 {
+            let mut order = (0..num_cells).collect::<Vec<usize>>();
+            order.shuffle(&mut rng);
+            for ci in order {
+                let d = V2d::new(
+                    rng.gen_range(-shift..shift),
+                    rng.gen_range(-shift..shift),
+                );
+                for (v, h) in
+                    cell_verts[ci].iter_mut().zip(home[ci].iter())
+                {
+                    *v = *h
+                        + d
+                        + V2d::new(
+                            rng.gen_range(-jitter..jitter),
+                            rng.gen_range(-jitter..jitter),
+                        );
+                }
+                rel_rgtps[ci] = random_rgtps(&mut rng);
+
+                incremental.update(ci, &cell_verts[ci]);
+                incremental
+                    .generate_into(&rel_rgtps, &mut interactions);
+                full.update(ci, &cell_verts[ci]);
+                full.invalidate();
+                let expected = full.generate(&rel_rgtps);
+
+                for (oci, (got, want)) in interactions
+                    .iter()
+                    .zip(expected.iter())
+                    .enumerate()
+                {
+                    assert!(
+                        bincode::serialize(got).unwrap()
+                            == bincode::serialize(want).unwrap(),
+                        "interactions of cell {} differ after updating cell {}:\n{:?}\n{:?}",
+                        oci,
+                        ci,
+                        got,
+                        want
+                    );
+                }
+            }
         }
     }
-    contacts
+
+    #[test]
+    fn incremental_generation_matches_full_regeneration() {
+        check_incremental_generation(false);
+    }
+
+    #[test]
+    fn incremental_generation_with_coa_matches_full_regeneration() {
+        check_incremental_generation(true);
+    }
 }
@@ -0,0 +1,133 @@
+use crate::interactions::RelativeRgtpActivity;
+use crate::math::geometry::Poly;
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
+
+/// How the per-vertex factors contributed by an `InteractionSource`
+/// affect a cell.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq,
+)]
+pub enum SourceEffect {
+    /// Increases the base Rac1 activation rate, like CAL and COA.
+    RacActivation,
+    /// Increases Rac1 auto-activation, like chemoattraction.
+    RacAutoActivation,
+    /// Increases the Rac1 inactivation rate.
+    RacInactivation,
+    /// Increases the RhoA activation rate.
+    RhoActivation,
+    /// Increases the RhoA inactivation rate.
+    RhoInactivation,
+    /// Force on each vertex along the unit vector pointing into the
+    /// cell at the vertex (outwards, if the factor is negative).
+    InwardForce,
+}
+
+/// Factors contributed by all interaction sources to a cell, summed
+/// by effect.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SourceFactors<const N: usize> {
+    #[serde(with = "BigArray")]
+    pub rac_acts: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rac_autos: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rac_inacts: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rho_acts: [f64; N],
+    #[serde(with = "BigArray")]
+    pub rho_inacts: [f64; N],
+    #[serde(with = "BigArray")]
+    pub inward_forces: [f64; N],
+}
+
+impl<const N: usize> Default for SourceFactors<N> {
+    fn default() -> Self {
+        SourceFactors {
+            rac_acts: [0.0; N],
+            rac_autos: [0.0; N],
+            rac_inacts: [0.0; N],
+            rho_acts: [0.0; N],
+            rho_inacts: [0.0; N],
+            inward_forces: [0.0; N],
+        }
+    }
+}
+
+impl<const N: usize> SourceFactors<N> {
+    /// Add factors `xs`, which have effect `effect`.
+    pub fn add(&mut self, effect: SourceEffect, xs: &[f64; N]) {
+        let sum = match effect {
+            SourceEffect::RacActivation => &mut self.rac_acts,
+            SourceEffect::RacAutoActivation => &mut self.rac_autos,
+            SourceEffect::RacInactivation => &mut self.rac_inacts,
+            SourceEffect::RhoActivation => &mut self.rho_acts,
+            SourceEffect::RhoInactivation => &mut self.rho_inacts,
+            SourceEffect::InwardForce => &mut self.inward_forces,
+        };
+        sum.iter_mut().zip(xs.iter()).for_each(|(s, x)| *s += x);
+    }
+}
+
+/// What an `InteractionSource` may use to generate the factors of a
+/// cell.
+pub struct SourceContext<'a, const N: usize> {
+    /// Polygons of all cells.
+    pub cell_polys: &'a [Poly<N>],
+    /// Relative Rho GTPase activities at the vertices of all cells.
+    pub rel_rgtps: &'a [[RelativeRgtpActivity; N]],
+    /// Indices, in increasing order, of the cells roughly in contact
+    /// with the cell.
+    pub contacts: &'a [usize],
+}
+
+/// A cue contributing per-vertex factors to cells, alongside the
+/// built-in interactions (CIL, CAL, adhesion, COA, chemoattraction
+/// and boundaries). Sources are registered using
+/// `World::add_interaction_source`.
+///
+/// Factors are only regenerated for cells which may have changed:
+/// the factors of a cell may depend on its own polygon and Rho
+/// GTPase activities, and on those of the cells in contact with it.
+/// A source with longer range dependencies must report the cells it
+/// affects from `update`.
+pub trait InteractionSource<const N: usize>: Send + Sync {
+    /// Name of the source, for example `"haptotaxis"`. Names of
+    /// registered sources are unique.
+    fn name(&self) -> &str;
+
+    /// How the factors of this source affect cells.
+    fn effect(&self) -> SourceEffect;
+
+    /// Called after the polygon of cell `ci` has changed. Returns the
+    /// indices of other cells whose factors may have changed as a
+    /// result.
+    fn update(
+        &mut self,
+        _ci: usize,
+        _cell_polys: &[Poly<N>],
+    ) -> Vec<usize> {
+        vec![]
+    }
+
+    /// Called after a new cell, which is the last of `cell_polys`,
+    /// has been added.
+    fn add_cell(&mut self, _cell_polys: &[Poly<N>]) {}
+
+    /// Called after cell `ci` has been removed. Cells with greater
+    /// indices have had their indices shifted down by one.
+    fn remove_cell(&mut self, _ci: usize) {}
+
+    /// Factors at the vertices of cell `ci`.
+    fn generate(&self, ci: usize, ctx: &SourceContext<N>)
+        -> [f64; N];
+
+    fn box_clone(&self) -> Box<dyn InteractionSource<N>>;
+}
+
+impl<const N: usize> Clone for Box<dyn InteractionSource<N>> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
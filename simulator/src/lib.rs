@@ -19,11 +19,32 @@ use std::fs::{create_dir_all, OpenOptions};
 use std::io::Read;
 use std::path::PathBuf;
 
-/// Number of vertices per model cell.
-pub const NVERTS: usize = 16;
+/// Number of vertices per model cell, if an experiment does not
+/// specify it.
+pub const DEFAULT_NVERTS: usize = 16;
+/// Numbers of vertices per model cell which simulations can be run
+/// with. Cell types are generic over the number of vertices `N`, and
+/// `with_nverts` instantiates them for each of these.
+pub const SUPPORTED_NVERTS: [usize; 4] = [8, 16, 32, 64];
 /// Default directory where simulation output will be placed.
 pub const DEFAULT_OUTPUT_DIR: &str = "B:\\rust-ncc\\output";
 
+/// Call `$f::<N>($args...)`, where `N` is the runtime value
+/// `$nverts`. Evaluates to `Ok` of the result if `$nverts` is one of
+/// `SUPPORTED_NVERTS`, and `Err($nverts)` otherwise.
+#[macro_export]
+macro_rules! with_nverts {
+    ($nverts:expr, $f:ident($($arg:expr),* $(,)?)) => {
+        match $nverts {
+            8 => Ok($f::<8>($($arg),*)),
+            16 => Ok($f::<16>($($arg),*)),
+            32 => Ok($f::<32>($($arg),*)),
+            64 => Ok($f::<64>($($arg),*)),
+            n => Err(n),
+        }
+    };
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Directories {
     pub out: PathBuf,
@@ -12,8 +12,8 @@ use crate::math::{
     InUnitInterval,
 };
 use crate::utils::{circ_ix_minus, circ_ix_plus};
-use crate::NVERTS;
 use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
 use std::fmt;
 use std::fmt::Display;
 use std::ops::{Add, Mul};
@@ -21,32 +21,33 @@ use std::ops::{Add, Mul};
 const INTERSECTION_CLOSE_EPS: f64 = 1e-16;
 
 #[derive(Clone, Copy, Deserialize, Serialize)]
-pub struct Poly {
-    pub verts: [V2d; NVERTS],
-    pub edges: [LineSeg2D; NVERTS],
+pub struct Poly<const N: usize> {
+    #[serde(with = "BigArray")]
+    pub verts: [V2d; N],
+    #[serde(with = "BigArray")]
+    pub edges: [LineSeg2D; N],
     pub bbox: BBox,
 }
 
-impl Poly {
-    pub fn gen_edges(verts: &[V2d; NVERTS]) -> [LineSeg2D; NVERTS] {
+impl<const N: usize> Poly<N> {
+    pub fn gen_edges(verts: &[V2d; N]) -> [LineSeg2D; N] {
         let mut edges =
-            [LineSeg2D::new(&V2d::zeros(), &V2d::zeros()); NVERTS];
-        (0..NVERTS).for_each(|vi| {
-            edges[vi]
-                .refresh(&verts[vi], &verts[circ_ix_plus(vi, NVERTS)])
+            [LineSeg2D::new(&V2d::zeros(), &V2d::zeros()); N];
+        (0..N).for_each(|vi| {
+            edges[vi].refresh(&verts[vi], &verts[circ_ix_plus(vi, N)])
         });
         edges
     }
 
-    pub fn gen_verts(edges: &[LineSeg2D; NVERTS]) -> [V2d; NVERTS] {
-        let mut verts = [V2d::default(); NVERTS];
-        (0..NVERTS).for_each(|vi| {
+    pub fn gen_verts(edges: &[LineSeg2D; N]) -> [V2d; N] {
+        let mut verts = [V2d::default(); N];
+        (0..N).for_each(|vi| {
             verts[vi] = edges[vi].p0;
         });
         verts
     }
 
-    pub fn from_verts(verts: &[V2d; NVERTS]) -> Poly {
+    pub fn from_verts(verts: &[V2d; N]) -> Poly<N> {
         let bbox = BBox::from_points(verts);
         let edges = Poly::gen_edges(verts);
         Poly {
@@ -56,7 +57,7 @@ impl Poly {
         }
     }
 
-    pub fn from_edges(edges: &[LineSeg2D; NVERTS]) -> Poly {
+    pub fn from_edges(edges: &[LineSeg2D; N]) -> Poly<N> {
         let verts = Poly::gen_verts(edges);
         let bbox = BBox::from_points(&verts);
         Poly {
@@ -65,6 +66,13 @@ impl Poly {
             bbox,
         }
     }
+
+    /// The polygon moved by `d`.
+    pub fn translate(&self, d: &V2d) -> Poly<N> {
+        let mut verts = self.verts;
+        verts.iter_mut().for_each(|v| *v = *v + *d);
+        Poly::from_verts(&verts)
+    }
 }
 
 /// Calculate the area of a polygon with vertices positioned at `xys`.
@@ -82,6 +90,12 @@ pub fn calc_poly_area(xys: &[V2d]) -> f64 {
     area * 0.5
 }
 
+/// Calculate the centroid of the vertices `xys` of a polygon.
+pub fn calc_poly_centroid(xys: &[V2d]) -> V2d {
+    let sum = xys.iter().fold(V2d::zeros(), |acc, &v| acc + v);
+    sum.scale(1.0 / (xys.len() as f64))
+}
+
 #[derive(
     Copy, Clone, Deserialize, Serialize, PartialEq, Default, Debug,
 )]
@@ -113,6 +127,16 @@ impl BBox {
         }
     }
 
+    /// Smallest box containing both `self` and `other`.
+    pub fn union(&self, other: &BBox) -> BBox {
+        BBox {
+            xmin: self.xmin.min(other.xmin),
+            ymin: self.ymin.min(other.ymin),
+            xmax: self.xmax.max(other.xmax),
+            ymax: self.ymax.max(other.ymax),
+        }
+    }
+
     #[inline]
     pub fn expand_by(&self, l: f64) -> BBox {
         BBox {
@@ -138,6 +162,101 @@ impl BBox {
             && point.y > self.ymin - eps
             && point.y < self.ymax + eps
     }
+
+    pub fn center(&self) -> V2d {
+        V2d {
+            x: 0.5 * (self.xmin + self.xmax),
+            y: 0.5 * (self.ymin + self.ymax),
+        }
+    }
+
+    /// The box moved by `d`.
+    pub fn translate(&self, d: &V2d) -> BBox {
+        BBox {
+            xmin: self.xmin + d.x,
+            ymin: self.ymin + d.y,
+            xmax: self.xmax + d.x,
+            ymax: self.ymax + d.y,
+        }
+    }
+}
+
+/// Rectangular domain with periodic boundary conditions: space is
+/// tiled by copies of the domain, and a point has an image in each
+/// copy. Objects interact with the images of others nearest to them
+/// (the minimum image convention), so the domain must be larger
+/// than twice the range of any interaction.
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Debug)]
+pub struct PeriodicDomain {
+    /// Bottom left corner of the domain.
+    pub origin: V2d,
+    /// Width and height of the domain.
+    pub size: V2d,
+}
+
+impl PeriodicDomain {
+    /// Translation taking `p` to its image nearest to `from`.
+    pub fn image_shift(&self, from: &V2d, p: &V2d) -> V2d {
+        let d = *p - *from;
+        V2d {
+            x: -self.size.x * (d.x / self.size.x).round(),
+            y: -self.size.y * (d.y / self.size.y).round(),
+        }
+    }
+
+    /// Translation taking `p` to its image in the domain.
+    pub fn wrap_shift(&self, p: &V2d) -> V2d {
+        let d = *p - self.origin;
+        V2d {
+            x: -self.size.x * (d.x / self.size.x).floor(),
+            y: -self.size.y * (d.y / self.size.y).floor(),
+        }
+    }
+
+    /// The image of `bb` whose center is in the domain, along with
+    /// its images in the eight neighbouring copies of the domain.
+    pub fn bbox_images(&self, bb: &BBox) -> Vec<BBox> {
+        let wrapped = bb.translate(&self.wrap_shift(&bb.center()));
+        let mut r = Vec::with_capacity(9);
+        for i in -1..=1 {
+            for j in -1..=1 {
+                r.push(wrapped.translate(&V2d {
+                    x: i as f64 * self.size.x,
+                    y: j as f64 * self.size.y,
+                }));
+            }
+        }
+        r
+    }
+}
+
+/// The image of `poly` nearest to `p` in `domain`, which is `poly`
+/// itself if there is no periodic domain.
+pub fn poly_image_near<const N: usize>(
+    domain: Option<&PeriodicDomain>,
+    poly: &Poly<N>,
+    p: &V2d,
+) -> Poly<N> {
+    match domain {
+        Some(domain) => poly
+            .translate(&domain.image_shift(p, &poly.bbox.center())),
+        None => *poly,
+    }
+}
+
+/// The image of `bb` nearest to `p` in `domain`, which is `bb` itself
+/// if there is no periodic domain.
+pub fn bbox_image_near(
+    domain: Option<&PeriodicDomain>,
+    bb: &BBox,
+    p: &V2d,
+) -> BBox {
+    match domain {
+        Some(domain) => {
+            bb.translate(&domain.image_shift(p, &bb.center()))
+        }
+        None => *bb,
+    }
 }
 
 impl Display for BBox {
@@ -554,6 +673,19 @@ impl LineSeg2D {
         self.bbox.intersects(bbox)
     }
 
+    /// Squared distance from `p` to the closest point on the line
+    /// segment.
+    pub fn dist_sq_to(&self, p: &V2d) -> f64 {
+        let r = p - &self.p0;
+        let len_sq = self.vector.mag_squared();
+        let t = if close_to_zero(len_sq, INTERSECTION_CLOSE_EPS) {
+            0.0
+        } else {
+            (r.dot(&self.vector) / len_sq).clamp(0.0, 1.0)
+        };
+        (r - self.vector.scale(t)).mag_squared()
+    }
+
     #[inline]
     pub fn mag(&self) -> f64 {
         self.vector.mag()
@@ -0,0 +1,92 @@
+// Copyright © 2020 Brian Merchant.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// Square matrix of size `n`, stored in row-major order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub n: usize,
+    pub data: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn zeros(n: usize) -> Matrix {
+        Matrix {
+            n,
+            data: vec![0.0; n * n],
+        }
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        self.data[i * self.n + j]
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, x: f64) {
+        self.data[i * self.n + j] = x;
+    }
+}
+
+/// LU decomposition of a square matrix, with partial pivoting.
+pub struct Lu {
+    /// Lower (below the diagonal, with implicit unit diagonal) and
+    /// upper (on and above the diagonal) triangular factors.
+    lu: Matrix,
+    /// Row of the original matrix which ended up in each row.
+    perm: Vec<usize>,
+}
+
+impl Lu {
+    /// Decompose `a`. Returns `None` if `a` is singular.
+    pub fn new(mut a: Matrix) -> Option<Lu> {
+        let n = a.n;
+        let mut perm = (0..n).collect::<Vec<usize>>();
+        for k in 0..n {
+            let p = (k..n)
+                .max_by(|&i, &j| {
+                    a.get(i, k).abs().total_cmp(&a.get(j, k).abs())
+                })
+                .unwrap();
+            if a.get(p, k) == 0.0 {
+                return None;
+            }
+            if p != k {
+                for j in 0..n {
+                    a.data.swap(k * n + j, p * n + j);
+                }
+                perm.swap(k, p);
+            }
+            let pivot = a.get(k, k);
+            for i in (k + 1)..n {
+                let l = a.get(i, k) / pivot;
+                a.set(i, k, l);
+                for j in (k + 1)..n {
+                    a.set(i, j, a.get(i, j) - l * a.get(k, j));
+                }
+            }
+        }
+        Some(Lu { lu: a, perm })
+    }
+
+    /// Solve `a x = b`, where `a` is the decomposed matrix.
+    pub fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let n = self.lu.n;
+        let mut x =
+            self.perm.iter().map(|&i| b[i]).collect::<Vec<f64>>();
+        for i in 0..n {
+            for j in 0..i {
+                x[i] -= self.lu.get(i, j) * x[j];
+            }
+        }
+        for i in (0..n).rev() {
+            for j in (i + 1)..n {
+                x[i] -= self.lu.get(i, j) * x[j];
+            }
+            x[i] /= self.lu.get(i, i);
+        }
+        x
+    }
+}
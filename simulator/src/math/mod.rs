@@ -7,6 +7,7 @@
 // except according to those terms.
 
 pub mod geometry;
+pub mod linalg;
 pub mod radians;
 pub mod v2d;
 
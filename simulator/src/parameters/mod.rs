@@ -8,12 +8,11 @@
 
 pub mod quantity;
 use crate::cell::chemistry::RgtpDistribution;
-use crate::math::geometry::{calc_poly_area, BBox};
+use crate::math::geometry::{calc_poly_area, PeriodicDomain};
 use crate::math::v2d::V2d;
 use crate::parameters::quantity::{
     Diffusion, Force, Length, Quantity, Stress, Time, Tinv, Viscosity,
 };
-use crate::NVERTS;
 use modify_derive::Modify;
 use rand_distr::num_traits::Pow;
 use serde::{Deserialize, Serialize};
@@ -138,20 +137,24 @@ pub struct RawCoaParams {
     /// Distance from point of emission at which COA signal reaches half
     /// its maximum value.
     pub halfmax_dist: Length,
-    /// Magnitude of COA. It will be divided by `NVERTS` so that it scales based
-    /// on the number of vertices.
+    /// Magnitude of COA. It will be divided by the number of vertices
+    /// per cell, so that it scales based on the number of vertices.
     pub mag: f64,
     /// If two vertices are within this distance, then COA cannot occur between them.
     pub too_close_dist: Length,
 }
 
 impl RawCoaParams {
-    pub fn refine(&self, bq: &CharQuantities) -> CoaParams {
+    pub fn refine(
+        &self,
+        bq: &CharQuantities,
+        nverts: usize,
+    ) -> CoaParams {
         let halfmax_dist = bq.normalize(&self.halfmax_dist);
         CoaParams {
             los_penalty: self.los_penalty,
             halfmax_dist,
-            vertex_mag: self.mag / NVERTS as f64,
+            vertex_mag: self.mag / nverts as f64,
             // self.mag * exp(distrib_exp * x), where x is distance
             // between points.
             distrib_exp: 0.5f64.ln() / halfmax_dist,
@@ -162,102 +165,486 @@ impl RawCoaParams {
     }
 }
 
+/// Interaction magnitudes for each ordered pair of cell groups, so
+/// that cells may interact with cells of other groups differently
+/// than with cells of their own. `cil[g][og]` is the magnitude of
+/// CIL on a cell of group `g` due to contact with a cell of group
+/// `og`, and similarly for CAL and COA. `adh[g][og]` is the magnitude
+/// of adhesion between a vertex of a cell of group `g` and an edge of
+/// a cell of group `og`. A matrix which is `None` leaves the
+/// corresponding magnitude of `RawPhysicalContactParams` or
+/// `RawCoaParams` to apply to every pair.
 #[derive(
-    Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug,
+    Deserialize, Serialize, Clone, PartialEq, Default, Debug,
 )]
-pub struct RawChemAttrParams {
+pub struct RawGroupPairMags {
+    pub cil: Option<Vec<Vec<f64>>>,
+    pub cal: Option<Vec<Vec<f64>>>,
+    pub adh: Option<Vec<Vec<Force>>>,
+    /// COA magnitudes, which only apply if COA is enabled. Like
+    /// `RawCoaParams::mag`, they will be divided by the number of
+    /// vertices per cell.
+    pub coa: Option<Vec<Vec<f64>>>,
+}
+
+/// Check that the matrix `m` of group pair magnitudes `name`, if
+/// given, has a row and a column for each of `num_groups` groups.
+fn validate_group_pair_matrix<T>(
+    name: &str,
+    m: &Option<Vec<Vec<T>>>,
+    num_groups: usize,
+) -> Result<(), String> {
+    match m {
+        Some(m)
+            if m.len() != num_groups
+                || m.iter().any(|row| row.len() != num_groups) =>
+        {
+            Err(format!(
+                "{} group pair magnitudes must be a {}x{} matrix, one row and column per cell group",
+                name, num_groups, num_groups
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl RawGroupPairMags {
+    /// Check that every matrix has a row and a column for each of
+    /// `num_groups` groups.
+    pub fn validate(&self, num_groups: usize) -> Result<(), String> {
+        validate_group_pair_matrix("cil", &self.cil, num_groups)?;
+        validate_group_pair_matrix("cal", &self.cal, num_groups)?;
+        validate_group_pair_matrix("adh", &self.adh, num_groups)?;
+        validate_group_pair_matrix("coa", &self.coa, num_groups)
+    }
+
+    pub fn refine(
+        &self,
+        bq: &CharQuantities,
+        nverts: usize,
+    ) -> GroupPairMags {
+        GroupPairMags {
+            cil: self.cil.clone(),
+            cal: self.cal.clone(),
+            adh: self.adh.as_ref().map(|m| {
+                m.iter()
+                    .map(|row| {
+                        row.iter().map(|f| bq.normalize(f)).collect()
+                    })
+                    .collect()
+            }),
+            coa: self.coa.as_ref().map(|m| {
+                m.iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|&mag| mag / nverts as f64)
+                            .collect()
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// How the chemoattractant sensed around a source falls off with
+/// distance from it.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum RawChemProfile {
+    /// Drops by `drop_per_char_l` of its value at the source for
+    /// every `char_l` away from it, down to zero.
+    Linear {
+        drop_per_char_l: f64,
+        char_l: Length,
+    },
+    /// Drops by a factor of `e` every `decay_length`.
+    Exponential { decay_length: Length },
+    /// Gaussian with standard deviation `width`.
+    Gaussian { width: Length },
+    /// Receptor saturation: concentration drops exponentially with
+    /// `decay_length`, and the sensed signal is proportional to
+    /// `c / (c + halfmax)`, where `c` is `1` at the source.
+    Saturating { decay_length: Length, halfmax: f64 },
+    /// Grows by `rise_per_char_l` of the source's magnitude for every
+    /// `char_l` away from it. This is the profile of the source at
+    /// `chem_dist` in `NCells` experiments.
+    Proportional {
+        rise_per_char_l: f64,
+        char_l: Length,
+    },
+}
+
+impl RawChemProfile {
+    pub fn refine(&self, bq: &CharQuantities) -> ChemProfile {
+        match *self {
+            RawChemProfile::Linear {
+                drop_per_char_l,
+                char_l,
+            } => ChemProfile::Linear {
+                slope: drop_per_char_l / bq.normalize(&char_l),
+            },
+            RawChemProfile::Exponential { decay_length } => {
+                ChemProfile::Exponential {
+                    decay_length: bq.normalize(&decay_length),
+                }
+            }
+            RawChemProfile::Gaussian { width } => {
+                ChemProfile::Gaussian {
+                    width: bq.normalize(&width),
+                }
+            }
+            RawChemProfile::Saturating {
+                decay_length,
+                halfmax,
+            } => ChemProfile::Saturating {
+                decay_length: bq.normalize(&decay_length),
+                halfmax,
+            },
+            RawChemProfile::Proportional {
+                rise_per_char_l,
+                char_l,
+            } => ChemProfile::Proportional {
+                slope: rise_per_char_l / bq.normalize(&char_l),
+            },
+        }
+    }
+}
+
+/// How the strength of a chemoattractant source changes over time,
+/// as a factor multiplying its magnitude.
+#[derive(
+    Deserialize, Serialize, Clone, PartialEq, Default, Debug,
+)]
+pub enum RawChemSchedule {
+    /// Always at full strength.
+    #[default]
+    Constant,
+    /// At full strength during each of the `[start, end)` periods,
+    /// and off otherwise.
+    OnOff { periods: Vec<[Time; 2]> },
+    /// Changes linearly from `from` at `start` to `to` at `end`,
+    /// staying at `from` before and at `to` after.
+    Ramp {
+        start: Time,
+        end: Time,
+        from: f64,
+        to: f64,
+    },
+}
+
+impl RawChemSchedule {
+    pub fn refine(&self, bq: &CharQuantities) -> ChemSchedule {
+        match self {
+            RawChemSchedule::Constant => ChemSchedule::Constant,
+            RawChemSchedule::OnOff { periods } => {
+                ChemSchedule::OnOff {
+                    periods: periods
+                        .iter()
+                        .map(|p| {
+                            [bq.normalize(&p[0]), bq.normalize(&p[1])]
+                        })
+                        .collect(),
+                }
+            }
+            RawChemSchedule::Ramp {
+                start,
+                end,
+                from,
+                to,
+            } => ChemSchedule::Ramp {
+                start: bq.normalize(start),
+                end: bq.normalize(end),
+                from: *from,
+                to: *to,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct RawChemAttrSource {
     pub center: [Length; 2],
+    /// Chemoattractant a vertex right on top of the source would
+    /// sense, at full strength.
     pub mag: f64,
-    pub drop_per_char_l: f64,
-    pub char_l: Length,
+    pub profile: RawChemProfile,
+    pub schedule: RawChemSchedule,
+}
+
+/// Chemoattractant given by analytic profiles around sources. The
+/// chemoattractant sensed at a vertex is the sum of that from each
+/// source.
+#[derive(
+    Deserialize, Serialize, Clone, PartialEq, Default, Debug,
+)]
+pub struct RawChemAttrParams {
+    pub sources: Vec<RawChemAttrSource>,
 }
 
 impl RawChemAttrParams {
     pub fn refine(&self, bq: &CharQuantities) -> ChemAttrParams {
         ChemAttrParams {
-            center: V2d {
-                x: bq.normalize(&self.center[0]),
-                y: bq.normalize(&self.center[1]),
+            sources: self
+                .sources
+                .iter()
+                .map(|s| ChemAttrSource {
+                    center: V2d {
+                        x: bq.normalize(&s.center[0]),
+                        y: bq.normalize(&s.center[1]),
+                    },
+                    mag: s.mag,
+                    profile: s.profile.refine(bq),
+                    schedule: s.schedule.refine(bq),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Point source of chemoattractant.
+#[derive(
+    Deserialize, Serialize, Clone, PartialEq, Default, Debug,
+)]
+pub struct RawChemSource {
+    pub pos: [Length; 2],
+    /// Rate at which the concentration of the grid square containing
+    /// the source increases, at full strength.
+    pub rate: Tinv,
+    pub schedule: RawChemSchedule,
+}
+
+#[derive(
+    Deserialize, Serialize, Clone, PartialEq, Default, Debug,
+)]
+pub struct RawChemFieldParams {
+    /// Bottom left corner of the grid.
+    pub origin: [Length; 2],
+    /// Side length of a grid square.
+    pub spacing: Length,
+    /// Number of grid squares along the x and y axes.
+    pub shape: [usize; 2],
+    pub diffusion: Diffusion,
+    /// Rate at which chemoattractant decays everywhere.
+    pub decay: Tinv,
+    /// Rate at which chemoattractant is consumed, or degraded, in
+    /// grid squares whose centers are covered by a cell.
+    pub consumption: Tinv,
+    pub sources: Vec<RawChemSource>,
+    /// Chemoattractant signal at a vertex, per unit of concentration.
+    pub mag: f64,
+}
+
+impl RawChemFieldParams {
+    pub fn refine(&self, bq: &CharQuantities) -> ChemFieldParams {
+        ChemFieldParams {
+            origin: V2d {
+                x: bq.normalize(&self.origin[0]),
+                y: bq.normalize(&self.origin[1]),
             },
-            center_mag: self.mag,
-            slope: self.drop_per_char_l / bq.normalize(&self.char_l),
+            spacing: bq.normalize(&self.spacing),
+            shape: self.shape,
+            diffusion: bq.normalize(&self.diffusion),
+            decay: bq.normalize(&self.decay),
+            consumption: bq.normalize(&self.consumption),
+            sources: self
+                .sources
+                .iter()
+                .map(|s| ChemSource {
+                    pos: V2d {
+                        x: bq.normalize(&s.pos[0]),
+                        y: bq.normalize(&s.pos[1]),
+                    },
+                    rate: bq.normalize(&s.rate),
+                    schedule: s.schedule.refine(bq),
+                })
+                .collect(),
+            mag: self.mag,
         }
     }
 }
 
 #[derive(
-    Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug,
+    Deserialize, Serialize, Clone, PartialEq, Default, Debug,
 )]
 pub struct RawBdryParams {
-    shape: [[Length; 2]; 4],
-    skip_bb_check: bool,
-    mag: f64,
+    /// Polygons, given by their vertices, making up the walls of the
+    /// boundary.
+    pub shapes: Vec<Vec<[Length; 2]>>,
+    /// Vertices within this distance of a wall are touching it.
+    pub touch_dist: Length,
+    /// Magnitude of CIL-type interaction.
+    pub mag: f64,
+}
+
+/// Normalize polygons given by their vertices.
+fn refine_shapes(
+    shapes: &[Vec<[Length; 2]>],
+    bq: &CharQuantities,
+) -> Vec<Vec<V2d>> {
+    shapes
+        .iter()
+        .map(|shape| {
+            shape
+                .iter()
+                .map(|p| V2d {
+                    x: bq.normalize(&p[0]),
+                    y: bq.normalize(&p[1]),
+                })
+                .collect::<Vec<V2d>>()
+        })
+        .collect::<Vec<Vec<V2d>>>()
 }
 
 impl RawBdryParams {
     pub fn refine(&self, bq: &CharQuantities) -> BdryParams {
-        let shape = self
-            .shape
-            .iter()
-            .map(|p| V2d {
-                x: bq.normalize(&p[0]),
-                y: bq.normalize(&p[1]),
-            })
-            .collect::<Vec<V2d>>();
-        let bbox = BBox::from_points(&shape);
         BdryParams {
-            shape,
-            bbox,
-            skip_bb_check: self.skip_bb_check,
+            shapes: refine_shapes(&self.shapes, bq),
+            touch_dist: bq.normalize(&self.touch_dist),
             mag: self.mag,
         }
     }
 }
 
+/// Stateful adhesion bonds between a vertex of one cell and an edge
+/// of another. Bonds form stochastically between close vertices and
+/// edges, and rupture at a rate which grows exponentially with the
+/// force they bear (Bell model).
 #[derive(
-    Deserialize,
-    Serialize,
-    Clone,
-    Copy,
-    PartialEq,
-    Default,
-    Debug,
-    Modify,
+    Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug,
+)]
+pub struct RawAdhBondParams {
+    /// A bond may form between a vertex and an edge within this
+    /// distance of it.
+    pub form_dist: Length,
+    /// Rate at which bonds form between a vertex and a close edge.
+    pub form_rate: Tinv,
+    /// Rate at which an unloaded bond ruptures.
+    pub rupture_rate: Tinv,
+    /// Force which increases the rupture rate by a factor of `e`.
+    pub bell_force: Force,
+    /// Bonds stretched beyond this length always rupture.
+    pub max_len: Length,
+}
+
+impl RawAdhBondParams {
+    pub fn refine(&self, bq: &CharQuantities) -> AdhBondParams {
+        AdhBondParams {
+            form_dist: bq.normalize(&self.form_dist),
+            form_rate: bq.normalize(&self.form_rate),
+            rupture_rate: bq.normalize(&self.rupture_rate),
+            bell_force: bq.normalize(&self.bell_force),
+            max_len: bq.normalize(&self.max_len),
+        }
+    }
+}
+
+#[derive(
+    Deserialize, Serialize, Clone, PartialEq, Default, Debug, Modify,
 )]
 pub struct RawInteractionParams {
     pub coa: Option<RawCoaParams>,
     pub chem_attr: Option<RawChemAttrParams>,
+    pub chem_field: Option<RawChemFieldParams>,
     pub bdry: Option<RawBdryParams>,
     pub phys_contact: RawPhysicalContactParams,
+    /// Magnitudes which depend on the groups of interacting cells.
+    /// If `None`, all cells interact the same way.
+    pub group_pair_mags: Option<RawGroupPairMags>,
+    /// Stateful adhesion bonds. If `None`, adhesion is calculated
+    /// from the current distances between cells alone.
+    pub adh_bonds: Option<RawAdhBondParams>,
 }
 
 impl RawInteractionParams {
-    pub fn refine(&self, bq: &CharQuantities) -> InteractionParams {
+    /// Refine the parameters for cells with `nverts` vertices.
+    pub fn refine(
+        &self,
+        bq: &CharQuantities,
+        nverts: usize,
+    ) -> InteractionParams {
         InteractionParams {
-            coa: self.coa.as_ref().map(|coa| coa.refine(bq)),
+            coa: self.coa.as_ref().map(|coa| coa.refine(bq, nverts)),
             chem_attr: self
                 .chem_attr
                 .as_ref()
                 .map(|chem_attr| chem_attr.refine(bq)),
+            chem_field: self
+                .chem_field
+                .as_ref()
+                .map(|chem_field| chem_field.refine(bq)),
             bdry: self.bdry.as_ref().map(|bdry| bdry.refine(bq)),
             phys_contact: self.phys_contact.refine(bq),
+            group_pair_mags: self
+                .group_pair_mags
+                .as_ref()
+                .map(|mags| mags.refine(bq, nverts)),
+            adh_bonds: self.adh_bonds.map(|ab| ab.refine(bq)),
         }
     }
 }
 
+/// Static obstacles, such as pillars or posts, which cells may not
+/// enter.
 #[derive(
-    Deserialize,
-    Serialize,
-    Copy,
-    Clone,
-    PartialEq,
-    Default,
-    Debug,
-    Modify,
+    Deserialize, Serialize, Clone, PartialEq, Default, Debug,
+)]
+pub struct RawObstacleParams {
+    /// Polygons, given by their vertices, making up the obstacles.
+    pub shapes: Vec<Vec<[Length; 2]>>,
+    /// Vertices within this distance of an obstacle are touching it.
+    pub touch_dist: Length,
+    /// Magnitude of CIL-type interaction at vertices touching an
+    /// obstacle. If `None`, obstacles only exclude volume.
+    pub cil_mag: Option<f64>,
+}
+
+impl RawObstacleParams {
+    pub fn refine(&self, bq: &CharQuantities) -> ObstacleParams {
+        ObstacleParams {
+            shapes: refine_shapes(&self.shapes, bq),
+            touch_dist: bq.normalize(&self.touch_dist),
+            cil_mag: self.cil_mag,
+        }
+    }
+}
+
+/// Periodic domain: a rectangle whose opposite sides are identified,
+/// so that cells interact with the nearest images of each other.
+#[derive(
+    Deserialize, Serialize, Clone, Copy, PartialEq, Default, Debug,
+)]
+pub struct RawPeriodicDomain {
+    /// Bottom left corner of the domain.
+    pub origin: [Length; 2],
+    /// Width and height of the domain.
+    pub size: [Length; 2],
+}
+
+impl RawPeriodicDomain {
+    pub fn refine(&self, bq: &CharQuantities) -> PeriodicDomain {
+        PeriodicDomain {
+            origin: V2d {
+                x: bq.normalize(&self.origin[0]),
+                y: bq.normalize(&self.origin[1]),
+            },
+            size: V2d {
+                x: bq.normalize(&self.size[0]),
+                y: bq.normalize(&self.size[1]),
+            },
+        }
+    }
+}
+
+#[derive(
+    Deserialize, Serialize, Clone, PartialEq, Default, Debug, Modify,
 )]
 pub struct RawWorldParameters {
     pub vertex_eta: Viscosity,
     pub interactions: RawInteractionParams,
+    /// Static obstacles. If `None`, there are none.
+    pub obstacles: Option<RawObstacleParams>,
+    /// Periodic domain. If `None`, space is unbounded. Walls,
+    /// obstacles and chemoattractant are not periodic.
+    pub domain: Option<RawPeriodicDomain>,
 }
 
 #[derive(
@@ -310,31 +697,224 @@ pub struct CoaParams {
     pub too_close_dist_sq: f64,
 }
 
+/// See `RawGroupPairMags`. Matrices are indexed by group index. A
+/// pair of groups which lies outside a matrix (which
+/// `RawGroupPairMags::validate` rules out for generated experiments)
+/// is treated as if the matrix were not given.
+#[derive(
+    Clone, Deserialize, Serialize, PartialEq, Default, Debug,
+)]
+pub struct GroupPairMags {
+    pub cil: Option<Vec<Vec<f64>>>,
+    pub cal: Option<Vec<Vec<f64>>>,
+    pub adh: Option<Vec<Vec<f64>>>,
+    /// COA magnitudes per vertex (see `CoaParams::vertex_mag`).
+    pub coa: Option<Vec<Vec<f64>>>,
+}
+
+/// Entry `(g, og)` of the matrix `m`, if `m` is given and has it.
+fn group_pair_entry(
+    m: &Option<Vec<Vec<f64>>>,
+    g: usize,
+    og: usize,
+) -> Option<f64> {
+    m.as_ref()?.get(g)?.get(og).copied()
+}
+
+impl GroupPairMags {
+    /// CIL magnitude on a cell of group `g` due to a cell of group
+    /// `og`, if given.
+    pub fn cil(&self, g: usize, og: usize) -> Option<f64> {
+        group_pair_entry(&self.cil, g, og)
+    }
+
+    /// CAL magnitude on a cell of group `g` due to a cell of group
+    /// `og`, if given.
+    pub fn cal(&self, g: usize, og: usize) -> Option<f64> {
+        group_pair_entry(&self.cal, g, og)
+    }
+
+    /// Adhesion magnitude between a vertex of a cell of group `g`
+    /// and an edge of a cell of group `og`, if given.
+    pub fn adh(&self, g: usize, og: usize) -> Option<f64> {
+        group_pair_entry(&self.adh, g, og)
+    }
+
+    /// COA magnitude per vertex on a cell of group `g` due to a cell
+    /// of group `og`, if given.
+    pub fn coa(&self, g: usize, og: usize) -> Option<f64> {
+        group_pair_entry(&self.coa, g, og)
+    }
+}
+
+/// See `RawChemProfile`.
 #[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Debug)]
-pub struct ChemAttrParams {
-    /// Location of the chemoattractant center.
+pub enum ChemProfile {
+    Linear { slope: f64 },
+    Exponential { decay_length: f64 },
+    Gaussian { width: f64 },
+    Saturating { decay_length: f64, halfmax: f64 },
+    Proportional { slope: f64 },
+}
+
+impl ChemProfile {
+    /// Fraction of the chemoattractant at the source which is sensed
+    /// at distance `d` from it.
+    pub fn at(&self, d: f64) -> f64 {
+        match *self {
+            ChemProfile::Linear { slope } => {
+                (1.0 - slope * d).max(0.0)
+            }
+            ChemProfile::Exponential { decay_length } => {
+                (-d / decay_length).exp()
+            }
+            ChemProfile::Gaussian { width } => {
+                (-d * d / (2.0 * width * width)).exp()
+            }
+            ChemProfile::Saturating {
+                decay_length,
+                halfmax,
+            } => {
+                let c = (-d / decay_length).exp();
+                c * (1.0 + halfmax) / (c + halfmax)
+            }
+            ChemProfile::Proportional { slope } => slope * d,
+        }
+    }
+}
+
+/// See `RawChemSchedule`.
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub enum ChemSchedule {
+    Constant,
+    OnOff {
+        periods: Vec<[f64; 2]>,
+    },
+    Ramp {
+        start: f64,
+        end: f64,
+        from: f64,
+        to: f64,
+    },
+}
+
+impl ChemSchedule {
+    /// Strength of a source at time `t`.
+    pub fn strength(&self, t: f64) -> f64 {
+        match self {
+            ChemSchedule::Constant => 1.0,
+            ChemSchedule::OnOff { periods } => {
+                if periods.iter().any(|p| p[0] <= t && t < p[1]) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ChemSchedule::Ramp {
+                start,
+                end,
+                from,
+                to,
+            } => {
+                let s = if t <= *start {
+                    0.0
+                } else if t >= *end {
+                    1.0
+                } else {
+                    (t - start) / (end - start)
+                };
+                from + s * (to - from)
+            }
+        }
+    }
+
+    pub fn is_constant(&self) -> bool {
+        matches!(self, ChemSchedule::Constant)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct ChemAttrSource {
+    /// Location of the source.
     pub center: V2d,
-    /// Magnitude of chemoattractant a cell would sense if it were
-    /// right on top of the chemoattractant source.
-    pub center_mag: f64,
-    /// Assuming shallow chemoattractant gradient, which can be
-    /// modelled using a linear function with slope `slope`.
-    pub slope: f64,
+    /// Magnitude of chemoattractant a vertex would sense if it were
+    /// right on top of the source, at full strength.
+    pub mag: f64,
+    pub profile: ChemProfile,
+    pub schedule: ChemSchedule,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct ChemAttrParams {
+    pub sources: Vec<ChemAttrSource>,
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct ChemSource {
+    pub pos: V2d,
+    pub rate: f64,
+    pub schedule: ChemSchedule,
+}
+
+/// Chemoattractant which diffuses on a grid of squares, and decays.
+/// See `ChemField::step`.
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct ChemFieldParams {
+    /// Bottom left corner of the grid.
+    pub origin: V2d,
+    /// Side length of a grid square.
+    pub spacing: f64,
+    /// Number of grid squares along the x and y axes.
+    pub shape: [usize; 2],
+    /// Diffusion coefficient of chemoattractant.
+    pub diffusion: f64,
+    /// Rate of decay of chemoattractant.
+    pub decay: f64,
+    /// Rate of consumption of chemoattractant by cells.
+    pub consumption: f64,
+    /// Point sources of chemoattractant. Sources outside of the grid
+    /// are ignored.
+    pub sources: Vec<ChemSource>,
+    /// Chemoattractant signal at a vertex, per unit of
+    /// concentration.
+    pub mag: f64,
 }
 
 #[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 pub struct BdryParams {
-    /// Shape of the boundary.
-    pub shape: Vec<V2d>,
-    /// Bounding box of the boundary.
-    pub bbox: BBox,
-    /// Should boundary bounding box be checked to see if cell is
-    /// within the boundary?
-    pub skip_bb_check: bool,
-    /// Magnitude of CIL-type interaction.
+    /// Polygons making up the walls of the boundary. Cells may not
+    /// cross the edges of a wall.
+    pub shapes: Vec<Vec<V2d>>,
+    /// Vertices within this distance of a wall are touching it.
+    pub touch_dist: f64,
+    /// Magnitude of CIL-type interaction at vertices touching a
+    /// wall.
     pub mag: f64,
 }
 
+/// See `RawAdhBondParams`.
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Debug)]
+pub struct AdhBondParams {
+    pub form_dist: f64,
+    pub form_rate: f64,
+    pub rupture_rate: f64,
+    pub bell_force: f64,
+    pub max_len: f64,
+}
+
+/// See `RawObstacleParams`.
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
+pub struct ObstacleParams {
+    /// Polygons making up the obstacles. Cells may not cross their
+    /// edges.
+    pub shapes: Vec<Vec<V2d>>,
+    /// Vertices within this distance of an obstacle are touching it.
+    pub touch_dist: f64,
+    /// Magnitude of CIL-type interaction at vertices touching an
+    /// obstacle, if any.
+    pub cil_mag: Option<f64>,
+}
+
 #[derive(
     Clone, Deserialize, Serialize, PartialEq, Default, Debug,
 )]
@@ -342,7 +922,10 @@ pub struct InteractionParams {
     pub phys_contact: PhysicalContactParams,
     pub coa: Option<CoaParams>,
     pub chem_attr: Option<ChemAttrParams>,
+    pub chem_field: Option<ChemFieldParams>,
     pub bdry: Option<BdryParams>,
+    pub group_pair_mags: Option<GroupPairMags>,
+    pub adh_bonds: Option<AdhBondParams>,
 }
 
 #[derive(
@@ -353,20 +936,36 @@ pub struct WorldParameters {
     /// vertex due to calculated forces on it.
     pub vertex_eta: f64,
     pub interactions: InteractionParams,
+    /// Static obstacles, if any. Since world parameters are part of
+    /// `WorldInfo`, their geometry is saved with the simulation's
+    /// output.
+    pub obstacles: Option<ObstacleParams>,
+    /// Periodic domain, if any.
+    pub domain: Option<PeriodicDomain>,
 }
 
 impl RawWorldParameters {
-    pub fn refine(&self, bq: &CharQuantities) -> WorldParameters {
+    /// Refine the parameters for cells with `nverts` vertices.
+    pub fn refine(
+        &self,
+        bq: &CharQuantities,
+        nverts: usize,
+    ) -> WorldParameters {
         WorldParameters {
             vertex_eta: bq.normalize(&self.vertex_eta),
-            interactions: self.interactions.refine(bq),
+            interactions: self.interactions.refine(bq, nverts),
+            obstacles: self
+                .obstacles
+                .as_ref()
+                .map(|obstacles| obstacles.refine(bq)),
+            domain: self.domain.map(|domain| domain.refine(bq)),
         }
     }
 }
 
 /// The "raw", unprocessed, parameters that are supplied by the user.
 #[derive(Clone, Copy, Modify)]
-pub struct RawParameters {
+pub struct RawParameters<const N: usize> {
     /// Cell diameter.
     pub cell_diam: Length,
     /// Fraction of max force achieved at `rgtp_act_at_max_f`.
@@ -386,9 +985,9 @@ pub struct RawParameters {
     /// Diffusion rate of Rho GTPase on membrane.
     pub diffusion_rgtp: Diffusion,
     /// Initial distribution of Rac1.
-    pub init_rac: RgtpDistribution,
+    pub init_rac: RgtpDistribution<N>,
     /// Initial distribution of RhoA.
-    pub init_rho: RgtpDistribution,
+    pub init_rho: RgtpDistribution<N>,
     /// Baseline Rac1 activation rate.
     pub kgtp_rac: Tinv,
     /// Rac1 auto-activation rate.
@@ -429,7 +1028,7 @@ pub struct RawParameters {
 #[derive(
     Copy, Clone, Deserialize, Serialize, Default, Debug, PartialEq,
 )]
-pub struct Parameters {
+pub struct Parameters<const N: usize> {
     /// Resting cell radius.
     pub cell_r: f64,
     /// Resting edge length.
@@ -451,9 +1050,9 @@ pub struct Parameters {
     /// Diffusion rate of Rho GTPase on membrane.
     pub diffusion_rgtp: f64,
     /// Initial distribution of Rac1.
-    pub init_rac: RgtpDistribution,
+    pub init_rac: RgtpDistribution<N>,
     /// Initial distribution of RhoA.
-    pub init_rho: RgtpDistribution,
+    pub init_rho: RgtpDistribution<N>,
     /// Halfmax Rho GTPase activity per vertex.
     pub halfmax_vertex_rgtp: f64,
     /// Halfmax Rho GTPase activity per vertex as concentration.
@@ -490,25 +1089,145 @@ pub struct Parameters {
     pub num_rand_vs: u32,
 }
 
-impl RawParameters {
-    pub fn refine(&self, bq: &CharQuantities) -> Parameters {
+/// Numeric fields of `RawParameters`, which may vary between the
+/// cells of a group.
+#[derive(
+    Deserialize,
+    Serialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Debug,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CellParam {
+    CellDiam,
+    HalfmaxRgtpMaxFFrac,
+    StiffnessCortex,
+    LmH,
+    HalfmaxRgtpFrac,
+    LmSs,
+    RhoFriction,
+    StiffnessCyto,
+    DiffusionRgtp,
+    KgtpRac,
+    KgtpRacAuto,
+    KdgtpRac,
+    KdgtpRhoOnRac,
+    HalfmaxTensionInhib,
+    TensionInhib,
+    KMemOff,
+    KMemOn,
+    KgtpRho,
+    KgtpAutoRho,
+    KdgtpRho,
+    KdgtpRacOnRho,
+    RandAvgT,
+    RandStdT,
+    RandMag,
+    RandVs,
+}
+
+impl<const N: usize> RawParameters<N> {
+    /// Scale the field `param` by `factor`.
+    pub fn scale_param(
+        mut self,
+        param: CellParam,
+        factor: f64,
+    ) -> RawParameters<N> {
+        match param {
+            CellParam::CellDiam => {
+                self.cell_diam = self.cell_diam.scale(factor)
+            }
+            CellParam::HalfmaxRgtpMaxFFrac => {
+                self.halfmax_rgtp_max_f_frac *= factor
+            }
+            CellParam::StiffnessCortex => {
+                self.stiffness_cortex =
+                    self.stiffness_cortex.scale(factor)
+            }
+            CellParam::LmH => self.lm_h = self.lm_h.scale(factor),
+            CellParam::HalfmaxRgtpFrac => {
+                self.halfmax_rgtp_frac *= factor
+            }
+            CellParam::LmSs => self.lm_ss = self.lm_ss.scale(factor),
+            CellParam::RhoFriction => self.rho_friction *= factor,
+            CellParam::StiffnessCyto => {
+                self.stiffness_cyto =
+                    self.stiffness_cyto.scale(factor)
+            }
+            CellParam::DiffusionRgtp => {
+                self.diffusion_rgtp =
+                    self.diffusion_rgtp.scale(factor)
+            }
+            CellParam::KgtpRac => {
+                self.kgtp_rac = self.kgtp_rac.scale(factor)
+            }
+            CellParam::KgtpRacAuto => {
+                self.kgtp_rac_auto = self.kgtp_rac_auto.scale(factor)
+            }
+            CellParam::KdgtpRac => {
+                self.kdgtp_rac = self.kdgtp_rac.scale(factor)
+            }
+            CellParam::KdgtpRhoOnRac => {
+                self.kdgtp_rho_on_rac =
+                    self.kdgtp_rho_on_rac.scale(factor)
+            }
+            CellParam::HalfmaxTensionInhib => {
+                self.halfmax_tension_inhib *= factor
+            }
+            CellParam::TensionInhib => self.tension_inhib *= factor,
+            CellParam::KMemOff => {
+                self.k_mem_off = self.k_mem_off.scale(factor)
+            }
+            CellParam::KMemOn => {
+                self.k_mem_on = self.k_mem_on.scale(factor)
+            }
+            CellParam::KgtpRho => {
+                self.kgtp_rho = self.kgtp_rho.scale(factor)
+            }
+            CellParam::KgtpAutoRho => {
+                self.kgtp_auto_rho = self.kgtp_auto_rho.scale(factor)
+            }
+            CellParam::KdgtpRho => {
+                self.kdgtp_rho = self.kdgtp_rho.scale(factor)
+            }
+            CellParam::KdgtpRacOnRho => {
+                self.kdgtp_rac_on_rho =
+                    self.kdgtp_rac_on_rho.scale(factor)
+            }
+            CellParam::RandAvgT => {
+                self.rand_avg_t = self.rand_avg_t.scale(factor)
+            }
+            CellParam::RandStdT => {
+                self.rand_std_t = self.rand_std_t.scale(factor)
+            }
+            CellParam::RandMag => self.rand_mag *= factor,
+            CellParam::RandVs => self.rand_vs *= factor,
+        }
+        self
+    }
+
+    pub fn refine(&self, bq: &CharQuantities) -> Parameters<N> {
         let cell_r = self.cell_diam.scale(0.5);
-        let rel = self.cell_diam.scale((PI / (NVERTS as f64)).sin());
+        let rel = self.cell_diam.scale((PI / (N as f64)).sin());
         let ra = Length(1.0)
             .pow(2.0)
-            .scale(calc_init_cell_area(cell_r.number()));
+            .scale(calc_init_cell_area(cell_r.number(), N));
         let const_protrusive =
             (self.lm_h.g() * self.lm_ss.g() * rel.g())
                 .scale(self.halfmax_rgtp_max_f_frac);
         let const_retractive =
             const_protrusive.scale(self.rho_friction);
-        let halfmax_vertex_rgtp =
-            self.halfmax_rgtp_frac / NVERTS as f64;
+        let halfmax_vertex_rgtp = self.halfmax_rgtp_frac / N as f64;
         let halfmax_vertex_rgtp_conc =
             rel.pow(-1.0).scale(halfmax_vertex_rgtp);
         let stiffness_edge = self.stiffness_cortex.g() * bq.l3d.g();
         let stiffness_cyto =
-            self.stiffness_cyto.g().scale(1.0 / NVERTS as f64);
+            self.stiffness_cyto.g().scale(1.0 / N as f64);
 
         Parameters {
             cell_r: bq.normalize(&cell_r),
@@ -518,8 +1237,7 @@ impl RawParameters {
             const_protrusive: bq.normalize(&const_protrusive),
             const_retractive: bq.normalize(&const_retractive),
             stiffness_cyto: bq.normalize(&stiffness_cyto),
-            k_mem_on_vertex: bq.normalize(&self.k_mem_on)
-                / NVERTS as f64,
+            k_mem_on_vertex: bq.normalize(&self.k_mem_on) / N as f64,
             k_mem_off: bq.normalize(&self.k_mem_off),
             diffusion_rgtp: bq.normalize(&self.diffusion_rgtp),
             init_rac: self.init_rac,
@@ -541,17 +1259,17 @@ impl RawParameters {
             rand_avg_t: bq.normalize(&self.rand_avg_t).ceil(),
             rand_std_t: bq.normalize(&self.rand_std_t).ceil(),
             rand_mag: self.rand_mag,
-            num_rand_vs: (self.rand_vs * NVERTS as f64) as u32,
+            num_rand_vs: (self.rand_vs * N as f64) as u32,
         }
     }
 }
 
 /// Calculate the area of an "ideal" initial cell of radius R, if it has
-/// `NVERTS`  vertices.
-pub fn calc_init_cell_area(r: f64) -> f64 {
-    let poly_coords = (0..NVERTS)
+/// `nverts`  vertices.
+pub fn calc_init_cell_area(r: f64, nverts: usize) -> f64 {
+    let poly_coords = (0..nverts)
         .map(|vix| {
-            let theta = (vix as f64) / (NVERTS as f64) * 2.0 * PI;
+            let theta = (vix as f64) / (nverts as f64) * 2.0 * PI;
             V2d {
                 x: r * theta.cos(),
                 y: r * theta.sin(),
@@ -1,8 +1,7 @@
 use crate::math::round;
-use crate::NVERTS;
 
-pub fn stringify_f64_arr(
-    arr: &[f64; NVERTS],
+pub fn stringify_f64_arr<const N: usize>(
+    arr: &[f64; N],
     round_to: u32,
 ) -> String {
     arr.iter()
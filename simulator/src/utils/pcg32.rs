@@ -72,6 +72,14 @@ impl Lcg64Xsh32 {
     }
 }
 
+/// Uses the default state and stream specified by PCG (see
+/// `Lcg64Xsh32::new`).
+impl Default for Lcg64Xsh32 {
+    fn default() -> Self {
+        Lcg64Xsh32::new(0xcafef00dd15ea5e5, 0xa02bdbf7bb3c0a7)
+    }
+}
+
 // Custom Debug implementation that does not expose the internal state
 impl fmt::Debug for Lcg64Xsh32 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
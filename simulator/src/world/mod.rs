@@ -1,4 +1,8 @@
+pub mod observer;
+pub mod population;
 pub mod py_comp;
+pub mod recovery;
+pub mod stop_conditions;
 
 // Copyright © 2020 Brian Merchant.
 //
@@ -7,34 +11,55 @@ pub mod py_comp;
 // <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
-use crate::cell::states::Core;
+use crate::cell::dense::{DenseOutput, Segment};
+use crate::cell::remesh::RemeshOpts;
+use crate::cell::rkdp5::IntStats;
+use crate::cell::states::{
+    confirm_volume_exclusion, Core, VolExViolation,
+};
 use crate::cell::Cell;
 use crate::exp_setup::{CellGroup, Experiment};
-use crate::hardio::AsyncWriter;
+use crate::hardio::{AsyncWriter, WorldSnapshot};
+use crate::interactions::source::InteractionSource;
 use crate::interactions::{
-    InteractionGenerator, Interactions, RelativeRgtpActivity,
+    ContactData, Contacts, InteractionGenerator, Interactions,
+    RelativeRgtpActivity,
 };
+use crate::math::geometry::{calc_poly_centroid, Poly};
 use crate::math::v2d::V2d;
 use crate::parameters::quantity::Quantity;
 use crate::parameters::{
     CharQuantities, Parameters, WorldParameters,
 };
 use crate::utils::pcg32::Pcg32;
+use crate::world::observer::SimObserver;
+use crate::world::population::{
+    LineageEvent, PopulationRules, RawPopulationRules, RemovalCause,
+};
 use crate::world::py_comp::execute_py_model;
-use crate::NVERTS;
+use crate::world::recovery::{Recovery, RecoveryOpts};
+use crate::world::stop_conditions::{
+    RawStopCondition, StopConditions,
+};
 use rand::seq::SliceRandom;
-use rand::{RngCore, SeedableRng};
+use rand::{Rng, RngCore, SeedableRng};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::f64::consts::PI;
 use std::path::PathBuf;
+use std::{error, fmt, io};
 
 #[derive(
     Clone, Deserialize, Serialize, PartialEq, Default, Debug,
 )]
-pub struct WorldCells {
+pub struct WorldCells<const N: usize> {
     pub tpoint: f64,
-    pub cells: Vec<Cell>,
-    pub interactions: Vec<Interactions>,
+    pub cells: Vec<Cell<N>>,
+    pub interactions: Vec<Interactions<N>>,
+    /// Integrator statistics for each cell (by index), from every
+    /// time step since state was last saved. Only the RKDP5
+    /// integrator records statistics.
+    pub int_stats: Vec<Vec<IntStats>>,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
@@ -73,11 +98,55 @@ impl Default for RkOpts {
     }
 }
 
+/// Strength of the noise added by stochastic integrators, in
+/// normalized units per square root of normalized time.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq,
+)]
+pub struct NoiseOpts {
+    /// Standard deviation of the additive noise on each vertex
+    /// coordinate.
+    pub vertex_noise: f64,
+    /// Relative strength of the multiplicative noise on Rac1 and RhoA
+    /// activation and deactivation.
+    pub reaction_noise: f64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SdeOpts {
+    pub num_int_steps: usize,
+    pub noise: NoiseOpts,
+}
+
+impl SdeOpts {
+    pub fn euler_opts(&self) -> EulerOpts {
+        EulerOpts {
+            num_int_steps: self.num_int_steps,
+        }
+    }
+}
+
+impl Default for SdeOpts {
+    fn default() -> Self {
+        SdeOpts {
+            num_int_steps: EulerOpts::default().num_int_steps,
+            noise: NoiseOpts::default(),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
 pub enum IntegratorOpts {
     Euler(EulerOpts),
     EulerDebug(EulerOpts),
     Rkdp5(RkOpts),
+    /// Stiff second order Rosenbrock method (see `ros2::integrator`),
+    /// with the same step size control as `Rkdp5`.
+    Ros2(RkOpts),
+    /// Euler-Maruyama method, which adds noise drawn from each cell's
+    /// random number generator to the Euler method (see
+    /// `Core::euler_maruyama_step`).
+    EulerMaruyama(SdeOpts),
 }
 
 impl Default for IntegratorOpts {
@@ -86,28 +155,432 @@ impl Default for IntegratorOpts {
     }
 }
 
-impl WorldCells {
-    fn simulate_rkdp5(
+/// How cells are updated within a time step.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq,
+)]
+pub enum UpdateMode {
+    /// Cells are integrated one after another, in a random order.
+    /// Each cell sees the new state of the cells integrated before
+    /// it.
+    #[default]
+    Sequential,
+    /// Cells are integrated in parallel on a thread pool. Each cell
+    /// sees the state its neighbours had at the start of the time
+    /// step, so cells which move into each other fail the time step
+    /// (see `World::step_recovering`). Not supported by
+    /// `IntegratorOpts::EulerDebug`, which is always sequential.
+    Jacobi,
+}
+
+/// Errors which can end a simulation early.
+#[derive(Debug)]
+pub enum SimError {
+    /// An edge of a cell's polygon intersects another cell.
+    VolEx {
+        tpoint: f64,
+        cell_ix: usize,
+        vert_ix: usize,
+        /// `None` if the other is a wall of the boundary.
+        other_cell_ix: Option<usize>,
+        msg: String,
+    },
+    /// The integrator failed to converge on a cell's next state
+    /// within its maximum number of iterations.
+    NonConvergence {
+        tpoint: f64,
+        cell_ix: usize,
+        num_iters: usize,
+        num_rejections: usize,
+        int_opts: IntegratorOpts,
+    },
+    /// A cell's state failed validation (only checked with the
+    /// `validate` feature).
+    InvalidState {
+        tpoint: f64,
+        cell_ix: usize,
+        msg: String,
+    },
+    /// Failure while writing simulation output.
+    Io(io::Error),
+}
+
+impl SimError {
+    pub fn vol_ex(
+        tpoint: f64,
+        cell_ix: usize,
+        violation: VolExViolation,
+    ) -> SimError {
+        let VolExViolation {
+            vert_ix,
+            other_cell_ix,
+            msg,
+        } = violation;
+        SimError::VolEx {
+            tpoint,
+            cell_ix,
+            vert_ix,
+            other_cell_ix,
+            msg,
+        }
+    }
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::VolEx {
+                tpoint,
+                cell_ix,
+                vert_ix,
+                other_cell_ix,
+                msg,
+            } => {
+                let other = match other_cell_ix {
+                    Some(oci) => format!("cell {}", oci),
+                    None => "boundary".to_string(),
+                };
+                write!(
+                    f,
+                    "tstep: {}\ncell {} (vertex {}) violates volume \
+                    exclusion with {}: {}",
+                    tpoint, cell_ix, vert_ix, other, msg
+                )
+            }
+            SimError::NonConvergence {
+                tpoint,
+                cell_ix,
+                num_iters,
+                num_rejections,
+                int_opts,
+            } => write!(
+                f,
+                "tstep: {}\ncell {}: too many iterations \
+                (num_iters: {}, num_rejections: {}, {:?})",
+                tpoint, cell_ix, num_iters, num_rejections, int_opts
+            ),
+            SimError::InvalidState {
+                tpoint,
+                cell_ix,
+                msg,
+            } => write!(
+                f,
+                "tstep: {}\ncell {} is invalid: {}",
+                tpoint, cell_ix, msg
+            ),
+            SimError::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl error::Error for SimError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SimError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SimError {
+    fn from(e: io::Error) -> Self {
+        SimError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for SimError {
+    fn from(e: bincode::Error) -> Self {
+        match *e {
+            bincode::ErrorKind::Io(e) => SimError::Io(e),
+            e => SimError::Io(io::Error::other(e)),
+        }
+    }
+}
+
+impl From<serde_cbor::Error> for SimError {
+    fn from(e: serde_cbor::Error) -> Self {
+        SimError::Io(io::Error::other(e))
+    }
+}
+
+/// Summary of a simulation which ran to completion.
+#[derive(Clone, Debug)]
+pub struct SimSummary {
+    /// Time point at which the simulation ended.
+    pub tpoint: f64,
+    /// Number of snapshots saved to disk.
+    pub num_saved: usize,
+    /// Why the simulation ended: "done" if `final_t` was reached,
+    /// otherwise a description of the stop condition which was met.
+    pub reason: String,
+}
+
+/// Integrates a cell over a time step using an adaptive step size
+/// method, such as `Cell::simulate_rkdp5`.
+type AdaptiveCellFn<const N: usize> =
+    fn(
+        &Cell<N>,
+        f64,
+        f64,
+        &Interactions<N>,
+        Contacts<N>,
+        &WorldParameters,
+        &Parameters<N>,
+        &mut Pcg32,
+        RkOpts,
+        Option<&mut DenseOutput<N>>,
+    ) -> Result<(Cell<N>, IntStats), SimError>;
+
+/// A cell's state over a time step, from which states between the
+/// start and end of the step are saved (see
+/// `World::save_interpolated`).
+struct CellDense<const N: usize> {
+    /// The cell at the start of the time step.
+    cell: Cell<N>,
+    /// Interactions the cell was integrated against.
+    interactions: Interactions<N>,
+    output: DenseOutput<N>,
+}
+
+impl<const N: usize> WorldCells<N> {
+    /// Integrate all cells over a time step of length `dt`, in
+    /// parallel. Every cell is integrated against the interactions
+    /// and contacts calculated from the state at `tpoint`.
+    ///
+    /// Each cell is given its own random number generator, seeded
+    /// from `rng` in order of cell index, so the result does not
+    /// depend on how the work is scheduled.
+    ///
+    /// If `dense` is true, the dense output of each cell is returned
+    /// too, in order of cell index.
+    ///
+    /// Since cells are not integrated against where their neighbours
+    /// end up, they may move into each other: the new polygons are
+    /// checked against each other once all cells have been
+    /// integrated, and a volume exclusion error is returned for the
+    /// lowest indexed cell which overlaps another.
+    fn simulate_jacobi<F>(
+        &self,
+        tpoint: f64,
+        dt: f64,
+        rng: &mut Pcg32,
+        cell_parameters: &[Parameters<N>],
+        interaction_generator: &mut InteractionGenerator<N>,
+        dense: bool,
+        integrate: F,
+    ) -> Result<(WorldCells<N>, Vec<CellDense<N>>), SimError>
+    where
+        F: Fn(
+                &Cell<N>,
+                &Interactions<N>,
+                Contacts<N>,
+                &mut Pcg32,
+                Option<&mut DenseOutput<N>>,
+            )
+                -> Result<(Cell<N>, Vec<IntStats>), SimError>
+            + Sync,
+    {
+        let contact_data = self
+            .cells
+            .iter()
+            .map(|c| interaction_generator.get_contact_data(c.ix))
+            .collect::<Vec<Contacts<N>>>();
+        let cell_rngs = self
+            .cells
+            .iter()
+            .map(|_| Pcg32::seed_from_u64(rng.next_u64()))
+            .collect::<Vec<Pcg32>>();
+        let results = self
+            .cells
+            .par_iter()
+            .zip(contact_data.into_par_iter())
+            .zip(cell_rngs.into_par_iter())
+            .map(|((cell, contacts), mut cell_rng)| {
+                let interactions = &self.interactions[cell.ix];
+                let mut output = DenseOutput::default();
+                integrate(
+                    cell,
+                    interactions,
+                    contacts,
+                    &mut cell_rng,
+                    dense.then_some(&mut output),
+                )
+                .map(|(new_cell, stats)| {
+                    let cell_dense = CellDense {
+                        cell: *cell,
+                        interactions: *interactions,
+                        output,
+                    };
+                    (new_cell, stats, cell_dense)
+                })
+            })
+            .collect::<Vec<_>>();
+        // Report the error of the lowest indexed failing cell, so
+        // that errors are deterministic too.
+        let results =
+            results.into_iter().collect::<Result<Vec<_>, _>>()?;
+        let mut new_cells = Vec::with_capacity(results.len());
+        let mut int_stats = self.int_stats.clone();
+        let mut dense_cells = vec![];
+        for (cell, stats, cell_dense) in results {
+            interaction_generator.update(cell.ix, &cell.core.poly);
+            int_stats[cell.ix].extend(stats);
+            new_cells.push(cell);
+            if dense {
+                dense_cells.push(cell_dense);
+            }
+        }
+        for cell in new_cells.iter() {
+            confirm_volume_exclusion(
+                &cell.core.poly,
+                &interaction_generator.get_contact_data(cell.ix),
+                "integrated",
+            )
+            .map_err(|v| SimError::vol_ex(tpoint + dt, cell.ix, v))?;
+        }
+        let rel_rgtps = new_cells
+            .iter()
+            .map(|c| {
+                c.core.calc_relative_rgtp_activity(
+                    &cell_parameters[c.ix],
+                )
+            })
+            .collect::<Vec<[RelativeRgtpActivity; N]>>();
+        Ok((
+            WorldCells {
+                tpoint: tpoint + dt,
+                cells: new_cells,
+                interactions: interaction_generator
+                    .generate(&rel_rgtps),
+                int_stats,
+            },
+            dense_cells,
+        ))
+    }
+
+    fn simulate_adaptive_jacobi(
+        &self,
+        tpoint: f64,
+        rng: &mut Pcg32,
+        world_parameters: &WorldParameters,
+        cell_parameters: &[Parameters<N>],
+        interaction_generator: &mut InteractionGenerator<N>,
+        int_opts: RkOpts,
+        substeps: &[usize],
+        dense: bool,
+        integrate: AdaptiveCellFn<N>,
+    ) -> Result<(WorldCells<N>, Vec<CellDense<N>>), SimError> {
+        let dt = 1.0;
+        self.simulate_jacobi(
+            tpoint,
+            dt,
+            rng,
+            cell_parameters,
+            interaction_generator,
+            dense,
+            |cell, interactions, contact_data, cell_rng, output| {
+                cell.simulate_substeps(
+                    tpoint,
+                    dt,
+                    substeps[cell.ix],
+                    output,
+                    |cell, t, h, output| {
+                        integrate(
+                            cell,
+                            t,
+                            h,
+                            interactions,
+                            contact_data.clone(),
+                            world_parameters,
+                            &cell_parameters[cell.ix],
+                            cell_rng,
+                            int_opts,
+                            output,
+                        )
+                        .map(|(cell, stats)| (cell, Some(stats)))
+                    },
+                )
+            },
+        )
+    }
+
+    fn simulate_euler_jacobi(
+        &self,
+        tpoint: f64,
+        rng: &mut Pcg32,
+        world_parameters: &WorldParameters,
+        cell_parameters: &[Parameters<N>],
+        interaction_generator: &mut InteractionGenerator<N>,
+        int_opts: EulerOpts,
+        noise: Option<NoiseOpts>,
+        substeps: &[usize],
+        dense: bool,
+    ) -> Result<(WorldCells<N>, Vec<CellDense<N>>), SimError> {
+        let dt = 1.0;
+        self.simulate_jacobi(
+            tpoint,
+            dt,
+            rng,
+            cell_parameters,
+            interaction_generator,
+            dense,
+            |cell, interactions, contact_data, cell_rng, output| {
+                cell.simulate_substeps(
+                    tpoint,
+                    dt,
+                    substeps[cell.ix],
+                    output,
+                    |cell, t, h, output| {
+                        cell.simulate_euler(
+                            t,
+                            h,
+                            interactions,
+                            contact_data.clone(),
+                            world_parameters,
+                            &cell_parameters[cell.ix],
+                            cell_rng,
+                            int_opts,
+                            noise,
+                            output,
+                        )
+                        .map(|cell| (cell, None))
+                    },
+                )
+            },
+        )
+    }
+
+    fn simulate_adaptive(
         &self,
         tpoint: f64,
         rng: &mut Pcg32,
         world_parameters: &WorldParameters,
-        group_parameters: &[Parameters],
-        interaction_generator: &mut InteractionGenerator,
+        cell_parameters: &[Parameters<N>],
+        interaction_generator: &mut InteractionGenerator<N>,
         int_opts: RkOpts,
-    ) -> Result<WorldCells, String> {
+        substeps: &[usize],
+        dense: bool,
+        integrate: AdaptiveCellFn<N>,
+    ) -> Result<(WorldCells<N>, Vec<CellDense<N>>), SimError> {
         let mut new_cells = self.cells.clone();
         let mut rel_rgtps = new_cells
             .iter()
             .map(|c| {
                 c.core.calc_relative_rgtp_activity(
-                    &group_parameters[c.group_ix],
+                    &cell_parameters[c.ix],
                 )
             })
-            .collect::<Vec<[RelativeRgtpActivity; NVERTS]>>();
+            .collect::<Vec<[RelativeRgtpActivity; N]>>();
         let mut interactions = self.interactions.clone();
+        // Whether `interactions` holds those last generated, which
+        // need not be true at first, for example if a time step was
+        // rolled back.
+        let mut generated = false;
+        let mut int_stats = self.int_stats.clone();
+        let mut dense_cells = vec![];
         let shuffled_cells = {
-            let mut crs = self.cells.iter().collect::<Vec<&Cell>>();
+            let mut crs =
+                self.cells.iter().collect::<Vec<&Cell<N>>>();
             crs.shuffle(rng);
             crs
         };
@@ -118,32 +591,64 @@ impl WorldCells {
             let contact_data =
                 interaction_generator.get_contact_data(ci);
 
-            let new_cell = cells.simulate_rkdp5(
+            let mut output = DenseOutput::default();
+            let (new_cell, stats) = cells.simulate_substeps(
                 tpoint,
                 dt,
-                &interactions[ci],
-                contact_data,
-                world_parameters,
-                &group_parameters[cells.group_ix],
-                rng,
-                int_opts,
+                substeps[ci],
+                dense.then_some(&mut output),
+                |cell, t, h, output| {
+                    integrate(
+                        cell,
+                        t,
+                        h,
+                        &interactions[ci],
+                        contact_data.clone(),
+                        world_parameters,
+                        &cell_parameters[cell.ix],
+                        rng,
+                        int_opts,
+                        output,
+                    )
+                    .map(|(cell, stats)| (cell, Some(stats)))
+                },
             )?;
+            if dense {
+                dense_cells.push(CellDense {
+                    cell: *cells,
+                    interactions: interactions[ci],
+                    output,
+                });
+            }
 
             rel_rgtps[ci] =
                 new_cell.core.calc_relative_rgtp_activity(
-                    &group_parameters[new_cell.group_ix],
+                    &cell_parameters[new_cell.ix],
                 );
             interaction_generator.update(ci, &new_cell.core.poly);
-            interactions = interaction_generator.generate(&rel_rgtps);
+            if generated {
+                interaction_generator
+                    .generate_into(&rel_rgtps, &mut interactions);
+            } else {
+                interactions =
+                    interaction_generator.generate(&rel_rgtps);
+                generated = true;
+            }
 
             new_cells[ci] = new_cell;
+            int_stats[ci].extend(stats);
         }
         // println!("-----------------------");
-        Ok(WorldCells {
-            tpoint: tpoint + dt,
-            cells: new_cells,
-            interactions,
-        })
+        dense_cells.sort_by_key(|d| d.cell.ix);
+        Ok((
+            WorldCells {
+                tpoint: tpoint + dt,
+                cells: new_cells,
+                interactions,
+                int_stats,
+            },
+            dense_cells,
+        ))
     }
 
     fn simulate_euler(
@@ -151,13 +656,18 @@ impl WorldCells {
         tpoint: f64,
         rng: &mut Pcg32,
         world_parameters: &WorldParameters,
-        group_parameters: &[Parameters],
-        interaction_generator: &mut InteractionGenerator,
+        cell_parameters: &[Parameters<N>],
+        interaction_generator: &mut InteractionGenerator<N>,
         int_opts: EulerOpts,
-    ) -> Result<WorldCells, String> {
+        noise: Option<NoiseOpts>,
+        substeps: &[usize],
+        dense: bool,
+    ) -> Result<(WorldCells<N>, Vec<CellDense<N>>), SimError> {
         let mut new_cells = self.cells.clone();
+        let mut dense_cells = vec![];
         let shuffled_cells = {
-            let mut crs = self.cells.iter().collect::<Vec<&Cell>>();
+            let mut crs =
+                self.cells.iter().collect::<Vec<&Cell<N>>>();
             crs.shuffle(rng);
             crs
         };
@@ -166,15 +676,35 @@ impl WorldCells {
             let contact_data =
                 interaction_generator.get_contact_data(ci);
 
-            let new_cell = cell.simulate_euler(
+            let mut output = DenseOutput::default();
+            let (new_cell, _) = cell.simulate_substeps(
                 tpoint,
-                &self.interactions[ci],
-                contact_data,
-                world_parameters,
-                &group_parameters[cell.group_ix],
-                rng,
-                int_opts,
+                1.0,
+                substeps[ci],
+                dense.then_some(&mut output),
+                |cell, t, h, output| {
+                    cell.simulate_euler(
+                        t,
+                        h,
+                        &self.interactions[ci],
+                        contact_data.clone(),
+                        world_parameters,
+                        &cell_parameters[cell.ix],
+                        rng,
+                        int_opts,
+                        noise,
+                        output,
+                    )
+                    .map(|cell| (cell, None))
+                },
             )?;
+            if dense {
+                dense_cells.push(CellDense {
+                    cell: *cell,
+                    interactions: self.interactions[ci],
+                    output,
+                });
+            }
 
             interaction_generator.update(ci, &new_cell.core.poly);
 
@@ -184,24 +714,30 @@ impl WorldCells {
             .iter()
             .map(|c| {
                 c.core.calc_relative_rgtp_activity(
-                    &group_parameters[c.group_ix],
+                    &cell_parameters[c.ix],
                 )
             })
-            .collect::<Vec<[RelativeRgtpActivity; NVERTS]>>();
-        Ok(WorldCells {
-            tpoint: tpoint + 1.0,
-            cells: new_cells,
-            interactions: interaction_generator.generate(&rel_rgtps),
-        })
+            .collect::<Vec<[RelativeRgtpActivity; N]>>();
+        dense_cells.sort_by_key(|d| d.cell.ix);
+        Ok((
+            WorldCells {
+                tpoint: tpoint + 1.0,
+                cells: new_cells,
+                interactions: interaction_generator
+                    .generate(&rel_rgtps),
+                int_stats: self.int_stats.clone(),
+            },
+            dense_cells,
+        ))
     }
 
     fn simulate_euler_debug(
         &self,
         world_parameters: &WorldParameters,
-        group_parameters: &[Parameters],
-        interaction_generator: &mut InteractionGenerator,
+        cell_parameters: &[Parameters<N>],
+        interaction_generator: &mut InteractionGenerator<N>,
         int_opts: EulerOpts,
-    ) -> Result<Vec<WorldCells>, String> {
+    ) -> Result<Vec<WorldCells<N>>, SimError> {
         let mut out = vec![];
         let dt = int_opts.dt();
         for int_step in 0..(int_opts.num_int_steps + 1) {
@@ -216,10 +752,10 @@ impl WorldCells {
                 .iter()
                 .map(|c| {
                     c.core.calc_relative_rgtp_activity(
-                        &group_parameters[c.group_ix],
+                        &cell_parameters[c.ix],
                     )
                 })
-                .collect::<Vec<[RelativeRgtpActivity; NVERTS]>>();
+                .collect::<Vec<[RelativeRgtpActivity; N]>>();
             let interactions =
                 interaction_generator.generate(&rel_rgtps);
             let ci = cell.ix;
@@ -228,10 +764,11 @@ impl WorldCells {
             let this_interactions = &interactions[ci];
 
             let r = cell.simulate_euler_debug(
+                self.tpoint,
                 this_interactions,
                 contact_data,
                 world_parameters,
-                &group_parameters[cell.group_ix],
+                &cell_parameters[cell.ix],
                 int_opts,
             )?;
             for (int_step, int_state) in r.iter().enumerate() {
@@ -250,45 +787,114 @@ impl WorldCells {
 #[derive(
     Deserialize, Serialize, Clone, Default, Debug, PartialEq,
 )]
-pub struct WorldInfo {
+pub struct WorldInfo<const N: usize> {
+    /// Number of vertices per cell, `N`. It is the first field, so
+    /// that it can be read from an output file before the rest of
+    /// the header (see `hardio::read_nverts`).
+    pub nverts: usize,
     pub final_t: f64,
     pub snap_period: f64,
     pub char_quants: CharQuantities,
     pub world_params: WorldParameters,
-    pub cell_params: Vec<Parameters>,
-}
-
-impl Iterator for WorldCells {
-    type Item = ();
-
-    fn next(&mut self) -> Option<Self::Item> {
-        unimplemented!()
-    }
+    /// Parameters of each cell, which may vary between the cells of
+    /// a group (see `exp_setup::sample_cell_params`).
+    pub cell_params: Vec<Parameters<N>>,
+    /// Parameters of each cell group. Unlike `cell_params`, these
+    /// remain valid as cells divide and are removed.
+    pub group_params: Vec<Parameters<N>>,
+    pub int_opts: IntegratorOpts,
+    pub update_mode: UpdateMode,
+    pub stop_conditions: Vec<RawStopCondition>,
+    pub population: RawPopulationRules,
+    pub remesh: Option<RemeshOpts>,
+    pub dense_output: bool,
+    pub recovery: RecoveryOpts,
 }
 
 #[derive(Clone)]
-pub struct WorldState {
+pub struct WorldState<const N: usize> {
     pub tpoint: f64,
-    pub cells: WorldCells,
+    pub cells: WorldCells<N>,
     pub rng: Pcg32,
+    /// Id that will be given to the next cell created.
+    pub next_cell_id: usize,
 }
 
-pub struct World {
+pub struct World<const N: usize> {
     final_t: f64,
     char_quants: CharQuantities,
-    state: WorldState,
+    state: WorldState<N>,
     params: WorldParameters,
-    cell_group_params: Vec<Parameters>,
-    writer: Option<AsyncWriter>,
-    interaction_generator: InteractionGenerator,
+    cell_group_params: Vec<Parameters<N>>,
+    /// Parameters of each cell. Daughters inherit the parameters of
+    /// their parent.
+    cell_params: Vec<Parameters<N>>,
+    writer: Option<AsyncWriter<N>>,
+    /// Observers notified of the simulation's progress, in addition
+    /// to `writer`.
+    observers: Vec<Box<dyn SimObserver<N>>>,
+    /// Whether observers have been notified of the simulation
+    /// starting.
+    started: bool,
+    interaction_generator: InteractionGenerator<N>,
     snap_period: f64,
     int_opts: IntegratorOpts,
+    update_mode: UpdateMode,
+    stop_conditions: StopConditions,
+    raw_population: RawPopulationRules,
+    population: PopulationRules,
+    remesh: Option<RemeshOpts>,
+    /// Whether states are saved at exact multiples of `snap_period`
+    /// (see `World::save_interpolated`), rather than at the end of
+    /// the first time step at least `snap_period` after the last
+    /// save. The end of each time step in which states were saved is
+    /// saved too, to resume from (see `World::save_step_end`).
+    dense_output: bool,
+    recovery: RecoveryOpts,
+    /// Changes in the cell population since state was last saved.
+    lineage: Vec<LineageEvent>,
+    /// Retries of time steps since state was last saved.
+    recoveries: Vec<Recovery>,
+    /// Description of the stop condition which ended the
+    /// simulation, if one has been met.
+    stop_reason: Option<String>,
+    /// Time point at which state was last saved, if any state has
+    /// been saved yet.
+    last_saved: Option<f64>,
+    /// Time point of the last state saved which was not
+    /// interpolated, if any.
+    state_saved: Option<f64>,
+}
+
+/// Iterator over the states of a `World`, created by
+/// `World::states`.
+pub struct WorldStates<'a, const N: usize> {
+    world: &'a mut World<N>,
+    failed: bool,
+}
+
+impl<'a, const N: usize> Iterator for WorldStates<'a, N> {
+    type Item = Result<WorldState<N>, SimError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.world.is_finished() {
+            None
+        } else {
+            match self.world.step() {
+                Ok(()) => Some(Ok(self.world.state.clone())),
+                Err(e) => {
+                    self.failed = true;
+                    Some(Err(e))
+                }
+            }
+        }
+    }
 }
 
-fn gen_poly(centroid: &V2d, radius: f64) -> [V2d; NVERTS] {
-    let mut r = [V2d::default(); NVERTS];
-    (0..NVERTS).for_each(|vix| {
-        let vf = (vix as f64) / (NVERTS as f64);
+fn gen_poly<const N: usize>(centroid: &V2d, radius: f64) -> [V2d; N] {
+    let mut r = [V2d::default(); N];
+    (0..N).for_each(|vix| {
+        let vf = (vix as f64) / (N as f64);
         let theta = 2.0 * PI * vf;
         r[vix] = V2d {
             x: centroid.x + theta.cos() * radius,
@@ -298,8 +904,8 @@ fn gen_poly(centroid: &V2d, radius: f64) -> [V2d; NVERTS] {
     r
 }
 
-impl World {
-    pub fn new(experiment: Experiment) -> World {
+impl<const N: usize> World<N> {
+    pub fn new(experiment: Experiment<N>) -> World<N> {
         // Unpack relevant info from `Experiment` data structure.
         let Experiment {
             char_quants,
@@ -310,6 +916,12 @@ impl World {
             snap_period,
             max_on_ram,
             int_opts,
+            update_mode,
+            stop_conditions,
+            population,
+            remesh,
+            dense_output,
+            recovery,
             out_dir,
             py_main,
             name,
@@ -326,7 +938,14 @@ impl World {
         let group_params = cell_groups
             .iter()
             .map(|cg| cg.parameters)
-            .collect::<Vec<Parameters>>();
+            .collect::<Vec<Parameters<N>>>();
+        let cell_params = cell_groups
+            .iter()
+            .flat_map(|cg| {
+                assert_eq!(cg.cell_parameters.len(), cg.num_cells);
+                cg.cell_parameters.iter().copied()
+            })
+            .collect::<Vec<Parameters<N>>>();
 
         // Create a list of indices of the groups. and create a vector
         // of the cell centroids in each group.
@@ -339,11 +958,11 @@ impl World {
         });
         // Generate the cell polygons from the cell centroid
         // information generated in the last step.
-        let cell_polys = cell_group_ixs
+        let cell_polys = cell_params
             .iter()
             .zip(cell_centroids.iter())
-            .map(|(&gix, cc)| gen_poly(cc, group_params[gix].cell_r))
-            .collect::<Vec<[V2d; NVERTS]>>();
+            .map(|(parameters, cc)| gen_poly(cc, parameters.cell_r))
+            .collect::<Vec<[V2d; N]>>();
         if let Some(pm) = &py_main {
             execute_py_model(
                 &out_dir,
@@ -355,40 +974,40 @@ impl World {
                 cell_polys.len() as u32,
                 world_params.interactions.phys_contact.cil_mag,
                 world_params.interactions.coa.map(|coa_params| {
-                    coa_params.vertex_mag * NVERTS as f64
+                    coa_params.vertex_mag * N as f64
                 }),
             );
         }
-        // Create initial cell states, using the parameters associated
-        // with the cell a cell group is in, and the cell's centroid
-        // location.
-        let cell_core_states = cell_group_ixs
+        // Create initial cell states, using the parameters of each
+        // cell, and the cell's centroid location.
+        let cell_core_states = cell_params
             .iter()
             .zip(cell_polys.iter())
-            .map(|(&gix, poly)| {
-                let parameters = &group_params[gix];
+            .map(|(parameters, poly)| {
                 Core::init(
                     *poly,
                     parameters.init_rac,
                     parameters.init_rho,
                 )
             })
-            .collect::<Vec<Core>>();
+            .collect::<Vec<Core<N>>>();
         // Calculate relative activity of Rac1 vs. RhoA at a node.
         // This is needed for CRL.
-        let cell_rgtps = cell_group_ixs
+        let cell_rgtps = cell_params
             .iter()
             .zip(cell_core_states.iter())
-            .map(|(&gix, state)| {
-                let parameters = &group_params[gix];
+            .map(|(parameters, state)| {
                 state.calc_relative_rgtp_activity(parameters)
             })
-            .collect::<Vec<[RelativeRgtpActivity; NVERTS]>>();
+            .collect::<Vec<[RelativeRgtpActivity; N]>>();
         // Create a new `InteractionGenerator`.
-        let interaction_generator = InteractionGenerator::new(
+        let mut interaction_generator = InteractionGenerator::new(
             &cell_polys,
             &cell_rgtps,
+            &cell_group_ixs,
             world_params.interactions.clone(),
+            world_params.obstacles.clone(),
+            world_params.domain,
         );
         // Generate initial cell interactions.
         let cell_interactions =
@@ -398,38 +1017,44 @@ impl World {
         for (cell_ix, group_ix) in
             cell_group_ixs.into_iter().enumerate()
         {
-            // Parameters that will be used by this cell. Determined
-            // by figuring out which group it belongs to, as all cells
-            // within a group use the same parameters.
-            let parameters = &group_params[group_ix];
-            let mut cell_rng = Pcg32::seed_from_u64(rng.next_u64());
+            // Parameters that will be used by this cell.
+            let parameters = &cell_params[cell_ix];
+            let cell_rng = Pcg32::seed_from_u64(rng.next_u64());
             // Create a new cell.
             cells.push(Cell::new(
+                cell_ix,
                 cell_ix,
                 group_ix,
                 cell_core_states[cell_ix],
                 parameters,
-                &mut cell_rng,
+                cell_rng,
             ));
         }
+        let num_cells = cells.len();
         let cells = WorldCells {
             tpoint: 0.0,
             cells,
             interactions: cell_interactions,
+            int_stats: vec![vec![]; num_cells],
         };
         let writer = Some(Self::init_writer(
             out_dir,
             name,
             WorldInfo {
+                nverts: N,
                 final_t: expected_final_t,
                 snap_period: normed_snap_period,
                 char_quants,
                 world_params: world_params.clone(),
-                cell_params: cells
-                    .cells
-                    .iter()
-                    .map(|s| group_params[s.group_ix])
-                    .collect::<Vec<Parameters>>(),
+                cell_params: cell_params.clone(),
+                group_params: group_params.clone(),
+                int_opts,
+                update_mode,
+                stop_conditions: stop_conditions.clone(),
+                population: population.clone(),
+                remesh,
+                dense_output,
+                recovery,
             },
             max_on_ram,
         ));
@@ -438,29 +1063,289 @@ impl World {
                 tpoint: 0.0,
                 cells,
                 rng,
+                next_cell_id: num_cells,
             },
             final_t: expected_final_t,
             char_quants,
             params: world_params,
             cell_group_params: group_params,
+            cell_params,
             interaction_generator,
             writer,
+            observers: vec![],
+            started: false,
             int_opts,
+            update_mode,
+            stop_conditions: StopConditions::new(
+                &stop_conditions,
+                &char_quants,
+                0.0,
+            ),
+            population: population.refine(&char_quants),
+            raw_population: population,
+            remesh,
+            dense_output,
+            recovery,
+            lineage: vec![],
+            recoveries: vec![],
+            stop_reason: None,
             snap_period: normed_snap_period,
+            last_saved: None,
+            state_saved: None,
+        }
+    }
+
+    /// Rebuild a `World` from a `WorldSnapshot` read from an earlier
+    /// simulation's output (see `hardio::load_last_snapshot`), so that
+    /// the simulation can be continued from where the snapshot was
+    /// taken. Output is appended to the existing file `name` in
+    /// `out_dir`. The snapshot must not be interpolated.
+    pub fn from_snapshot(
+        info: WorldInfo<N>,
+        snapshot: WorldSnapshot<N>,
+        out_dir: PathBuf,
+        name: String,
+        max_on_ram: usize,
+    ) -> World<N> {
+        assert!(
+            !snapshot.interpolated,
+            "cannot resume from an interpolated snapshot"
+        );
+        let WorldSnapshot {
+            tpoint,
+            cells: cell_snaps,
+            rng,
+            next_cell_id,
+            chem_field,
+            adh_bonds,
+            cell_params,
+            ..
+        } = snapshot;
+        let group_params = info.group_params.clone();
+        let cells = cell_snaps
+            .iter()
+            .map(|c| Cell {
+                ix: c.ix,
+                id: c.id,
+                group_ix: c.group_ix,
+                rac_rand: c.rac_rand,
+                core: c.core,
+                rng: c.rng,
+            })
+            .collect::<Vec<Cell<N>>>();
+        let cell_polys = cells
+            .iter()
+            .map(|c| c.core.poly)
+            .collect::<Vec<[V2d; N]>>();
+        let cell_params = cell_params.unwrap_or_else(|| {
+            cells
+                .iter()
+                .map(|c| group_params[c.group_ix])
+                .collect::<Vec<Parameters<N>>>()
+        });
+        let cell_rgtps = cells
+            .iter()
+            .map(|c| {
+                c.core.calc_relative_rgtp_activity(&cell_params[c.ix])
+            })
+            .collect::<Vec<[RelativeRgtpActivity; N]>>();
+        let cell_group_ixs =
+            cells.iter().map(|c| c.group_ix).collect::<Vec<usize>>();
+        let mut interaction_generator = InteractionGenerator::new(
+            &cell_polys,
+            &cell_rgtps,
+            &cell_group_ixs,
+            info.world_params.interactions.clone(),
+            info.world_params.obstacles.clone(),
+            info.world_params.domain,
+        );
+        if let Some(field) = chem_field {
+            interaction_generator.set_chem_field(field);
+        }
+        if let Some(bonds) = adh_bonds {
+            interaction_generator.set_adh_bonds(bonds);
+        }
+        interaction_generator.advance_chem(tpoint, 0.0);
+        let cells = WorldCells {
+            tpoint,
+            cells,
+            interactions: cell_snaps
+                .iter()
+                .map(|c| c.interactions)
+                .collect(),
+            int_stats: vec![vec![]; cell_snaps.len()],
+        };
+        let writer = Some(AsyncWriter::new(
+            out_dir,
+            name,
+            max_on_ram,
+            false,
+            info.clone(),
+        ));
+        World {
+            state: WorldState {
+                tpoint,
+                cells,
+                rng,
+                next_cell_id,
+            },
+            final_t: info.final_t,
+            char_quants: info.char_quants,
+            params: info.world_params,
+            cell_group_params: group_params,
+            cell_params,
+            interaction_generator,
+            writer,
+            observers: vec![],
+            started: false,
+            int_opts: info.int_opts,
+            update_mode: info.update_mode,
+            stop_conditions: StopConditions::new(
+                &info.stop_conditions,
+                &info.char_quants,
+                tpoint,
+            ),
+            population: info.population.refine(&info.char_quants),
+            raw_population: info.population,
+            remesh: info.remesh,
+            dense_output: info.dense_output,
+            recovery: info.recovery,
+            lineage: vec![],
+            recoveries: vec![],
+            stop_reason: None,
+            snap_period: info.snap_period,
+            // The snapshot we are resuming from has already been
+            // saved.
+            last_saved: Some(tpoint),
+            state_saved: Some(tpoint),
+        }
+    }
+
+    /// Returns the time point at which state was last saved. If no
+    /// state has been saved yet, the current state is saved first.
+    fn last_saved_or_save_state(&mut self) -> f64 {
+        match self.last_saved {
+            Some(t) => t,
+            None => {
+                // A retried first time step must not save the
+                // initial state again.
+                self.save_state();
+                self.last_saved = Some(self.state.tpoint);
+                self.state.tpoint
+            }
+        }
+    }
+
+    /// Register an observer, which will be notified of the
+    /// simulation's progress alongside the writer.
+    pub fn add_observer(
+        &mut self,
+        observer: Box<dyn SimObserver<N>>,
+    ) {
+        self.observers.push(observer);
+    }
+
+    /// Register an interaction source, which contributes factors to
+    /// the interactions of cells from now on (see
+    /// `InteractionSource`). A source which has the same name as one
+    /// already registered replaces it.
+    pub fn add_interaction_source(
+        &mut self,
+        source: Box<dyn InteractionSource<N>>,
+    ) {
+        self.interaction_generator.add_source(source);
+        self.refresh_interactions();
+    }
+
+    /// Names of the registered interaction sources.
+    pub fn interaction_source_names(&self) -> Vec<&str> {
+        self.interaction_generator.source_names()
+    }
+
+    /// Factors contributed by each registered interaction source to
+    /// the vertices of the cell with index `ix`, by source name (see
+    /// `InteractionGenerator::source_factors`).
+    pub fn interaction_source_factors(
+        &self,
+        ix: usize,
+    ) -> Vec<(&str, [f64; N])> {
+        self.interaction_generator.source_factors(ix)
+    }
+
+    /// Remove the writer, so that snapshots are only passed to the
+    /// registered observers, instead of also being saved to disk.
+    pub fn take_writer(&mut self) -> Option<AsyncWriter<N>> {
+        self.writer.take()
+    }
+
+    /// Notify the writer and the registered observers, using `f`.
+    fn notify<F: FnMut(&mut dyn SimObserver<N>)>(
+        writer: &mut Option<AsyncWriter<N>>,
+        observers: &mut [Box<dyn SimObserver<N>>],
+        mut f: F,
+    ) {
+        if let Some(writer) = writer {
+            f(writer);
+        }
+        for observer in observers.iter_mut() {
+            f(observer.as_mut());
+        }
+    }
+
+    fn start(&mut self) {
+        if !self.started {
+            self.started = true;
+            let info = self.info();
+            Self::notify(
+                &mut self.writer,
+                &mut self.observers,
+                |o| o.on_start(&info),
+            );
         }
     }
 
     pub fn save_state(&mut self) {
-        if let Some(writer) = &mut self.writer {
-            writer.push(self.state.clone());
+        self.save(None);
+    }
+
+    /// Save `state`, interpolated within the last time step, or the
+    /// current state if `state` is `None`. The integrator statistics
+    /// of the current state are cleared once saved.
+    fn save(&mut self, state: Option<&WorldState<N>>) {
+        let lineage = std::mem::take(&mut self.lineage);
+        let recoveries = std::mem::take(&mut self.recoveries);
+        if state.is_none() {
+            self.state_saved = Some(self.state.tpoint);
+        }
+        if self.writer.is_some() || !self.observers.is_empty() {
+            let snapshot = WorldSnapshot::from_state(
+                state.unwrap_or(&self.state),
+                &self.cell_params,
+                lineage,
+                recoveries,
+                self.interaction_generator.chem_field().cloned(),
+                self.interaction_generator
+                    .adh_bonds()
+                    .map(|bonds| bonds.to_vec()),
+                self.varied_cell_params(),
+                state.is_some(),
+            );
+            Self::notify(
+                &mut self.writer,
+                &mut self.observers,
+                |o| o.on_snapshot(&snapshot),
+            );
         }
+        self.state
+            .cells
+            .int_stats
+            .iter_mut()
+            .for_each(|s| s.clear());
     }
 
     pub fn periodic_save(&mut self, last_saved: f64) -> f64 {
         if (self.state.tpoint - last_saved) >= self.snap_period {
-            if let Some(writer) = &mut self.writer {
-                writer.push(self.state.clone());
-            }
+            self.save_state();
             self.state.tpoint
         } else {
             last_saved
@@ -477,154 +1362,794 @@ impl World {
             //     "curr_tpoint: {}, saving: {}",
             //     curr_tpoint, self.state.tpoint
             // );
-            if let Some(writer) = &mut self.writer {
-                writer.push(self.state.clone());
-            }
+            self.save_state();
             curr_tpoint
         } else {
             last_saved
         }
     }
 
-    pub fn simulate_rkdp5(
+    /// The first multiple of `snap_period` after `last_saved`.
+    fn next_dense_save(&self, last_saved: f64) -> f64 {
+        let k = (last_saved / self.snap_period + 1e-9).floor() + 1.0;
+        k * self.snap_period
+    }
+
+    /// Save the state at each multiple of `snap_period` which falls
+    /// strictly within the time step which has just been taken,
+    /// starting at `tpoint`. States between the start and end of the
+    /// step are found from the dense output of each cell, `dense`.
+    /// The integrator statistics of the step are saved with the
+    /// first of them. Returns the time point at which state was last
+    /// saved.
+    fn save_interpolated(
         &mut self,
-        save_cbor: bool,
-        int_opts: RkOpts,
-    ) {
-        // Save initial state.
-        self.save_state();
-        let mut last_saved = 0.0;
-        while self.state.tpoint < self.final_t {
-            let new_cells: WorldCells = self
-                .state
-                .cells
-                .simulate_rkdp5(
-                    self.state.tpoint,
-                    &mut self.state.rng,
-                    &self.params,
-                    &self.cell_group_params,
-                    &mut self.interaction_generator,
-                    int_opts,
-                )
-                .unwrap_or_else(|e| {
-                    self.final_save(save_cbor, "panicking");
-                    panic!("tstep: {}\n{}", self.state.tpoint, e);
-                });
+        tpoint: f64,
+        mut last_saved: f64,
+        dense: &[CellDense<N>],
+    ) -> f64 {
+        loop {
+            let save_t = self.next_dense_save(last_saved);
+            if save_t < self.state.tpoint - 1e-9 {
+                let int_stats = std::mem::replace(
+                    &mut self.state.cells.int_stats,
+                    vec![vec![]; dense.len()],
+                );
+                let cells = WorldCells {
+                    tpoint: save_t,
+                    cells: dense
+                        .iter()
+                        .map(|d| Cell {
+                            core: d.output.eval(save_t - tpoint),
+                            ..d.cell
+                        })
+                        .collect(),
+                    interactions: dense
+                        .iter()
+                        .map(|d| d.interactions)
+                        .collect(),
+                    int_stats,
+                };
+                let state = WorldState {
+                    tpoint: save_t,
+                    cells,
+                    rng: self.state.rng,
+                    next_cell_id: self.state.next_cell_id,
+                };
+                self.save(Some(&state));
+                last_saved = save_t;
+            } else {
+                return last_saved;
+            }
+        }
+    }
 
-            self.state.tpoint = new_cells.tpoint;
-            self.state.cells = new_cells;
-            last_saved = self.periodic_save(last_saved);
+    /// Save the state at the end of a time step with dense output, if
+    /// it is the next multiple of `snap_period` after `last_saved`.
+    /// Otherwise, if states were interpolated within the step (that
+    /// is, if `last_saved` has moved on from `step_saved`, its value
+    /// at the start of the step), the state is saved anyway, so that
+    /// a simulation can be resumed from the end of the step. Returns
+    /// the time point of the last multiple of `snap_period` saved.
+    fn save_step_end(
+        &mut self,
+        step_saved: f64,
+        last_saved: f64,
+    ) -> f64 {
+        let save_t = self.next_dense_save(last_saved);
+        if (save_t - self.state.tpoint).abs() < 1e-9 {
+            self.save_state();
+            save_t
+        } else {
+            if last_saved > step_saved {
+                self.save_state();
+            }
+            last_saved
         }
-        self.final_save(save_cbor, "done");
     }
 
-    pub fn simulate_euler(
+    /// Save state after a time step starting at `tpoint` has been
+    /// taken, either at multiples of `snap_period` using the dense
+    /// output `dense`, or once at least `snap_period` has passed
+    /// since `last_saved`. The cell population is updated, and cells
+    /// remeshed, before the state at the end of the step is saved, so
+    /// that a simulation resumed from it continues as it would have.
+    fn save_after_step(
         &mut self,
-        save_cbor: bool,
-        int_opts: EulerOpts,
+        tpoint: f64,
+        last_saved: f64,
+        dense: &[CellDense<N>],
     ) {
-        // Save initial state.
-        self.save_state();
-        let mut last_saved = 0.0;
-        while self.state.tpoint < self.final_t {
-            let new_cells: WorldCells = self
-                .state
-                .cells
-                .simulate_euler(
-                    self.state.tpoint,
-                    &mut self.state.rng,
-                    &self.params,
-                    &self.cell_group_params,
-                    &mut self.interaction_generator,
-                    int_opts,
-                )
-                .unwrap_or_else(|e| {
-                    self.final_save(save_cbor, "panicking");
-                    panic!("tstep: {}\n{}", self.state.tpoint, e);
-                });
+        let dt = self.state.tpoint - tpoint;
+        self.last_saved = Some(if self.dense_output {
+            let saved =
+                self.save_interpolated(tpoint, last_saved, dense);
+            self.update_population(dt);
+            self.remesh_cells();
+            self.save_step_end(last_saved, saved)
+        } else {
+            self.update_population(dt);
+            self.remesh_cells();
+            self.periodic_save(last_saved)
+        });
+    }
 
-            self.state.tpoint = new_cells.tpoint;
-            self.state.cells = new_cells;
-            last_saved = self.periodic_save(last_saved);
-        }
-        self.final_save(save_cbor, "done");
+    fn step_adaptive(
+        &mut self,
+        int_opts: RkOpts,
+        update_mode: UpdateMode,
+        substeps: &[usize],
+        integrate: AdaptiveCellFn<N>,
+    ) -> Result<(), SimError> {
+        let last_saved = self.last_saved_or_save_state();
+        let simulate = match update_mode {
+            UpdateMode::Sequential => WorldCells::simulate_adaptive,
+            UpdateMode::Jacobi => {
+                WorldCells::simulate_adaptive_jacobi
+            }
+        };
+        let tpoint = self.state.tpoint;
+        let (new_cells, dense) = simulate(
+            &self.state.cells,
+            tpoint,
+            &mut self.state.rng,
+            &self.params,
+            &self.cell_params,
+            &mut self.interaction_generator,
+            int_opts,
+            substeps,
+            self.dense_output,
+            integrate,
+        )?;
+
+        self.state.tpoint = new_cells.tpoint;
+        self.state.cells = new_cells;
+        self.update_chem(self.state.tpoint - tpoint);
+        self.update_adh_bonds(self.state.tpoint - tpoint);
+        self.save_after_step(tpoint, last_saved, &dense);
+        Ok(())
     }
 
-    pub fn simulate_euler_debug(
+    fn step_euler(
         &mut self,
-        save_cbor: bool,
         int_opts: EulerOpts,
-    ) {
-        let mut last_saved = 0.0 - self.snap_period;
-        while self.state.tpoint < self.final_t {
-            let new_cells = self
-                .state
-                .cells
-                .simulate_euler_debug(
-                    &self.params,
-                    &self.cell_group_params,
-                    &mut self.interaction_generator,
-                    int_opts,
-                )
-                .unwrap_or_else(|e| {
-                    self.final_save(save_cbor, "panicking");
-                    panic!("tstep: {}\n{}", self.state.tpoint, e);
-                });
-            let curr_tpoint = self.state.tpoint;
-            let mut next_last_saved = 0.0;
-            for cells in new_cells[..int_opts.num_int_steps].iter() {
-                self.state.tpoint = cells.tpoint;
-                self.state.cells = cells.clone();
-                next_last_saved = self.periodic_save_euler_debug(
-                    curr_tpoint,
-                    last_saved,
-                );
-            }
-            self.state.tpoint =
-                new_cells[int_opts.num_int_steps].tpoint;
-            self.state.cells =
-                new_cells[int_opts.num_int_steps].clone();
-            last_saved = next_last_saved;
+        noise: Option<NoiseOpts>,
+        update_mode: UpdateMode,
+        substeps: &[usize],
+    ) -> Result<(), SimError> {
+        let last_saved = self.last_saved_or_save_state();
+        let simulate = match update_mode {
+            UpdateMode::Sequential => WorldCells::simulate_euler,
+            UpdateMode::Jacobi => WorldCells::simulate_euler_jacobi,
+        };
+        let tpoint = self.state.tpoint;
+        let (new_cells, dense) = simulate(
+            &self.state.cells,
+            tpoint,
+            &mut self.state.rng,
+            &self.params,
+            &self.cell_params,
+            &mut self.interaction_generator,
+            int_opts,
+            noise,
+            substeps,
+            self.dense_output,
+        )?;
+
+        self.state.tpoint = new_cells.tpoint;
+        self.state.cells = new_cells;
+        self.update_chem(self.state.tpoint - tpoint);
+        self.update_adh_bonds(self.state.tpoint - tpoint);
+        self.save_after_step(tpoint, last_saved, &dense);
+        Ok(())
+    }
+
+    fn step_euler_debug(
+        &mut self,
+        int_opts: EulerOpts,
+    ) -> Result<(), SimError> {
+        if self.dense_output {
+            return self.step_euler_debug_dense(int_opts);
+        }
+        let last_saved =
+            self.last_saved.unwrap_or(0.0 - self.snap_period);
+        let new_cells = self.state.cells.simulate_euler_debug(
+            &self.params,
+            &self.cell_params,
+            &mut self.interaction_generator,
+            int_opts,
+        )?;
+        let curr_tpoint = self.state.tpoint;
+        let mut next_last_saved = 0.0;
+        for cells in new_cells[..int_opts.num_int_steps].iter() {
+            self.state.tpoint = cells.tpoint;
+            self.state.cells = cells.clone();
+            next_last_saved = self
+                .periodic_save_euler_debug(curr_tpoint, last_saved);
         }
-        self.final_save(save_cbor, "done");
+        self.state.tpoint = new_cells[int_opts.num_int_steps].tpoint;
+        self.state.cells = new_cells[int_opts.num_int_steps].clone();
+        self.update_chem(self.state.tpoint - curr_tpoint);
+        self.update_adh_bonds(self.state.tpoint - curr_tpoint);
+        self.update_population(self.state.tpoint - curr_tpoint);
+        self.remesh_cells();
+        self.last_saved = Some(next_last_saved);
+        Ok(())
+    }
+
+    /// Like `step_euler_debug`, but states are saved at multiples of
+    /// `snap_period`, interpolating between the integration steps.
+    fn step_euler_debug_dense(
+        &mut self,
+        int_opts: EulerOpts,
+    ) -> Result<(), SimError> {
+        let last_saved = self.last_saved_or_save_state();
+        let new_cells = self.state.cells.simulate_euler_debug(
+            &self.params,
+            &self.cell_params,
+            &mut self.interaction_generator,
+            int_opts,
+        )?;
+        let tpoint = self.state.tpoint;
+        let dt = int_opts.dt();
+        let dense = self
+            .state
+            .cells
+            .cells
+            .iter()
+            .map(|cell| {
+                let mut output = DenseOutput::default();
+                for (k, w) in new_cells.windows(2).enumerate() {
+                    output.push(Segment::Linear {
+                        t0: k as f64 * dt,
+                        h: dt,
+                        y0: w[0].cells[cell.ix].core,
+                        y1: w[1].cells[cell.ix].core,
+                    });
+                }
+                CellDense {
+                    cell: *cell,
+                    interactions: new_cells[1].interactions[cell.ix],
+                    output,
+                }
+            })
+            .collect::<Vec<CellDense<N>>>();
+        let last = new_cells[int_opts.num_int_steps].clone();
+        self.state.tpoint = last.tpoint;
+        self.state.cells = last;
+        self.update_chem(self.state.tpoint - tpoint);
+        self.update_adh_bonds(self.state.tpoint - tpoint);
+        self.save_after_step(tpoint, last_saved, &dense);
+        Ok(())
     }
 
-    pub fn simulate(&mut self, save_cbor: bool) {
+    /// Take a time step using the integrator given by the
+    /// experiment's `IntegratorOpts`, updating cells as given by
+    /// `update_mode`, with the integration of cell `ix` split into
+    /// `substeps[ix]` steps. `EulerDebug` integration is never split.
+    fn integrate_step(
+        &mut self,
+        update_mode: UpdateMode,
+        substeps: &[usize],
+    ) -> Result<(), SimError> {
         match self.int_opts {
             IntegratorOpts::Euler(int_opts) => {
-                self.simulate_euler(save_cbor, int_opts)
+                self.step_euler(int_opts, None, update_mode, substeps)
             }
             IntegratorOpts::EulerDebug(int_opts) => {
-                self.simulate_euler_debug(save_cbor, int_opts)
+                self.step_euler_debug(int_opts)
             }
-            IntegratorOpts::Rkdp5(int_opts) => {
-                self.simulate_rkdp5(save_cbor, int_opts)
+            IntegratorOpts::Rkdp5(int_opts) => self.step_adaptive(
+                int_opts,
+                update_mode,
+                substeps,
+                Cell::simulate_rkdp5,
+            ),
+            IntegratorOpts::Ros2(int_opts) => self.step_adaptive(
+                int_opts,
+                update_mode,
+                substeps,
+                Cell::simulate_ros2,
+            ),
+            IntegratorOpts::EulerMaruyama(sde_opts) => self
+                .step_euler(
+                    sde_opts.euler_opts(),
+                    Some(sde_opts.noise),
+                    update_mode,
+                    substeps,
+                ),
+        }
+    }
+
+    /// Take a time step, recovering from volume exclusion violations
+    /// as allowed by the experiment's `RecoveryOpts`. A violation
+    /// means that a cell's polygon already intersected another cell
+    /// when the cell was about to be integrated, or, when cells are
+    /// integrated in parallel, that cells moved into each other over
+    /// the time step. To recover, the world is rolled back to its
+    /// state at the start of the time step, the overlapping vertices
+    /// of the two cells are pushed apart (see
+    /// `Core::separate_from`), and the time step is retried with the
+    /// integration of both cells split into twice as many steps as
+    /// before. Retries are always integrated sequentially, since
+    /// cells integrated in parallel against the same neighbours
+    /// would move into each other again.
+    fn step_recovering(&mut self) -> Result<(), SimError> {
+        let rng = self.state.rng;
+        let mut substeps = vec![1; self.state.cells.cells.len()];
+        let mut update_mode = self.update_mode;
+        let mut retry = 0;
+        loop {
+            match self.integrate_step(update_mode, &substeps) {
+                Err(SimError::VolEx {
+                    cell_ix,
+                    other_cell_ix,
+                    ..
+                }) if retry < self.recovery.max_retries => {
+                    retry += 1;
+                    update_mode = UpdateMode::Sequential;
+                    self.roll_back(rng);
+                    let separated =
+                        self.separate_cells(cell_ix, other_cell_ix);
+                    substeps[cell_ix] *= 2;
+                    if let Some(oci) = other_cell_ix {
+                        substeps[oci] *= 2;
+                    }
+                    self.recoveries.push(Recovery {
+                        tpoint: self.state.tpoint,
+                        cell_ix,
+                        other_cell_ix,
+                        retry,
+                        substeps: [
+                            substeps[cell_ix],
+                            other_cell_ix
+                                .map_or(0, |oci| substeps[oci]),
+                        ],
+                        separated,
+                    });
+                }
+                result => return result,
             }
         }
     }
 
-    pub fn info(&self) -> WorldInfo {
+    /// Undo the changes made by a failed time step, at the start of
+    /// which the world's random number generator was `rng`.
+    fn roll_back(&mut self, rng: Pcg32) {
+        self.state.rng = rng;
+        for cell in self.state.cells.cells.iter() {
+            self.interaction_generator
+                .update(cell.ix, &cell.core.poly);
+        }
+    }
+
+    /// Push apart the overlapping vertices of the cells with indices
+    /// `a` and `b` (see `Core::separate_from`). If `b` is `None`,
+    /// cell `a` is pushed away from the boundary. Returns whether
+    /// neither cell violates volume exclusion afterwards.
+    fn separate_cells(&mut self, a: usize, b: Option<usize>) -> bool {
+        let mut separated = true;
+        let mut moved = false;
+        for ix in std::iter::once(a).chain(b) {
+            let contacts =
+                self.interaction_generator.get_contact_data(ix);
+            let old_core = self.state.cells.cells[ix].core;
+            let mut core = old_core;
+            separated &= core.separate_from(&contacts);
+            if core != old_core {
+                self.state.cells.cells[ix].core = core;
+                self.interaction_generator.update(ix, &core.poly);
+                moved = true;
+            }
+        }
+        if moved {
+            self.refresh_interactions();
+        }
+        separated
+    }
+
+    /// Advance the simulation by a single time step, using the
+    /// integrator given by the experiment's `IntegratorOpts`. A
+    /// time step which fails due to a volume exclusion violation is
+    /// retried as described in `World::step_recovering`. States are
+    /// saved, and observers notified, as they would be by
+    /// `simulate`. If a stop condition is met, the state which met
+    /// it is saved, so that it is the last state saved.
+    pub fn step(&mut self) -> Result<(), SimError> {
+        self.start();
+        let result = self.step_recovering();
+        if result.is_ok() && self.stop_reason.is_none() {
+            self.stop_reason = self
+                .stop_conditions
+                .check(&self.state, &self.interaction_generator);
+            if self.stop_reason.is_some()
+                && self.state_saved != Some(self.state.tpoint)
+            {
+                self.save_state();
+            }
+        }
+        let state = &self.state;
+        match &result {
+            Ok(()) => Self::notify(
+                &mut self.writer,
+                &mut self.observers,
+                |o| o.on_step(state),
+            ),
+            Err(e) => Self::notify(
+                &mut self.writer,
+                &mut self.observers,
+                |o| o.on_error(e),
+            ),
+        }
+        result
+    }
+
+    /// Advance chemoattractant over a time step of length `dt`, which
+    /// ends at the current time point: the field, if there is one, is
+    /// stepped with the cells where they are at the end of the time
+    /// step, and source strengths are updated. Called before the
+    /// state at the end of the time step is saved, so that snapshots
+    /// hold the field at their time point.
+    fn update_chem(&mut self, dt: f64) {
+        if self
+            .interaction_generator
+            .advance_chem(self.state.tpoint, dt)
+        {
+            self.refresh_interactions();
+        }
+    }
+
+    /// Advance adhesion bonds over a time step of length `dt`, which
+    /// ends at the current time point, so that snapshots hold the
+    /// bonds at their time point.
+    fn update_adh_bonds(&mut self, dt: f64) {
+        if self
+            .interaction_generator
+            .step_adh_bonds(dt, &mut self.state.rng)
+        {
+            self.refresh_interactions();
+        }
+    }
+
+    /// Apply the experiment's population rules after a time step of
+    /// length `dt`. Cells which have left the domain or died are
+    /// removed first, then the remaining cells may divide. Cells are
+    /// considered in order of index.
+    fn update_population(&mut self, dt: f64) {
+        if !self.population.is_active() {
+            return;
+        }
+        let mut removals = vec![];
+        for cell in self.state.cells.cells.iter() {
+            let centroid = calc_poly_centroid(&cell.core.poly);
+            if self.population.outside_domain(&centroid) {
+                removals.push((cell.ix, RemovalCause::LeftDomain));
+            } else if let Some(p) = self.population.death_prob(dt) {
+                if self.state.rng.gen::<f64>() < p {
+                    removals.push((cell.ix, RemovalCause::Died));
+                }
+            }
+        }
+        // Each removal shifts the indices of the cells after it down
+        // by one.
+        for (k, (ix, cause)) in removals.into_iter().enumerate() {
+            self.remove_cell(ix - k, cause);
+        }
+        if let Some(p) = self.population.division_prob(dt) {
+            let num_cells = self.state.cells.cells.len();
+            for ix in 0..num_cells {
+                if self
+                    .population
+                    .can_divide(self.state.cells.cells.len())
+                    && self.state.rng.gen::<f64>() < p
+                {
+                    self.divide_cell(ix);
+                }
+            }
+        }
+        if self.state.cells.cells.is_empty()
+            && self.stop_reason.is_none()
+        {
+            self.stop_reason = Some("all cells removed".to_string());
+        }
+    }
+
+    /// Remesh (see `Core::remesh`) each cell whose edge lengths have
+    /// become too uneven, as given by the experiment's `RemeshOpts`.
+    /// A cell is left as it is if remeshing it would violate volume
+    /// exclusion.
+    fn remesh_cells(&mut self) {
+        let opts = match self.remesh {
+            Some(opts) => opts,
+            None => return,
+        };
+        let mut remeshed = false;
+        for ix in 0..self.state.cells.cells.len() {
+            let core = &self.state.cells.cells[ix].core;
+            if core.edge_len_ratio() <= opts.max_edge_ratio {
+                continue;
+            }
+            let new_core = core.remesh();
+            if !self.remesh_fits(ix, &new_core) {
+                continue;
+            }
+            self.state.cells.cells[ix].core = new_core;
+            self.interaction_generator.update(ix, &new_core.poly);
+            self.interaction_generator.release_adh_bonds(ix);
+            remeshed = true;
+        }
+        if remeshed {
+            self.refresh_interactions();
+        }
+    }
+
+    /// Whether cell `ix` could take on the remeshed `core` without
+    /// violating volume exclusion.
+    fn remesh_fits(&self, ix: usize, core: &Core<N>) -> bool {
+        let contacts =
+            self.interaction_generator.get_contact_data(ix);
+        let remeshed = Contacts {
+            cells: vec![ContactData {
+                oci: ix,
+                poly: Poly::from_verts(&core.poly),
+            }],
+            walls: vec![],
+            obstacles: vec![],
+        };
+        confirm_volume_exclusion(&core.poly, &contacts, "remeshed")
+            .is_ok()
+            && contacts.cells.iter().all(|c| {
+                confirm_volume_exclusion(
+                    &c.poly.verts,
+                    &remeshed,
+                    "neighbour",
+                )
+                .is_ok()
+            })
+    }
+
+    /// Divide the cell with index `ix` along a randomly oriented line
+    /// through its centroid (see `Core::divide`). The first daughter
+    /// takes the parent's index, and the second is added after all
+    /// other cells. Returns the ids of the daughters, or `None` if
+    /// the cell's shape did not allow it to divide.
+    pub fn divide_cell(&mut self, ix: usize) -> Option<[usize; 2]> {
+        let parent = self.state.cells.cells[ix];
+        let theta = 2.0 * PI * self.state.rng.gen::<f64>();
+        let dir = V2d {
+            x: theta.cos(),
+            y: theta.sin(),
+        };
+        let cores = parent.core.divide(dir)?;
+        if !self.daughters_fit(ix, &cores) {
+            return None;
+        }
+        let parameters = self.cell_params[ix];
+        let ixs = [ix, self.state.cells.cells.len()];
+        let ids =
+            [self.state.next_cell_id, self.state.next_cell_id + 1];
+        self.state.next_cell_id += 2;
+        let daughters = [0, 1].map(|k| {
+            let cell_rng =
+                Pcg32::seed_from_u64(self.state.rng.next_u64());
+            Cell::new(
+                ixs[k],
+                ids[k],
+                parent.group_ix,
+                cores[k],
+                &parameters,
+                cell_rng,
+            )
+        });
+        self.state.cells.cells[ix] = daughters[0];
+        self.state.cells.cells.push(daughters[1]);
+        self.cell_params.push(parameters);
+        self.state.cells.int_stats[ix].clear();
+        self.state.cells.int_stats.push(vec![]);
+        self.interaction_generator
+            .update(ix, &daughters[0].core.poly);
+        self.interaction_generator.release_adh_bonds(ix);
+        self.interaction_generator.add_cell(
+            &daughters[1].core.poly,
+            daughters[1]
+                .core
+                .calc_relative_rgtp_activity(&parameters),
+            daughters[1].group_ix,
+        );
+        self.lineage.push(LineageEvent::Division {
+            tpoint: self.state.tpoint,
+            parent: parent.id,
+            daughters: ids,
+        });
+        self.refresh_interactions();
+        Some(ids)
+    }
+
+    /// Whether the daughters of cell `ix` could replace it without
+    /// violating volume exclusion. Daughter polygons are resampled,
+    /// so if the parent is concave, they may cut into neighbouring
+    /// cells.
+    fn daughters_fit(&self, ix: usize, cores: &[Core<N>; 2]) -> bool {
+        let contacts =
+            self.interaction_generator.get_contact_data(ix);
+        let daughters = cores.map(|c| ContactData {
+            oci: ix,
+            poly: Poly::from_verts(&c.poly),
+        });
+        cores.iter().enumerate().all(|(k, c)| {
+            confirm_volume_exclusion(&c.poly, &contacts, "daughter")
+                .is_ok()
+                && confirm_volume_exclusion(
+                    &c.poly,
+                    &Contacts {
+                        cells: vec![daughters[1 - k].clone()],
+                        walls: vec![],
+                        obstacles: vec![],
+                    },
+                    "daughter",
+                )
+                .is_ok()
+        }) && contacts.cells.iter().all(|c| {
+            confirm_volume_exclusion(
+                &c.poly.verts,
+                &Contacts {
+                    cells: daughters.to_vec(),
+                    walls: vec![],
+                    obstacles: vec![],
+                },
+                "neighbour",
+            )
+            .is_ok()
+        })
+    }
+
+    /// Remove the cell with index `ix`. Cells with greater indices
+    /// have their indices shifted down by one.
+    pub fn remove_cell(&mut self, ix: usize, cause: RemovalCause) {
+        let cell = self.state.cells.cells.remove(ix);
+        self.state.cells.int_stats.remove(ix);
+        self.cell_params.remove(ix);
+        for c in self.state.cells.cells[ix..].iter_mut() {
+            c.ix -= 1;
+        }
+        self.interaction_generator.remove_cell(ix);
+        self.lineage.push(LineageEvent::Removal {
+            tpoint: self.state.tpoint,
+            id: cell.id,
+            cause,
+        });
+        self.refresh_interactions();
+    }
+
+    /// Regenerate the interactions of all cells, for example after
+    /// the cell population has changed.
+    fn refresh_interactions(&mut self) {
+        let rel_rgtps = self
+            .state
+            .cells
+            .cells
+            .iter()
+            .map(|c| {
+                c.core.calc_relative_rgtp_activity(
+                    &self.cell_params[c.ix],
+                )
+            })
+            .collect::<Vec<[RelativeRgtpActivity; N]>>();
+        self.state.cells.interactions =
+            self.interaction_generator.generate(&rel_rgtps);
+    }
+
+    /// Step the simulation until the (normalized) time point is at
+    /// least `t`, or a stop condition is met.
+    pub fn step_until(&mut self, t: f64) -> Result<(), SimError> {
+        while self.state.tpoint < t && self.stop_reason.is_none() {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Iterate over the states of the world after each time step,
+    /// until the simulation is finished (see `is_finished`). If a
+    /// step fails, the error is yielded, and iteration ends.
+    ///
+    /// The writer is not finished when iteration ends: call
+    /// `final_save` to flush saved states to disk.
+    pub fn states(&mut self) -> WorldStates<'_, N> {
+        WorldStates {
+            world: self,
+            failed: false,
+        }
+    }
+
+    pub fn state(&self) -> &WorldState<N> {
+        &self.state
+    }
+
+    pub fn final_t(&self) -> f64 {
+        self.final_t
+    }
+
+    /// Whether the final time point of the experiment has been
+    /// reached, or one of its stop conditions has been met.
+    pub fn is_finished(&self) -> bool {
+        self.state.tpoint >= self.final_t
+            || self.stop_reason.is_some()
+    }
+
+    /// Description of the stop condition which ended the simulation,
+    /// if any.
+    pub fn stop_reason(&self) -> Option<&str> {
+        self.stop_reason.as_deref()
+    }
+
+    /// Run the simulation until the final time point of the
+    /// experiment, or until one of its stop conditions is met. If a
+    /// step fails, states saved so far are written to disk before
+    /// the error is returned.
+    pub fn simulate(
+        &mut self,
+        save_cbor: bool,
+    ) -> Result<SimSummary, SimError> {
+        self.start();
+        while !self.is_finished() {
+            if let Err(e) = self.step() {
+                // The simulation error is more useful to the caller
+                // than any error from saving what we have so far.
+                let _ = self.final_save(save_cbor, "failed");
+                return Err(e);
+            }
+        }
+        let reason = self
+            .stop_reason
+            .clone()
+            .unwrap_or_else(|| "done".to_string());
+        let num_saved = self.final_save(save_cbor, &reason)?;
+        Ok(SimSummary {
+            tpoint: self.state.tpoint,
+            num_saved,
+            reason,
+        })
+    }
+
+    /// Parameters of each cell, if any differ from those of its
+    /// group, so that they need to be saved to resume the simulation.
+    fn varied_cell_params(&self) -> Option<Vec<Parameters<N>>> {
+        self.state
+            .cells
+            .cells
+            .iter()
+            .any(|c| {
+                self.cell_params[c.ix]
+                    != self.cell_group_params[c.group_ix]
+            })
+            .then(|| self.cell_params.clone())
+    }
+
+    pub fn info(&self) -> WorldInfo<N> {
         WorldInfo {
+            nverts: N,
             final_t: self.final_t,
             snap_period: self.snap_period,
             char_quants: self.char_quants,
             world_params: self.params.clone(),
-            cell_params: self
-                .state
-                .cells
-                .cells
-                .iter()
-                .map(|s| self.cell_group_params[s.group_ix])
-                .collect::<Vec<Parameters>>(),
+            cell_params: self.cell_params.clone(),
+            group_params: self.cell_group_params.clone(),
+            int_opts: self.int_opts,
+            update_mode: self.update_mode,
+            stop_conditions: self.stop_conditions.raw().to_vec(),
+            population: self.raw_population.clone(),
+            remesh: self.remesh,
+            dense_output: self.dense_output,
+            recovery: self.recovery,
         }
     }
 
     pub fn init_writer(
         output_dir: PathBuf,
         file_name: String,
-        info: WorldInfo,
+        info: WorldInfo<N>,
         max_capacity: usize,
-    ) -> AsyncWriter {
+    ) -> AsyncWriter<N> {
         AsyncWriter::new(
             output_dir,
             file_name,
@@ -634,20 +2159,37 @@ impl World {
         )
     }
 
-    pub fn final_save(&mut self, save_cbor: bool, reason: &str) {
-        if let Some(writer) = self.writer.take() {
-            writer.finish(save_cbor, reason);
+    /// Notify observers that the simulation has finished, and finish
+    /// writing saved states to disk. Returns the number of snapshots
+    /// saved.
+    pub fn final_save(
+        &mut self,
+        save_cbor: bool,
+        reason: &str,
+    ) -> Result<usize, SimError> {
+        let mut result = Ok(());
+        for observer in self.observers.iter_mut() {
+            let r = observer.on_finish(reason);
+            if result.is_ok() {
+                result = r;
+            }
         }
+        let num_saved = match self.writer.take() {
+            Some(writer) => writer.finish(save_cbor, reason)?,
+            None => 0,
+        };
+        result.map(|_| num_saved)
     }
 }
 
-pub fn gen_cell_centroids(
-    cg: &CellGroup,
+pub fn gen_cell_centroids<const N: usize>(
+    cg: &CellGroup<N>,
 ) -> Result<Vec<V2d>, String> {
     let CellGroup {
         num_cells,
         layout,
         parameters,
+        ..
     } = cg;
     let cell_r = parameters.cell_r;
     if layout.width * layout.height >= *num_cells {
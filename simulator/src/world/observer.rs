@@ -0,0 +1,29 @@
+use crate::hardio::WorldSnapshot;
+use crate::world::{SimError, WorldInfo, WorldState};
+
+/// Receives updates from a `World` as it is simulated. Observers are
+/// registered using `World::add_observer`; the `AsyncWriter` which
+/// saves simulation output to disk is also an observer.
+///
+/// All methods do nothing by default, so an implementation only
+/// needs to provide the methods it is interested in.
+pub trait SimObserver<const N: usize> {
+    /// Called once, before the first time step is simulated.
+    fn on_start(&mut self, _info: &WorldInfo<N>) {}
+
+    /// Called after every successful time step, with the new state
+    /// of the world.
+    fn on_step(&mut self, _state: &WorldState<N>) {}
+
+    /// Called whenever a snapshot of the world is saved, which
+    /// happens every `snap_period`.
+    fn on_snapshot(&mut self, _snapshot: &WorldSnapshot<N>) {}
+
+    /// Called if a time step fails.
+    fn on_error(&mut self, _error: &SimError) {}
+
+    /// Called when the simulation ends, with the reason it ended.
+    fn on_finish(&mut self, _reason: &str) -> Result<(), SimError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use crate::math::geometry::BBox;
+use crate::math::v2d::V2d;
+use crate::parameters::quantity::{Length, Quantity, Time};
+use crate::parameters::CharQuantities;
+use serde::{Deserialize, Serialize};
+
+/// Rules by which cells divide and are removed during a simulation,
+/// as given in an experiment's JSON. Lengths are in micrometers, and
+/// times in seconds.
+#[derive(
+    Clone, Debug, Default, Deserialize, Serialize, PartialEq,
+)]
+pub struct RawPopulationRules {
+    /// Mean time between divisions of a cell. If `None`, cells do
+    /// not divide.
+    pub division_time: Option<f64>,
+    /// Mean lifetime of a cell. If `None`, cells do not die.
+    pub lifetime: Option<f64>,
+    /// Cells whose centroids leave the rectangle with bottom left
+    /// corner `domain[0]` and top right corner `domain[1]` are
+    /// removed.
+    pub domain: Option<[[f64; 2]; 2]>,
+    /// Cells stop dividing once there are this many of them.
+    pub max_cells: Option<usize>,
+}
+
+/// `RawPopulationRules` normalized by characteristic quantities.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PopulationRules {
+    pub division_time: Option<f64>,
+    pub lifetime: Option<f64>,
+    pub domain: Option<BBox>,
+    pub max_cells: Option<usize>,
+}
+
+impl RawPopulationRules {
+    pub fn refine(&self, cq: &CharQuantities) -> PopulationRules {
+        let length = |l: f64| cq.normalize(&Length(l).micro());
+        let time = |t: f64| cq.normalize(&Time(t));
+        PopulationRules {
+            division_time: self.division_time.map(time),
+            lifetime: self.lifetime.map(time),
+            domain: self.domain.map(|[bl, tr]| BBox {
+                xmin: length(bl[0]),
+                ymin: length(bl[1]),
+                xmax: length(tr[0]),
+                ymax: length(tr[1]),
+            }),
+            max_cells: self.max_cells,
+        }
+    }
+}
+
+impl PopulationRules {
+    /// Whether any of the rules can change the cell population.
+    pub fn is_active(&self) -> bool {
+        self.division_time.is_some()
+            || self.lifetime.is_some()
+            || self.domain.is_some()
+    }
+
+    /// Probability that a cell divides during a time step of length
+    /// `dt`, if cells divide.
+    pub fn division_prob(&self, dt: f64) -> Option<f64> {
+        self.division_time.map(|t| 1.0 - (-dt / t).exp())
+    }
+
+    /// Probability that a cell dies during a time step of length
+    /// `dt`, if cells die.
+    pub fn death_prob(&self, dt: f64) -> Option<f64> {
+        self.lifetime.map(|t| 1.0 - (-dt / t).exp())
+    }
+
+    /// Whether a cell with centroid `centroid` has left the domain.
+    pub fn outside_domain(&self, centroid: &V2d) -> bool {
+        self.domain
+            .is_some_and(|domain| !domain.contains(centroid, 0.0))
+    }
+
+    /// Whether cells may divide, given there are `num_cells` of
+    /// them.
+    pub fn can_divide(&self, num_cells: usize) -> bool {
+        self.max_cells.is_none_or(|max| num_cells < max)
+    }
+}
+
+/// Why a cell was removed from the simulation.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub enum RemovalCause {
+    /// The cell's centroid left the domain.
+    LeftDomain,
+    /// The cell died.
+    Died,
+}
+
+/// A change in the cell population. Cells are referred to by their
+/// ids (see `Cell::id`).
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub enum LineageEvent {
+    /// Cell `parent` divided into cells `daughters`. The parent no
+    /// longer exists after division.
+    Division {
+        tpoint: f64,
+        parent: usize,
+        daughters: [usize; 2],
+    },
+    /// Cell `id` was removed.
+    Removal {
+        tpoint: f64,
+        id: usize,
+        cause: RemovalCause,
+    },
+}
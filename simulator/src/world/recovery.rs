@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// How a simulation recovers from time steps which fail because a
+/// cell's polygon already violates volume exclusion (see
+/// `World::step`). By default, simulations do not recover.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq,
+)]
+pub struct RecoveryOpts {
+    /// Number of times a time step is retried before the simulation
+    /// ends with an error. If zero, the simulation ends at the first
+    /// failure.
+    pub max_retries: usize,
+}
+
+/// A retry of the time step starting at `tpoint`, after a volume
+/// exclusion violation between the cells with indices `cell_ix` and
+/// `other_cell_ix`, or between cell `cell_ix` and the boundary if
+/// `other_cell_ix` is `None`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Recovery {
+    pub tpoint: f64,
+    pub cell_ix: usize,
+    pub other_cell_ix: Option<usize>,
+    /// Number of the retry, starting from 1, within the time step.
+    pub retry: usize,
+    /// Number of steps the integration of each of the two cells is
+    /// split into in the retry. The second entry is `0` if the other
+    /// is the boundary.
+    pub substeps: [usize; 2],
+    /// Whether the cells' overlapping vertices could be pushed
+    /// apart before the retry.
+    pub separated: bool,
+}
@@ -0,0 +1,164 @@
+use crate::interactions::InteractionGenerator;
+use crate::math::geometry::calc_poly_centroid;
+use crate::math::v2d::V2d;
+use crate::parameters::quantity::{Length, Quantity, Time};
+use crate::parameters::CharQuantities;
+use crate::world::WorldState;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// A condition which ends a simulation before `final_t`, as given in
+/// an experiment's JSON. Lengths are in micrometers, and times in
+/// seconds.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum RawStopCondition {
+    /// Cell centroids have crossed the line through `point`, onto
+    /// the side of it which `normal` points towards. If `all` is
+    /// true, every cell must have crossed the line, otherwise one
+    /// cell is enough.
+    CentroidCrossesLine {
+        point: [f64; 2],
+        normal: [f64; 2],
+        all: bool,
+    },
+    /// The largest distance between the centroids of any two cells
+    /// is greater than `dist`.
+    MaxPairwiseDist { dist: f64 },
+    /// No two cells have been in contact for at least `t`.
+    NoContactsFor { t: f64 },
+    /// The simulation has run for at least `secs` of wall-clock
+    /// time.
+    WallClock { secs: f64 },
+}
+
+/// `RawStopCondition` normalized by characteristic quantities.
+#[derive(Clone, Copy, Debug)]
+pub enum StopCondition {
+    CentroidCrossesLine { point: V2d, normal: V2d, all: bool },
+    MaxPairwiseDist { dist: f64 },
+    NoContactsFor { t: f64 },
+    WallClock { secs: f64 },
+}
+
+impl RawStopCondition {
+    pub fn refine(&self, cq: &CharQuantities) -> StopCondition {
+        let length = |l: f64| cq.normalize(&Length(l).micro());
+        match self {
+            RawStopCondition::CentroidCrossesLine {
+                point,
+                normal,
+                all,
+            } => StopCondition::CentroidCrossesLine {
+                point: V2d {
+                    x: length(point[0]),
+                    y: length(point[1]),
+                },
+                normal: V2d {
+                    x: normal[0],
+                    y: normal[1],
+                },
+                all: *all,
+            },
+            RawStopCondition::MaxPairwiseDist { dist } => {
+                StopCondition::MaxPairwiseDist {
+                    dist: length(*dist),
+                }
+            }
+            RawStopCondition::NoContactsFor { t } => {
+                StopCondition::NoContactsFor {
+                    t: cq.normalize(&Time(*t)),
+                }
+            }
+            RawStopCondition::WallClock { secs } => {
+                StopCondition::WallClock { secs: *secs }
+            }
+        }
+    }
+}
+
+/// Checks a simulation's stop conditions, and keeps track of the
+/// information needed to do so.
+pub struct StopConditions {
+    raw: Vec<RawStopCondition>,
+    refined: Vec<StopCondition>,
+    /// Time point at which cells were last seen in contact.
+    last_contact_t: f64,
+    /// Wall-clock time at which checking began.
+    started: Instant,
+}
+
+impl StopConditions {
+    pub fn new(
+        raw: &[RawStopCondition],
+        cq: &CharQuantities,
+        tpoint: f64,
+    ) -> StopConditions {
+        StopConditions {
+            raw: raw.to_vec(),
+            refined: raw.iter().map(|c| c.refine(cq)).collect(),
+            last_contact_t: tpoint,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn raw(&self) -> &[RawStopCondition] {
+        &self.raw
+    }
+
+    /// Check the stop conditions against the current state. Returns
+    /// a description of the first condition which is met, if any.
+    pub fn check<const N: usize>(
+        &mut self,
+        state: &WorldState<N>,
+        interaction_generator: &InteractionGenerator<N>,
+    ) -> Option<String> {
+        if self.refined.is_empty() {
+            return None;
+        }
+        let centroids = state
+            .cells
+            .cells
+            .iter()
+            .map(|c| calc_poly_centroid(&c.core.poly))
+            .collect::<Vec<V2d>>();
+        let num_cells = centroids.len();
+        if (0..num_cells).any(|ci| {
+            !interaction_generator
+                .get_physical_contacts(ci)
+                .is_empty()
+        }) {
+            self.last_contact_t = state.tpoint;
+        }
+        let met = self.refined.iter().position(|c| match c {
+            StopCondition::CentroidCrossesLine {
+                point,
+                normal,
+                all,
+            } => {
+                let mut crossed = centroids
+                    .iter()
+                    .map(|&c| (c - *point).dot(normal) > 0.0);
+                if *all {
+                    crossed.all(|x| x)
+                } else {
+                    crossed.any(|x| x)
+                }
+            }
+            StopCondition::MaxPairwiseDist { dist } => (0..num_cells)
+                .any(|ci| {
+                    ((ci + 1)..num_cells).any(|oci| {
+                        (centroids[ci] - centroids[oci]).mag() > *dist
+                    })
+                }),
+            StopCondition::NoContactsFor { t } => {
+                state.tpoint - self.last_contact_t >= *t
+            }
+            StopCondition::WallClock { secs } => {
+                self.started.elapsed().as_secs_f64() >= *secs
+            }
+        });
+        met.map(|ix| {
+            format!("stop condition met: {:?}", self.raw[ix])
+        })
+    }
+}